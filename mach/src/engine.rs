@@ -0,0 +1,247 @@
+use std::{
+	fmt,
+	ops::Neg,
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc,
+	},
+	time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{game::*, tablebase::Tablebase};
+
+pub mod eval;
+mod search;
+mod table;
+
+pub use self::eval::*;
+
+use self::search::Search;
+use self::table::TranspositionTable;
+
+/// The score of a checkmate on the board. Mates further away score less by one for each ply until they
+/// happen, so that the search prefers the quickest mate and the slowest loss.
+pub const MATE_SCORE: i32 = 32_000;
+/// The lowest score that still means a mate has been found, leaving room for mates up to this many plies
+/// away
+const MATE_THRESHOLD: i32 = MATE_SCORE - 1000;
+
+/// How good a position is for the side whose turn it is, in hundredths of a pawn or as a forced mate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize)]
+pub struct Score(pub i32);
+
+impl Score {
+	pub const DRAW: Score = Score(0);
+
+	pub fn centipawns(centipawns: i32) -> Self {
+		Score(centipawns)
+	}
+
+	/// The score of the side to move giving mate after the given number of plies
+	pub fn mate_in(plies: u32) -> Self {
+		Score(MATE_SCORE - plies as i32)
+	}
+
+	/// The score of the side to move being mated after the given number of plies
+	pub fn mated_in(plies: u32) -> Self {
+		Score(-MATE_SCORE + plies as i32)
+	}
+
+	pub fn is_mate(self) -> bool {
+		self.0.abs() >= MATE_THRESHOLD
+	}
+
+	/// The number of moves until mate if this score is a forced mate, positive if the side to move gives
+	/// mate and negative if it is mated
+	pub fn mate_moves(self) -> Option<i32> {
+		if !self.is_mate() {
+			return None;
+		}
+		let plies = MATE_SCORE - self.0.abs();
+		let moves = (plies + 1) / 2;
+		Some(if self.0 > 0 { moves } else { -moves })
+	}
+
+	/// The inverse of `mate_moves`, for reading the mates other engines report. Returns `None` if the mate
+	/// is too far away to be told apart from other scores.
+	pub fn from_mate_moves(moves: i32) -> Option<Self> {
+		let plies = moves.checked_abs()?.checked_mul(2)?;
+		if plies > MATE_SCORE - MATE_THRESHOLD {
+			return None;
+		}
+		Some(if moves > 0 {
+			Score::mate_in(plies as u32 - 1)
+		} else {
+			Score::mated_in(plies as u32)
+		})
+	}
+}
+
+impl Neg for Score {
+	type Output = Score;
+
+	fn neg(self) -> Score {
+		Score(-self.0)
+	}
+}
+
+impl fmt::Display for Score {
+	/// Write the score in pawns, such as `+1.25`, or as the number of moves until mate, such as `#3` or
+	/// `#-2`
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self.mate_moves() {
+			Some(moves) => write!(f, "#{}", moves),
+			None => write!(f, "{:+.2}", f64::from(self.0) / 100.0),
+		}
+	}
+}
+
+/// How long a search may run for. The search stops at whichever limit it reaches first, and runs until
+/// it is stopped if there are none.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct SearchLimits {
+	/// The number of plies to search to
+	pub depth: Option<u32>,
+	/// The number of positions to visit
+	pub nodes: Option<u64>,
+	/// The time to search for
+	pub time: Option<Duration>,
+}
+
+impl SearchLimits {
+	pub fn depth(depth: u32) -> Self {
+		Self {
+			depth: Some(depth),
+			..Self::default()
+		}
+	}
+
+	pub fn nodes(nodes: u64) -> Self {
+		Self {
+			nodes: Some(nodes),
+			..Self::default()
+		}
+	}
+
+	pub fn time(time: Duration) -> Self {
+		Self {
+			time: Some(time),
+			..Self::default()
+		}
+	}
+}
+
+/// What a search found, as of the last depth it completed
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SearchResult {
+	/// The best move for the side whose turn it is, or `None` if it has no legal moves
+	pub best_move: Option<Move>,
+	/// The score of the position for the side whose turn it is
+	pub score: Score,
+	/// The principal variation, which is the line of play expected from both sides starting with the
+	/// best move
+	pub pv: Vec<Move>,
+	/// The depth that was completed, in plies
+	pub depth: u32,
+	/// The number of positions visited
+	pub nodes: u64,
+	/// The time the search has taken
+	pub time: Duration,
+}
+
+/// A chess engine, which searches for the best move in a position. The transposition table is kept
+/// between searches, so searching the positions of one game one after another gets faster as it goes.
+pub struct Engine {
+	table: TranspositionTable,
+	stop: Arc<AtomicBool>,
+	/// Endgame tables the search looks positions up in, if it has been given any
+	tablebase: Option<Arc<Tablebase>>,
+}
+
+impl Engine {
+	/// The size of the transposition table of a new engine, in megabytes
+	pub const DEFAULT_HASH_SIZE: usize = 16;
+
+	pub fn new() -> Self {
+		Self::with_hash_size(Self::DEFAULT_HASH_SIZE)
+	}
+
+	/// Create an engine with a transposition table of about the given number of megabytes
+	pub fn with_hash_size(megabytes: usize) -> Self {
+		Self {
+			table: TranspositionTable::new(megabytes),
+			stop: Arc::new(AtomicBool::new(false)),
+			tablebase: None,
+		}
+	}
+
+	/// Replace the transposition table with an empty one of about the given number of megabytes
+	pub fn set_hash_size(&mut self, megabytes: usize) {
+		self.table = TranspositionTable::new(megabytes);
+	}
+
+	/// Forget everything learned in previous searches, such as when starting a new game
+	pub fn clear(&mut self) {
+		self.table.clear();
+	}
+
+	/// Use endgame tables to score the positions they cover exactly, or stop using them with `None`
+	pub fn set_tablebase(&mut self, tablebase: Option<Arc<Tablebase>>) {
+		self.tablebase = tablebase;
+	}
+
+	/// Get a flag that stops the running search as soon as it is set, from any thread. The search returns
+	/// what it found at the last depth it completed. The flag stays set until `reset_stop` is called.
+	pub fn stop_flag(&self) -> Arc<AtomicBool> {
+		self.stop.clone()
+	}
+
+	/// Clear the stop flag so that the next search runs to its limits. This has to be called before the
+	/// search is handed to another thread rather than on that thread, or a stop sent right after starting
+	/// it could be cleared before the search sees it.
+	pub fn reset_stop(&self) {
+		self.stop.store(false, Ordering::Relaxed);
+	}
+
+	/// Search a position for the best move by the standard rules
+	pub fn search(&mut self, game_state: &GameState, limits: SearchLimits) -> SearchResult {
+		self.search_with(game_state, &PositionHistory::new(game_state), limits, |_| {})
+	}
+
+	/// Search a position reached in a game with the given history, so that repeating earlier positions is
+	/// scored as a draw. `report` is called with the result of each depth as it completes.
+	pub fn search_with(
+		&mut self,
+		game_state: &GameState,
+		history: &PositionHistory,
+		limits: SearchLimits,
+		report: impl FnMut(&SearchResult),
+	) -> SearchResult {
+		let tablebase = self.tablebase.as_deref();
+		Search::new(&mut self.table, &self.stop, history.clone(), limits, tablebase).run(game_state, report)
+	}
+}
+
+impl Default for Engine {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[test]
+fn score_test() {
+	assert_eq!(Score::mate_in(1).mate_moves(), Some(1));
+	assert_eq!(Score::mate_in(3).mate_moves(), Some(2));
+	assert_eq!(Score::mated_in(2).mate_moves(), Some(-1));
+	assert_eq!(Score::centipawns(125).mate_moves(), None);
+	assert_eq!(Score::from_mate_moves(2), Some(Score::mate_in(3)));
+	assert_eq!(Score::from_mate_moves(-1), Some(Score::mated_in(2)));
+	assert_eq!(Score::from_mate_moves(i32::MIN), None);
+	assert_eq!(Score::from_mate_moves(1_000_000), None);
+	assert_eq!(Score::mate_in(5).to_string(), "#3");
+	assert_eq!(Score::centipawns(-40).to_string(), "-0.40");
+	assert!(Score::mate_in(7) > Score::mate_in(9));
+	assert_eq!(-Score::mate_in(4), Score::mated_in(4));
+}
@@ -0,0 +1,405 @@
+use std::fmt;
+
+use crate::{engine::Score, game::*};
+
+/// The value of each piece, in centipawns
+pub fn piece_value(piece: Piece) -> i32 {
+	match piece {
+		Piece::Pawn => 100,
+		Piece::Knight => 320,
+		Piece::Bishop => 330,
+		Piece::Rook => 500,
+		Piece::Queen => 900,
+		Piece::Archbishop => 825,
+		Piece::Chancellor => 875,
+		Piece::King => 0,
+	}
+}
+
+/// The bonus for keeping both bishops
+const BISHOP_PAIR: i32 = 30;
+/// The bonus for each square a piece other than a pawn or king attacks that is not taken by a piece of its
+/// own color
+const MOBILITY: [(Piece, i32); 6] = [
+	(Piece::Knight, 4),
+	(Piece::Bishop, 5),
+	(Piece::Rook, 2),
+	(Piece::Queen, 1),
+	(Piece::Archbishop, 2),
+	(Piece::Chancellor, 1),
+];
+const DOUBLED_PAWN: i32 = -15;
+const ISOLATED_PAWN: i32 = -15;
+/// The bonus for a passed pawn by how many rows it has advanced from its side's first row
+const PASSED_PAWN: [i32; 8] = [0, 5, 10, 20, 35, 60, 100, 0];
+/// The bonus for each pawn in front of the king, on its column or the ones next to it
+const PAWN_SHIELD: i32 = 10;
+/// The penalty for each attack by the opponent on the squares around the king
+const KING_ZONE_ATTACK: i32 = -6;
+/// The combined value of the knights, bishops, rooks and queens at the start of a game, used to tell how
+/// far from the endgame a position is
+const OPENING_MATERIAL: i32 = 2 * (2 * 320 + 2 * 330 + 2 * 500 + 900);
+
+/// Piece-square tables, giving a bonus for a piece of white standing on each square, written with the
+/// eighth row first so that they look like the board from white's side. Black uses them mirrored, and
+/// boards of other sizes are scaled to fit them.
+#[rustfmt::skip]
+const PAWN_SQUARES: [i32; 64] = [
+	 0,  0,   0,   0,   0,   0,  0,  0,
+	50, 50,  50,  50,  50,  50, 50, 50,
+	10, 10,  20,  30,  30,  20, 10, 10,
+	 5,  5,  10,  25,  25,  10,  5,  5,
+	 0,  0,   0,  20,  20,   0,  0,  0,
+	 5, -5, -10,   0,   0, -10, -5,  5,
+	 5, 10,  10, -20, -20,  10, 10,  5,
+	 0,  0,   0,   0,   0,   0,  0,  0,
+];
+
+#[rustfmt::skip]
+const KNIGHT_SQUARES: [i32; 64] = [
+	-50, -40, -30, -30, -30, -30, -40, -50,
+	-40, -20,   0,   0,   0,   0, -20, -40,
+	-30,   0,  10,  15,  15,  10,   0, -30,
+	-30,   5,  15,  20,  20,  15,   5, -30,
+	-30,   0,  15,  20,  20,  15,   0, -30,
+	-30,   5,  10,  15,  15,  10,   5, -30,
+	-40, -20,   0,   5,   5,   0, -20, -40,
+	-50, -40, -30, -30, -30, -30, -40, -50,
+];
+
+#[rustfmt::skip]
+const BISHOP_SQUARES: [i32; 64] = [
+	-20, -10, -10, -10, -10, -10, -10, -20,
+	-10,   0,   0,   0,   0,   0,   0, -10,
+	-10,   0,   5,  10,  10,   5,   0, -10,
+	-10,   5,   5,  10,  10,   5,   5, -10,
+	-10,   0,  10,  10,  10,  10,   0, -10,
+	-10,  10,  10,  10,  10,  10,  10, -10,
+	-10,   5,   0,   0,   0,   0,   5, -10,
+	-20, -10, -10, -10, -10, -10, -10, -20,
+];
+
+#[rustfmt::skip]
+const ROOK_SQUARES: [i32; 64] = [
+	 0,  0,  0,  0,  0,  0,  0,  0,
+	 5, 10, 10, 10, 10, 10, 10,  5,
+	-5,  0,  0,  0,  0,  0,  0, -5,
+	-5,  0,  0,  0,  0,  0,  0, -5,
+	-5,  0,  0,  0,  0,  0,  0, -5,
+	-5,  0,  0,  0,  0,  0,  0, -5,
+	-5,  0,  0,  0,  0,  0,  0, -5,
+	 0,  0,  0,  5,  5,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+const QUEEN_SQUARES: [i32; 64] = [
+	-20, -10, -10, -5, -5, -10, -10, -20,
+	-10,   0,   0,  0,  0,   0,   0, -10,
+	-10,   0,   5,  5,  5,   5,   0, -10,
+	 -5,   0,   5,  5,  5,   5,   0,  -5,
+	  0,   0,   5,  5,  5,   5,   0,  -5,
+	-10,   5,   5,  5,  5,   5,   0, -10,
+	-10,   0,   5,  0,  0,   0,   0, -10,
+	-20, -10, -10, -5, -5, -10, -10, -20,
+];
+
+/// Where the king should stand while there are still many pieces on the board, which is tucked away
+/// behind its pawns
+#[rustfmt::skip]
+const KING_SQUARES: [i32; 64] = [
+	-30, -40, -40, -50, -50, -40, -40, -30,
+	-30, -40, -40, -50, -50, -40, -40, -30,
+	-30, -40, -40, -50, -50, -40, -40, -30,
+	-30, -40, -40, -50, -50, -40, -40, -30,
+	-20, -30, -30, -40, -40, -30, -30, -20,
+	-10, -20, -20, -20, -20, -20, -20, -10,
+	 20,  20,   0,   0,   0,   0,  20,  20,
+	 20,  30,  10,   0,   0,  10,  30,  20,
+];
+
+/// Where the king should stand in the endgame, which is in the center where it can help its pawns
+#[rustfmt::skip]
+const KING_ENDGAME_SQUARES: [i32; 64] = [
+	-50, -40, -30, -20, -20, -30, -40, -50,
+	-30, -20, -10,   0,   0, -10, -20, -30,
+	-30, -10,  20,  30,  30,  20, -10, -30,
+	-30, -10,  30,  40,  40,  30, -10, -30,
+	-30, -10,  30,  40,  40,  30, -10, -30,
+	-30, -10,  20,  30,  30,  20, -10, -30,
+	-30, -30,   0,   0,   0,   0, -30, -30,
+	-50, -30, -30, -30, -30, -30, -30, -50,
+];
+
+/// The static evaluation of a position, split into the terms it is made of. Every term is in
+/// centipawns from white's point of view, so a positive term favors white and a negative one black.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Evaluation {
+	/// The value of each side's pieces, with a bonus for keeping both bishops
+	pub material: i32,
+	/// How well placed each piece is on its square
+	pub piece_squares: i32,
+	/// How many squares each side's pieces can move to
+	pub mobility: i32,
+	/// Doubled, isolated and passed pawns
+	pub pawn_structure: i32,
+	/// The pawns sheltering each king and the attacks on the squares around it, which matter less as
+	/// pieces come off the board
+	pub king_safety: i32,
+}
+
+impl Evaluation {
+	/// Evaluate a position without searching it. This does not check whether the game is over; use
+	/// `evaluate` for that.
+	pub fn new(game_state: &GameState) -> Self {
+		let board = BitGameBoard::from(&game_state.board);
+		let phase = middlegame_phase(&board);
+		let mut evaluation = Evaluation::default();
+		for &color in &[Color::White, Color::Black] {
+			let sign = match color {
+				Color::White => 1,
+				Color::Black => -1,
+			};
+			evaluation.material += sign * material(&board, color);
+			evaluation.piece_squares += sign * piece_squares(&board, color, phase);
+			evaluation.mobility += sign * mobility(&board, color);
+			evaluation.pawn_structure += sign * pawn_structure(&board, color);
+			evaluation.king_safety += sign * king_safety(&board, color) * phase / OPENING_MATERIAL;
+		}
+		evaluation
+	}
+
+	/// The terms of the evaluation along with their names
+	pub fn terms(&self) -> [(&'static str, i32); 5] {
+		[
+			("Material", self.material),
+			("Piece squares", self.piece_squares),
+			("Mobility", self.mobility),
+			("Pawn structure", self.pawn_structure),
+			("King safety", self.king_safety),
+		]
+	}
+
+	/// The sum of every term, from white's point of view
+	pub fn total(&self) -> i32 {
+		self.terms().iter().map(|&(_, value)| value).sum()
+	}
+
+	/// The score of the evaluation for the given side
+	pub fn score(&self, color: Color) -> Score {
+		match color {
+			Color::White => Score(self.total()),
+			Color::Black => Score(-self.total()),
+		}
+	}
+}
+
+impl fmt::Display for Evaluation {
+	/// Write each term on its own line, followed by the total, in pawns from white's point of view
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		for &(name, value) in self.terms().iter() {
+			writeln!(f, "{:<16}{}", name, Score(value))?;
+		}
+		write!(f, "{:<16}{}", "Total", Score(self.total()))
+	}
+}
+
+/// Score a position for the side whose turn it is without searching it. A side that is checkmated scores
+/// as mated, and stalemate and positions without enough material to mate score as draws.
+pub fn evaluate(game_state: &GameState) -> Score {
+	if !game_state.has_legal_moves() {
+		return if game_state.is_check() {
+			Score::mated_in(0)
+		} else {
+			Score::DRAW
+		};
+	}
+	if game_state.is_insufficient_material() {
+		return Score::DRAW;
+	}
+	Evaluation::new(game_state).score(game_state.turn)
+}
+
+/// How far the position is from the endgame, from `OPENING_MATERIAL` when every piece is on the board
+/// down to 0 when only kings and pawns are left
+fn middlegame_phase(board: &BitGameBoard) -> i32 {
+	let phase: i32 = board
+		.pieces()
+		.filter(|&(_, piece)| piece.piece != Piece::Pawn)
+		.map(|(_, piece)| piece_value(piece.piece))
+		.sum();
+	phase.min(OPENING_MATERIAL)
+}
+
+fn material(board: &BitGameBoard, color: Color) -> i32 {
+	let value: i32 = board
+		.pieces()
+		.filter(|&(_, piece)| piece.color == color)
+		.map(|(_, piece)| piece_value(piece.piece))
+		.sum();
+	let bishops = board.piece_set(GamePiece::new(Piece::Bishop, color)).count();
+	value + if bishops >= 2 { BISHOP_PAIR } else { 0 }
+}
+
+/// Look up the piece-square table entry of a square for the given color, on a board of the given size
+fn square_bonus(table: &[i32; 64], index: BoardIndex, color: Color, size: BoardSize) -> i32 {
+	let column = u32::from(index.column) * 8 / size.columns;
+	let row = match color {
+		Color::White => size.rows - 1 - u32::from(index.row),
+		Color::Black => u32::from(index.row),
+	} * 8 / size.rows;
+	table[(row * 8 + column) as usize]
+}
+
+fn piece_squares(board: &BitGameBoard, color: Color, phase: i32) -> i32 {
+	let size = board.size();
+	board
+		.pieces()
+		.filter(|&(_, piece)| piece.color == color)
+		.map(|(index, piece)| match piece.piece {
+			Piece::Pawn => square_bonus(&PAWN_SQUARES, index, color, size),
+			Piece::Knight => square_bonus(&KNIGHT_SQUARES, index, color, size),
+			Piece::Bishop => square_bonus(&BISHOP_SQUARES, index, color, size),
+			Piece::Rook => square_bonus(&ROOK_SQUARES, index, color, size),
+			// The archbishop and chancellor want to be central and active like the queen
+			Piece::Queen | Piece::Archbishop | Piece::Chancellor => square_bonus(&QUEEN_SQUARES, index, color, size),
+			// The king moves from its middlegame squares to its endgame squares as pieces are traded
+			Piece::King => {
+				let middlegame = square_bonus(&KING_SQUARES, index, color, size);
+				let endgame = square_bonus(&KING_ENDGAME_SQUARES, index, color, size);
+				(middlegame * phase + endgame * (OPENING_MATERIAL - phase)) / OPENING_MATERIAL
+			}
+		})
+		.sum()
+}
+
+fn mobility(board: &BitGameBoard, color: Color) -> i32 {
+	let own = board.color_set(color);
+	MOBILITY
+		.iter()
+		.map(|&(piece, bonus)| {
+			let piece = GamePiece::new(piece, color);
+			board
+				.piece_set(piece)
+				.iter()
+				.map(|index| (board.attacks(index, piece) & !own).count() as i32 * bonus)
+				.sum::<i32>()
+		})
+		.sum()
+}
+
+fn pawn_structure(board: &BitGameBoard, color: Color) -> i32 {
+	let size = board.size();
+	let columns = size.columns as i32;
+	let pawns = board.piece_set(GamePiece::new(Piece::Pawn, color));
+	let enemy_pawns = board.piece_set(GamePiece::new(Piece::Pawn, color.opposite()));
+	let column_count = |set: Bitboard, column: i32| {
+		if (0..columns).contains(&column) {
+			set.iter()
+				.filter(|index| u32::from(index.column) as i32 == column)
+				.count() as i32
+		} else {
+			0
+		}
+	};
+
+	let mut score = 0;
+	for column in 0..columns {
+		let count = column_count(pawns, column);
+		if count > 1 {
+			score += DOUBLED_PAWN * (count - 1);
+		}
+		if count > 0 && column_count(pawns, column - 1) == 0 && column_count(pawns, column + 1) == 0 {
+			score += ISOLATED_PAWN * count;
+		}
+	}
+
+	// A pawn is passed when no pawn of the opponent stands in front of it on its own or a neighboring
+	// column, so that nothing but pieces can stop it from promoting
+	for pawn in pawns.iter() {
+		let column = u32::from(pawn.column) as i32;
+		let row = u32::from(pawn.row) as i32;
+		let blocked = enemy_pawns.iter().any(|enemy| {
+			let enemy_row = u32::from(enemy.row) as i32;
+			(u32::from(enemy.column) as i32 - column).abs() <= 1 && (enemy_row - row) * color.pawn_direction() > 0
+		});
+		if !blocked {
+			let advanced = match color {
+				Color::White => row,
+				Color::Black => size.rows as i32 - 1 - row,
+			};
+			score += PASSED_PAWN[(advanced * 8 / size.rows as i32) as usize];
+		}
+	}
+	score
+}
+
+fn king_safety(board: &BitGameBoard, color: Color) -> i32 {
+	let king = match board.find_king(color) {
+		Some(king) => king,
+		None => return 0,
+	};
+	let size = board.size();
+	let pawn = GamePiece::new(Piece::Pawn, color);
+	let mut score = 0;
+	for columns in -1..=1 {
+		for rows in 1..=2 {
+			if let Some(index) = size.offset(king, columns, rows * color.pawn_direction()) {
+				if board.get_board_index(index) == Some(pawn) {
+					score += PAWN_SHIELD;
+				}
+			}
+		}
+	}
+
+	let zone = KING_OFFSETS
+		.iter()
+		.filter_map(|&(columns, rows)| size.offset(king, columns, rows))
+		.fold(Bitboard::from_index(king), |zone, index| {
+			zone | Bitboard::from_index(index)
+		});
+	let attacks: u32 = board
+		.pieces()
+		.filter(|&(_, piece)| piece.color != color && piece.piece != Piece::King)
+		.map(|(index, piece)| (board.attacks(index, piece) & zone).count())
+		.sum();
+	score + KING_ZONE_ATTACK * attacks as i32
+}
+
+#[test]
+fn evaluate_test() {
+	use crate::variant::{CAPABLANCA_FEN, GRAND_FEN, MINICHESS_FEN};
+
+	// The starting position is symmetrical, so every term cancels out
+	let start = GameState::standard();
+	assert_eq!(Evaluation::new(&start), Evaluation::default());
+	assert_eq!(evaluate(&start), Score::DRAW);
+	for &fen in &[CAPABLANCA_FEN, GRAND_FEN, MINICHESS_FEN] {
+		let start = GameState::from_fen(fen).unwrap();
+		assert_eq!(Evaluation::new(&start), Evaluation::default(), "{}", fen);
+	}
+
+	// An extra queen outweighs everything else, and the score is from the side to move
+	let game_state = GameState::from_fen("4k3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+	let evaluation = Evaluation::new(&game_state);
+	assert_eq!(evaluation.material, 900);
+	assert!(evaluate(&game_state) > Score::centipawns(800));
+	let game_state = GameState::from_fen("4k3/8/8/8/8/8/8/3QK3 b - - 0 1").unwrap();
+	assert!(evaluate(&game_state) < Score::centipawns(-800));
+
+	// Doubled and isolated pawns are weaknesses, and a passed pawn is a strength
+	let game_state = GameState::from_fen("4k3/pp6/8/8/8/2P5/2P5/4K3 w - - 0 1").unwrap();
+	assert!(Evaluation::new(&game_state).pawn_structure < 0);
+	let game_state = GameState::from_fen("4k3/8/1P6/8/8/6p1/8/4K3 w - - 0 1").unwrap();
+	assert_eq!(Evaluation::new(&game_state).pawn_structure, 0);
+	let game_state = GameState::from_fen("4k3/8/1P6/8/8/8/8/4K3 w - - 0 1").unwrap();
+	assert_eq!(
+		Evaluation::new(&game_state).pawn_structure,
+		PASSED_PAWN[5] + ISOLATED_PAWN
+	);
+
+	// Checkmate and stalemate end the game
+	let mated = GameState::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1").unwrap();
+	assert_eq!(evaluate(&mated), Score::mated_in(0));
+	let stalemate = GameState::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+	assert_eq!(evaluate(&stalemate), Score::DRAW);
+}
@@ -0,0 +1,397 @@
+use std::{
+	sync::atomic::{AtomicBool, Ordering},
+	time::Instant,
+};
+
+use crate::{
+	engine::{piece_value, table::*, Evaluation, Score, SearchLimits, SearchResult, MATE_SCORE, MATE_THRESHOLD},
+	game::*,
+	tablebase::Tablebase,
+};
+
+/// The deepest the search goes below the root, including extensions and the quiescence search
+const MAX_PLY: u32 = 128;
+/// A score out of the range of every real score
+const INFINITY: i32 = MATE_SCORE + 1;
+/// How many positions are visited between checks of the clock and the stop flag
+const CHECK_INTERVAL: u64 = 2048;
+
+/// A single search of a position, run by iterative deepening: the position is searched to a depth of one
+/// ply, then two, and so on, each depth ordering moves by what the previous ones found until a limit is
+/// reached
+pub(crate) struct Search<'a> {
+	table: &'a mut TranspositionTable,
+	stop: &'a AtomicBool,
+	/// The positions of the game and of the line being searched, to score repetitions as draws
+	history: PositionHistory,
+	limits: SearchLimits,
+	start: Instant,
+	nodes: u64,
+	aborted: bool,
+	/// Two quiet moves at each ply that recently caused a cutoff, which are tried early in sibling
+	/// positions
+	killers: Vec<[Option<Move>; 2]>,
+	/// Tables to look up the exact score of endgame positions in, instead of searching them
+	tablebase: Option<&'a Tablebase>,
+}
+
+impl<'a> Search<'a> {
+	pub fn new(
+		table: &'a mut TranspositionTable,
+		stop: &'a AtomicBool,
+		history: PositionHistory,
+		limits: SearchLimits,
+		tablebase: Option<&'a Tablebase>,
+	) -> Self {
+		Self {
+			table,
+			stop,
+			history,
+			limits,
+			start: Instant::now(),
+			nodes: 0,
+			aborted: false,
+			killers: vec![[None; 2]; MAX_PLY as usize + 1],
+			tablebase,
+		}
+	}
+
+	pub fn run(mut self, game_state: &GameState, mut report: impl FnMut(&SearchResult)) -> SearchResult {
+		let moves = game_state.legal_moves();
+		let mut result = SearchResult {
+			best_move: moves.first().copied(),
+			score: if moves.is_empty() && game_state.is_check() {
+				Score::mated_in(0)
+			} else {
+				Score::DRAW
+			},
+			pv: moves.first().copied().into_iter().collect(),
+			depth: 0,
+			nodes: 0,
+			time: self.start.elapsed(),
+		};
+		if moves.is_empty() {
+			return result;
+		}
+
+		let mut state = game_state.clone();
+		let max_depth = self.limits.depth.unwrap_or(MAX_PLY).clamp(1, MAX_PLY);
+		for depth in 1..=max_depth {
+			let mut pv = Vec::new();
+			let score = self.negamax(&mut state, depth, 0, -INFINITY, INFINITY, &mut pv);
+			if self.aborted {
+				break;
+			}
+			result = SearchResult {
+				best_move: pv.first().copied(),
+				score: Score(score),
+				pv,
+				depth,
+				nodes: self.nodes,
+				time: self.start.elapsed(),
+			};
+			report(&result);
+
+			// A mate found within the depth searched can not be bettered, and a depth that took more than
+			// half of the time left is not likely to be followed by another that finishes in time
+			let mate_found = result.score.is_mate() && MATE_SCORE - score.abs() <= depth as i32;
+			let out_of_time = self.limits.time.is_some_and(|time| result.time * 2 > time);
+			if mate_found || out_of_time {
+				break;
+			}
+		}
+		result.nodes = self.nodes;
+		result.time = self.start.elapsed();
+		result
+	}
+
+	/// Check whether the search has to stop, because a limit has been reached or it was stopped from
+	/// outside
+	fn should_stop(&mut self) -> bool {
+		if self.limits.nodes.is_some_and(|nodes| self.nodes >= nodes) {
+			self.aborted = true;
+		} else if self.nodes.is_multiple_of(CHECK_INTERVAL) {
+			let out_of_time = self.limits.time.is_some_and(|time| self.start.elapsed() >= time);
+			self.aborted = out_of_time || self.stop.load(Ordering::Relaxed);
+		}
+		self.aborted
+	}
+
+	/// Search a position with alpha-beta pruning to the given depth, filling `pv` with the best line found
+	/// from it. Returns the score of the position for the side whose turn it is, which is only exact if it
+	/// falls between `alpha` and `beta`.
+	fn negamax(
+		&mut self,
+		state: &mut GameState,
+		depth: u32,
+		ply: u32,
+		mut alpha: i32,
+		beta: i32,
+		pv: &mut Vec<Move>,
+	) -> i32 {
+		pv.clear();
+		if self.should_stop() {
+			return 0;
+		}
+		if ply > 0
+			&& (state.halfmove_clock >= FIFTY_MOVE_RULE
+				|| self.history.repetitions(state) >= 2
+				|| state.is_insufficient_material())
+		{
+			return 0;
+		}
+		if let Some(dtm) = self
+			.tablebase
+			.filter(|_| ply > 0)
+			.and_then(|tablebase| tablebase.probe(state))
+		{
+			self.nodes += 1;
+			return score_from_table(dtm.score().0, ply);
+		}
+		if depth == 0 || ply >= MAX_PLY {
+			return self.quiescence(state, ply, alpha, beta);
+		}
+		self.nodes += 1;
+
+		let key = state.zobrist_key();
+		let entry = self.table.get(key);
+		if let Some(entry) = entry.filter(|entry| ply > 0 && entry.depth >= depth) {
+			let score = score_from_table(entry.score, ply);
+			match entry.bound {
+				Bound::Exact => return score,
+				Bound::Lower if score >= beta => return score,
+				Bound::Upper if score <= alpha => return score,
+				_ => {}
+			}
+		}
+
+		let in_check = state.is_check();
+		let mut moves = state.legal_moves();
+		if moves.is_empty() {
+			return if in_check { -MATE_SCORE + ply as i32 } else { 0 };
+		}
+		self.order_moves(state, &mut moves, entry.and_then(|entry| entry.best_move), ply);
+
+		let original_alpha = alpha;
+		let mut best = -INFINITY;
+		let mut best_move = None;
+		let mut line = Vec::new();
+		for mv in moves {
+			let quiet = !is_capture(state, mv) && mv.promotion.is_none();
+			let outcome = state.apply_move_unchecked(mv);
+			self.history.push(state);
+			// Looking one ply further after a check keeps the search from stopping in the middle of an attack
+			let extension = if state.is_check() { 1 } else { 0 };
+			let score = -self.negamax(state, depth - 1 + extension, ply + 1, -beta, -alpha, &mut line);
+			self.history.pop();
+			state.undo_move(mv, outcome);
+			if self.aborted {
+				return 0;
+			}
+
+			if score > best {
+				best = score;
+				best_move = Some(mv);
+				if score > alpha {
+					alpha = score;
+					pv.clear();
+					pv.push(mv);
+					pv.extend_from_slice(&line);
+				}
+			}
+			if alpha >= beta {
+				if quiet {
+					let killers = &mut self.killers[ply as usize];
+					if killers[0] != Some(mv) {
+						killers[1] = killers[0];
+						killers[0] = Some(mv);
+					}
+				}
+				break;
+			}
+		}
+
+		let bound = if best >= beta {
+			Bound::Lower
+		} else if best > original_alpha {
+			Bound::Exact
+		} else {
+			Bound::Upper
+		};
+		self.table.store(TableEntry {
+			key,
+			depth,
+			score: score_to_table(best, ply),
+			bound,
+			best_move,
+		});
+		best
+	}
+
+	/// Search only captures and promotions until the position is quiet, so that positions are not scored
+	/// in the middle of an exchange of pieces
+	fn quiescence(&mut self, state: &mut GameState, ply: u32, mut alpha: i32, beta: i32) -> i32 {
+		if self.should_stop() {
+			return 0;
+		}
+		self.nodes += 1;
+
+		let stand_pat = Evaluation::new(state).score(state.turn).0;
+		if stand_pat >= beta || ply >= MAX_PLY {
+			return stand_pat;
+		}
+		alpha = alpha.max(stand_pat);
+
+		let mut moves: Vec<Move> = state
+			.legal_moves()
+			.into_iter()
+			.filter(|&mv| is_capture(state, mv) || mv.promotion.is_some())
+			.collect();
+		self.order_moves(state, &mut moves, None, ply);
+		for mv in moves {
+			let outcome = state.apply_move_unchecked(mv);
+			let score = -self.quiescence(state, ply + 1, -beta, -alpha);
+			state.undo_move(mv, outcome);
+			if self.aborted {
+				return 0;
+			}
+			if score >= beta {
+				return score;
+			}
+			alpha = alpha.max(score);
+		}
+		alpha
+	}
+
+	/// Sort moves so that the ones most likely to be best are searched first: the best move found for the
+	/// position before, then captures of valuable pieces by cheap ones, promotions, and the killer moves
+	fn order_moves(&self, state: &GameState, moves: &mut [Move], best_move: Option<Move>, ply: u32) {
+		let killers = self.killers[ply as usize];
+		moves.sort_by_cached_key(|&mv| {
+			let priority = if Some(mv) == best_move {
+				1_000_000
+			} else if is_capture(state, mv) {
+				let victim = state
+					.board
+					.get_board_index(mv.end)
+					.map_or(Piece::Pawn, |piece| piece.piece);
+				let attacker = state
+					.board
+					.get_board_index(mv.start)
+					.map_or(Piece::Pawn, |piece| piece.piece);
+				100_000 + 10 * piece_value(victim) - piece_value(attacker)
+			} else if let Some(promotion) = mv.promotion {
+				90_000 + piece_value(promotion)
+			} else if killers.contains(&Some(mv)) {
+				80_000
+			} else {
+				0
+			};
+			-priority
+		});
+	}
+}
+
+/// Check whether a move captures a piece, including capturing en passant
+fn is_capture(state: &GameState, mv: Move) -> bool {
+	if mv.is_drop() {
+		return false;
+	}
+	match state.board.get_board_index(mv.end) {
+		Some(target) => state
+			.board
+			.get_board_index(mv.start)
+			.is_some_and(|piece| piece.color != target.color),
+		None => Some(mv.end) == state.en_passant,
+	}
+}
+
+/// Mate scores count plies from the root of the search, but a table entry can be found at any ply, so
+/// they are stored counting from the position of the entry instead
+fn score_to_table(score: i32, ply: u32) -> i32 {
+	if score >= MATE_THRESHOLD {
+		score + ply as i32
+	} else if score <= -MATE_THRESHOLD {
+		score - ply as i32
+	} else {
+		score
+	}
+}
+
+fn score_from_table(score: i32, ply: u32) -> i32 {
+	if score >= MATE_THRESHOLD {
+		score - ply as i32
+	} else if score <= -MATE_THRESHOLD {
+		score + ply as i32
+	} else {
+		score
+	}
+}
+
+#[test]
+fn search_test() {
+	use crate::engine::Engine;
+
+	let mut engine = Engine::new();
+
+	// A rook on the back rank mates at once
+	let game_state = GameState::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+	let result = engine.search(&game_state, SearchLimits::depth(3));
+	assert_eq!(result.best_move, Move::from_uci("a1a8"));
+	assert_eq!(result.score, Score::mate_in(1));
+
+	// Two rooks mate in two moves
+	let game_state = GameState::from_fen("7k/8/8/8/8/8/R7/1R5K w - - 0 1").unwrap();
+	let result = engine.search(&game_state, SearchLimits::depth(4));
+	assert_eq!(result.score.mate_moves(), Some(2));
+	let mut after = game_state.clone();
+	for &mv in &result.pv {
+		after.apply_move(mv).unwrap();
+	}
+	assert!(after.is_checkmate());
+
+	// An undefended queen is taken
+	let game_state = GameState::from_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1").unwrap();
+	let result = engine.search(&game_state, SearchLimits::depth(4));
+	assert_eq!(result.best_move, Move::from_uci("d2d5"));
+
+	// Stalemate has no best move
+	let game_state = GameState::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+	let result = engine.search(&game_state, SearchLimits::depth(3));
+	assert_eq!((result.best_move, result.score), (None, Score::DRAW));
+
+	// Boards of other sizes are searched the same way
+	let game_state = GameState::from_fen("k4/5/1K3/5/4R w - - 0 1").unwrap();
+	let result = engine.search(&game_state, SearchLimits::depth(3));
+	assert_eq!(result.best_move, Move::from_uci("e1e5"));
+	assert_eq!(result.score, Score::mate_in(1));
+}
+
+#[test]
+fn search_limits_test() {
+	use crate::engine::Engine;
+
+	let mut engine = Engine::new();
+	let game_state = GameState::standard();
+	let result = engine.search(&game_state, SearchLimits::nodes(5000));
+	assert!(result.nodes <= 5000);
+	assert!(game_state.is_legal(result.best_move.unwrap()));
+
+	let mut depths = Vec::new();
+	let history = PositionHistory::new(&game_state);
+	let result = engine.search_with(&game_state, &history, SearchLimits::depth(3), |result| {
+		depths.push(result.depth)
+	});
+	assert_eq!(depths, [1, 2, 3]);
+	assert_eq!(result.depth, 3);
+
+	// A stop requested before the search starts is kept until it is reset, and the search still returns
+	// a move
+	engine.stop_flag().store(true, Ordering::Relaxed);
+	let result = engine.search(&game_state, SearchLimits::default());
+	assert_eq!(result.depth, 0);
+	assert!(result.best_move.is_some());
+	engine.reset_stop();
+	let result = engine.search(&game_state, SearchLimits::depth(2));
+	assert_eq!(result.depth, 2);
+}
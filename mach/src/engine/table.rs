@@ -0,0 +1,69 @@
+use std::mem;
+
+use crate::game::*;
+
+/// How the score of a table entry relates to the true score of its position, which depends on whether
+/// the search of the position was cut off
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Bound {
+	/// The score is exact
+	Exact,
+	/// The true score is at least the score, as a move was found that was too good for the opponent to
+	/// allow
+	Lower,
+	/// The true score is at most the score, as no move reached the score the side to move was already
+	/// guaranteed
+	Upper,
+}
+
+/// What was learned about a position by searching it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct TableEntry {
+	pub key: u64,
+	pub depth: u32,
+	pub score: i32,
+	pub bound: Bound,
+	pub best_move: Option<Move>,
+}
+
+/// A hash table of searched positions indexed by their Zobrist keys, so that a position reached again by
+/// a different move order does not have to be searched again, and the best move found for it last time
+/// can be tried first
+pub(crate) struct TranspositionTable {
+	entries: Vec<Option<TableEntry>>,
+}
+
+impl TranspositionTable {
+	/// Create an empty table of about the given number of megabytes. The number of entries is rounded
+	/// down to a power of two.
+	pub fn new(megabytes: usize) -> Self {
+		let count = (megabytes * 1024 * 1024 / mem::size_of::<Option<TableEntry>>()).max(1);
+		let count = 1 << (usize::BITS - 1 - count.leading_zeros());
+		Self {
+			entries: vec![None; count],
+		}
+	}
+
+	pub fn clear(&mut self) {
+		self.entries.iter_mut().for_each(|entry| *entry = None);
+	}
+
+	fn index(&self, key: u64) -> usize {
+		key as usize & (self.entries.len() - 1)
+	}
+
+	/// Look up the entry of the position with the given key
+	pub fn get(&self, key: u64) -> Option<TableEntry> {
+		self.entries[self.index(key)].filter(|entry| entry.key == key)
+	}
+
+	/// Store an entry, replacing an entry of another position in the same slot, or one of the same
+	/// position searched to a lower depth
+	pub fn store(&mut self, entry: TableEntry) {
+		let index = self.index(entry.key);
+		let slot = &mut self.entries[index];
+		if slot.is_none_or(|previous| previous.key != entry.key || previous.depth <= entry.depth) {
+			*slot = Some(entry);
+		}
+	}
+}
@@ -1,44 +1,365 @@
-use serde::{Deserialize, Serialize};
+use std::{
+	fmt,
+	hash::{Hash, Hasher},
+};
 
-pub const SIZE: u32 = 8;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub mod apply;
+pub mod bitboard;
+pub mod chess960;
+pub mod draw;
+pub mod fen;
+pub mod movegen;
+pub mod perft;
+pub mod pocket;
+pub mod record;
+pub mod san;
+pub mod status;
+pub mod zobrist;
+
+pub use self::apply::*;
+pub use self::bitboard::*;
+pub use self::chess960::*;
+pub use self::draw::*;
+pub use self::fen::*;
+pub use self::movegen::*;
+pub use self::pocket::*;
+pub use self::record::*;
+pub use self::san::*;
+pub use self::status::*;
+pub use self::zobrist::*;
+
+/// The most columns or rows a board can have
+pub const MAX_SIZE: u32 = 10;
+
+/// The state of a game at one point in time. The Zobrist key is derived from the other fields, so it is
+/// neither compared, hashed nor serialized, and is recomputed when a game state is deserialized.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(remote = "Self")]
 pub struct GameState {
 	pub board: GameBoard,
 	pub turn: Color,
+	pub castling: CastlingRights,
+	/// The square a pawn skipped over with a double push on the previous move, if any
+	pub en_passant: Option<BoardIndex>,
+	/// The number of moves since the last capture or pawn move
+	pub halfmove_clock: u32,
+	/// The number of the current full move, starting at 1 and incremented after each of black's moves
+	pub fullmove_number: u32,
+	/// Whether castling follows the Chess960 rules, in which castling moves are written as the king
+	/// capturing its own rook
+	pub chess960: bool,
+	/// The columns of the rooks each side castles with
+	pub castling_columns: CastlingColumns,
+	/// The number of times each side has given check, counted only by variants that need it such as
+	/// Three-check
+	#[serde(default)]
+	pub checks: CheckCount,
+	/// The pieces each side holds in hand and may drop onto the board, which only variants such as
+	/// Crazyhouse fill
+	#[serde(default)]
+	pub pockets: Pockets,
+	/// The squares of pieces that were promoted from pawns, which Crazyhouse tracks because they return to
+	/// being pawns when captured
+	#[serde(default)]
+	pub promoted: Bitboard,
+	/// The Zobrist key of the game state, kept up to date as moves are applied and undone
+	#[serde(skip)]
+	zobrist: u64,
 }
 
 impl GameState {
+	/// Create a game state with an empty board and white to move
 	pub fn new() -> Self {
 		Self {
 			board: GameBoard::new(),
 			turn: Color::White,
+			castling: CastlingRights::none(),
+			en_passant: None,
+			halfmove_clock: 0,
+			fullmove_number: 1,
+			chess960: false,
+			castling_columns: CastlingColumns::standard(),
+			checks: CheckCount::default(),
+			pockets: Pockets::default(),
+			promoted: Bitboard::EMPTY,
+			zobrist: 0,
+		}
+	}
+
+	/// Create a game state with an empty board of the given size and white to move
+	pub fn with_size(size: BoardSize) -> Self {
+		Self {
+			board: GameBoard::with_size(size),
+			castling_columns: CastlingColumns::both(size.last_column(), Column::A),
+			..Self::new()
+		}
+	}
+
+	/// Create a game state for the start of a standard game
+	pub fn standard() -> Self {
+		let mut game_state = Self::new();
+		game_state.board.set_standard();
+		game_state.castling = CastlingRights::all();
+		game_state.refresh_zobrist_key();
+		game_state
+	}
+
+	/// How pawns advance and promote in this game state, which follows from the size of the board
+	pub fn pawn_rules(&self) -> PawnRules {
+		PawnRules::for_size(self.board.size())
+	}
+
+	/// Count a check given by the given color, keeping the Zobrist key up to date
+	pub fn add_check(&mut self, color: Color) {
+		let count = self.checks.get_mut(color);
+		*count += 1;
+		self.zobrist ^= check_key(color, *count);
+	}
+
+	/// Take back a check counted with `add_check`, keeping the Zobrist key up to date
+	pub fn remove_check(&mut self, color: Color) {
+		let count = self.checks.get_mut(color);
+		if *count > 0 {
+			self.zobrist ^= check_key(color, *count);
+			*count -= 1;
+		}
+	}
+}
+
+impl Default for GameState {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl PartialEq for GameState {
+	fn eq(&self, other: &Self) -> bool {
+		self.board == other.board
+			&& self.turn == other.turn
+			&& self.castling == other.castling
+			&& self.en_passant == other.en_passant
+			&& self.halfmove_clock == other.halfmove_clock
+			&& self.fullmove_number == other.fullmove_number
+			&& self.chess960 == other.chess960
+			&& self.castling_columns == other.castling_columns
+			&& self.checks == other.checks
+			&& self.pockets == other.pockets
+			&& self.promoted == other.promoted
+	}
+}
+
+impl Eq for GameState {}
+
+impl Serialize for GameState {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		GameState::serialize(self, serializer)
+	}
+}
+
+impl<'de> Deserialize<'de> for GameState {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let mut game_state = GameState::deserialize(deserializer)?;
+		game_state.refresh_zobrist_key();
+		Ok(game_state)
+	}
+}
+
+impl Hash for GameState {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.board.hash(state);
+		self.turn.hash(state);
+		self.castling.hash(state);
+		self.en_passant.hash(state);
+		self.halfmove_clock.hash(state);
+		self.fullmove_number.hash(state);
+		self.chess960.hash(state);
+		self.castling_columns.hash(state);
+		self.checks.hash(state);
+		self.pockets.hash(state);
+		self.promoted.hash(state);
+	}
+}
+
+/// The columns of the rooks that each side castles with on each side of its king. These are always `H` and
+/// `A` in standard chess and the corners of the board in Capablanca chess, but depend on the starting
+/// position in Chess960, and a position set up from a FEN may give each side different ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CastlingColumns {
+	pub white_kingside: Column,
+	pub white_queenside: Column,
+	pub black_kingside: Column,
+	pub black_queenside: Column,
+}
+
+impl CastlingColumns {
+	pub fn standard() -> Self {
+		Self::both(Column::H, Column::A)
+	}
+
+	/// Castle with the rooks on the same columns for both sides
+	pub fn both(kingside: Column, queenside: Column) -> Self {
+		Self {
+			white_kingside: kingside,
+			white_queenside: queenside,
+			black_kingside: kingside,
+			black_queenside: queenside,
+		}
+	}
+
+	pub fn kingside(self, color: Color) -> Column {
+		match color {
+			Color::White => self.white_kingside,
+			Color::Black => self.black_kingside,
+		}
+	}
+
+	pub fn queenside(self, color: Color) -> Column {
+		match color {
+			Color::White => self.white_queenside,
+			Color::Black => self.black_queenside,
+		}
+	}
+
+	pub fn kingside_mut(&mut self, color: Color) -> &mut Column {
+		match color {
+			Color::White => &mut self.white_kingside,
+			Color::Black => &mut self.black_kingside,
+		}
+	}
+
+	pub fn queenside_mut(&mut self, color: Color) -> &mut Column {
+		match color {
+			Color::White => &mut self.white_queenside,
+			Color::Black => &mut self.black_queenside,
+		}
+	}
+}
+
+impl Default for CastlingColumns {
+	fn default() -> Self {
+		Self::standard()
+	}
+}
+
+/// The number of checks each side has given
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct CheckCount {
+	pub white: u32,
+	pub black: u32,
+}
+
+impl CheckCount {
+	pub fn get(self, color: Color) -> u32 {
+		match color {
+			Color::White => self.white,
+			Color::Black => self.black,
+		}
+	}
+
+	pub fn get_mut(&mut self, color: Color) -> &mut u32 {
+		match color {
+			Color::White => &mut self.white,
+			Color::Black => &mut self.black,
+		}
+	}
+}
+
+/// Which castling moves each side is still allowed to make at some point in the game
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CastlingRights {
+	pub white_kingside: bool,
+	pub white_queenside: bool,
+	pub black_kingside: bool,
+	pub black_queenside: bool,
+}
+
+impl CastlingRights {
+	pub fn all() -> Self {
+		Self {
+			white_kingside: true,
+			white_queenside: true,
+			black_kingside: true,
+			black_queenside: true,
+		}
+	}
+
+	pub fn none() -> Self {
+		Self {
+			white_kingside: false,
+			white_queenside: false,
+			black_kingside: false,
+			black_queenside: false,
+		}
+	}
+
+	pub fn kingside(self, color: Color) -> bool {
+		match color {
+			Color::White => self.white_kingside,
+			Color::Black => self.black_kingside,
+		}
+	}
+
+	pub fn queenside(self, color: Color) -> bool {
+		match color {
+			Color::White => self.white_queenside,
+			Color::Black => self.black_queenside,
+		}
+	}
+
+	pub fn kingside_mut(&mut self, color: Color) -> &mut bool {
+		match color {
+			Color::White => &mut self.white_kingside,
+			Color::Black => &mut self.black_kingside,
+		}
+	}
+
+	pub fn queenside_mut(&mut self, color: Color) -> &mut bool {
+		match color {
+			Color::White => &mut self.white_queenside,
+			Color::Black => &mut self.black_queenside,
 		}
 	}
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct GameBoard {
-	/// Row major storage for the board grid. The grid is represented with square A8 at index 0. This
-	/// Vec will always be of size (SIZE * SIZE).
+	/// The number of columns and rows of the board
+	#[serde(default)]
+	size: BoardSize,
+	/// Row major storage for the board grid, with square A1 at index 0 and each row following the one
+	/// below it. This Vec will always hold one cell for each square of the board.
 	board: Vec<Option<GamePiece>>,
 }
 
 impl GameBoard {
-	/// Create an empty board
+	/// Create an empty board of the standard size
 	pub fn new() -> Self {
+		Self::with_size(BoardSize::STANDARD)
+	}
+
+	/// Create an empty board of the given size
+	pub fn with_size(size: BoardSize) -> Self {
 		Self {
-			board: (0..(SIZE * SIZE)).into_iter().map(|_| None).collect(),
+			size,
+			board: (0..(size.columns * size.rows)).map(|_| None).collect(),
 		}
 	}
 
-	/// Set the board to the standard layout
+	/// The number of columns and rows of the board
+	pub fn size(&self) -> BoardSize {
+		self.size
+	}
+
+	/// Set the board to the standard layout, on a board of the standard size
 	pub fn set_standard(&mut self) {
 		use self::Color::*;
 		use self::Column::*;
 		use self::Piece::*;
 		use self::Row::*;
 
+		*self = GameBoard::new();
 		*self.get_board_index_mut(BoardIndex::new(A, R8)) = Some(GamePiece::new(Rook, Black));
 		*self.get_board_index_mut(BoardIndex::new(B, R8)) = Some(GamePiece::new(Knight, Black));
 		*self.get_board_index_mut(BoardIndex::new(C, R8)) = Some(GamePiece::new(Bishop, Black));
@@ -72,24 +393,127 @@ impl GameBoard {
 		*self.get_board_index_mut(BoardIndex::new(F, R1)) = Some(GamePiece::new(Bishop, White));
 		*self.get_board_index_mut(BoardIndex::new(G, R1)) = Some(GamePiece::new(Knight, White));
 		*self.get_board_index_mut(BoardIndex::new(H, R1)) = Some(GamePiece::new(Rook, White));
-
-		for row_num in 2..6 {
-			let row = Row::from(row_num);
-			for col_num in 0..8 {
-				let column = Column::from(col_num);
-				*self.get_board_index_mut(BoardIndex::new(column, row)) = None;
-			}
-		}
 	}
 
-	/// Get the current value of the cell at the given board index
+	/// Get the current value of the cell at the given board index, which is always empty for an index
+	/// that is not on the board
 	pub fn get_board_index(&self, index: BoardIndex) -> Option<GamePiece> {
-		self.board[index.to_linear()]
+		if !self.size.contains(index) {
+			return None;
+		}
+		self.board[self.cell(index)]
 	}
 
-	/// Get a mutable reference to the cell at the given board index
+	/// Get a mutable reference to the cell at the given board index. Panics if the index is not on the
+	/// board.
 	pub fn get_board_index_mut(&mut self, index: BoardIndex) -> &mut Option<GamePiece> {
-		&mut self.board[index.to_linear()]
+		assert!(self.size.contains(index), "Index {} is not on the board", index);
+		let cell = self.cell(index);
+		&mut self.board[cell]
+	}
+
+	/// Iterate over every occupied square on the board along with the piece on it
+	pub fn pieces(&self) -> impl Iterator<Item = (BoardIndex, GamePiece)> + '_ {
+		self.size
+			.indices()
+			.zip(self.board.iter())
+			.filter_map(|(index, cell)| cell.map(|piece| (index, piece)))
+	}
+
+	/// The position in `board` of the cell of an index on the board
+	fn cell(&self, index: BoardIndex) -> usize {
+		(u32::from(index.row) * self.size.columns + u32::from(index.column)) as usize
+	}
+}
+
+impl Default for GameBoard {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// The number of columns and rows of a board. Standard chess is played on a board of eight of each, and
+/// variants such as Capablanca chess, Grand Chess and Minichess on boards of other sizes. Every board
+/// starts from the `A1` corner, so it holds the squares of each board that is smaller in both directions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct BoardSize {
+	pub columns: u32,
+	pub rows: u32,
+}
+
+impl BoardSize {
+	pub const STANDARD: BoardSize = BoardSize { columns: 8, rows: 8 };
+
+	/// Create a board size, or `None` if either side is empty or longer than `MAX_SIZE`
+	pub fn new(columns: u32, rows: u32) -> Option<Self> {
+		if (1..=MAX_SIZE).contains(&columns) && (1..=MAX_SIZE).contains(&rows) {
+			Some(Self { columns, rows })
+		} else {
+			None
+		}
+	}
+
+	/// Check whether the given index is on a board of this size
+	pub fn contains(self, index: BoardIndex) -> bool {
+		u32::from(index.column) < self.columns && u32::from(index.row) < self.rows
+	}
+
+	/// Iterate over every index on a board of this size, in linear order
+	pub fn indices(self) -> impl Iterator<Item = BoardIndex> {
+		(0..self.rows).flat_map(move |row| {
+			(0..self.columns).map(move |column| BoardIndex::new(Column::nth(column), Row::nth(row)))
+		})
+	}
+
+	/// Get the index that is `columns` columns and `rows` rows away from the given one, or `None` if that
+	/// would fall off of a board of this size
+	pub fn offset(self, index: BoardIndex, columns: i32, rows: i32) -> Option<BoardIndex> {
+		index.offset(columns, rows).filter(|&index| self.contains(index))
+	}
+
+	pub fn last_column(self) -> Column {
+		Column::nth(self.columns - 1)
+	}
+
+	/// The row that is `rows` rows away from the end of the board the given color starts at
+	pub fn row_from(self, color: Color, rows: u32) -> Row {
+		match color {
+			Color::White => Row::nth(rows),
+			Color::Black => Row::nth(self.rows - 1 - rows),
+		}
+	}
+
+	/// The number of rows between the given row and the end of the board that pawns of the given color
+	/// advance towards
+	pub fn rows_ahead(self, color: Color, row: Row) -> u32 {
+		match color {
+			Color::White => self.rows - 1 - u32::from(row),
+			Color::Black => u32::from(row),
+		}
+	}
+
+	/// The row that the king and other pieces of the given color start on
+	pub fn home_row(self, color: Color) -> Row {
+		self.row_from(color, 0)
+	}
+
+	/// The column the king starts on in the standard layout of a board this wide, which is `E` on a board
+	/// of eight columns and `F` on one of ten. Castling is only written as the king moving along its row,
+	/// as in standard chess, from this column.
+	pub fn king_column(self) -> Column {
+		Column::nth(self.columns / 2)
+	}
+}
+
+impl Default for BoardSize {
+	fn default() -> Self {
+		Self::STANDARD
+	}
+}
+
+impl fmt::Display for BoardSize {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}x{}", self.columns, self.rows)
 	}
 }
 
@@ -104,14 +528,59 @@ impl BoardIndex {
 		Self { column, row }
 	}
 
+	/// The position of this index in a `Bitboard`, counting along each row of a board of the largest size,
+	/// so that an index has the same position whatever the size of its board
 	pub fn to_linear(self) -> usize {
 		let column: u32 = self.column.into();
 		let row: u32 = self.row.into();
-		(row * SIZE) as usize + column as usize
+		(row * MAX_SIZE) as usize + column as usize
+	}
+
+	/// The inverse of `to_linear`. Panics if the index is out of bounds.
+	pub fn from_linear(index: usize) -> Self {
+		let index = index as u32;
+		Self::new(Column::nth(index % MAX_SIZE), Row::nth(index / MAX_SIZE))
+	}
+
+	/// Get the index that is `columns` columns and `rows` rows away from this one, or `None` if that
+	/// would fall off of a board of the largest size. Positive values move towards column J and row 10
+	/// respectively. Use `BoardSize::offset` to stay on a smaller board.
+	pub fn offset(self, columns: i32, rows: i32) -> Option<BoardIndex> {
+		let column = u32::from(self.column) as i32 + columns;
+		let row = u32::from(self.row) as i32 + rows;
+		if column < 0 || column >= MAX_SIZE as i32 || row < 0 || row >= MAX_SIZE as i32 {
+			return None;
+		}
+		Some(BoardIndex::new(Column::nth(column as u32), Row::nth(row as u32)))
+	}
+
+	/// Parse a board index in algebraic notation, such as `e4` or `j10`. Whether it is on a board of a
+	/// particular size is not checked.
+	pub fn parse(input: &str) -> Option<BoardIndex> {
+		match BoardIndex::parse_prefix(input)? {
+			(index, "") => Some(index),
+			_ => None,
+		}
+	}
+
+	/// Parse the board index at the start of the input, returning it along with the rest of the input.
+	/// Every digit after the column is read as part of the row.
+	pub(crate) fn parse_prefix(input: &str) -> Option<(BoardIndex, &str)> {
+		let column = Column::from_char(input.chars().next()?)?;
+		let rest = &input[1..];
+		let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+		let row = Row::parse(&rest[..digits])?;
+		Some((BoardIndex::new(column, row), &rest[digits..]))
 	}
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+impl fmt::Display for BoardIndex {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}{}", self.column.to_char(), self.row)
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum Column {
 	A,
 	B,
@@ -121,20 +590,43 @@ pub enum Column {
 	F,
 	G,
 	H,
+	I,
+	J,
 }
 
-impl From<u32> for Column {
-	fn from(t: u32) -> Self {
-		match t {
-			0 => Column::A,
-			1 => Column::B,
-			2 => Column::C,
-			3 => Column::D,
-			4 => Column::E,
-			5 => Column::F,
-			6 => Column::G,
-			7 => Column::H,
-			_ => panic!("Invalid column index: {}", t),
+impl Column {
+	/// Get the column at the given index, counting from `A`, or `None` if there is no such column
+	pub fn from_index(index: u32) -> Option<Column> {
+		match index {
+			0 => Some(Column::A),
+			1 => Some(Column::B),
+			2 => Some(Column::C),
+			3 => Some(Column::D),
+			4 => Some(Column::E),
+			5 => Some(Column::F),
+			6 => Some(Column::G),
+			7 => Some(Column::H),
+			8 => Some(Column::I),
+			9 => Some(Column::J),
+			_ => None,
+		}
+	}
+
+	/// Get the column at an index that is already known to be on the board. Panics if the index is out of
+	/// bounds, so parsers should use `from_index` instead.
+	pub(crate) fn nth(index: u32) -> Column {
+		Column::from_index(index).unwrap_or_else(|| panic!("Invalid column index: {}", index))
+	}
+
+	/// Get the lowercase letter used for this column in algebraic notation
+	pub fn to_char(self) -> char {
+		(b'a' + u32::from(self) as u8) as char
+	}
+
+	pub fn from_char(c: char) -> Option<Column> {
+		match c {
+			'a'..='j' => Column::from_index(c as u32 - 'a' as u32),
+			_ => None,
 		}
 	}
 }
@@ -150,11 +642,13 @@ impl From<Column> for u32 {
 			Column::F => 5,
 			Column::G => 6,
 			Column::H => 7,
+			Column::I => 8,
+			Column::J => 9,
 		}
 	}
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum Row {
 	R1,
 	R2,
@@ -164,21 +658,51 @@ pub enum Row {
 	R6,
 	R7,
 	R8,
+	R9,
+	R10,
 }
 
-impl From<u32> for Row {
-	fn from(t: u32) -> Self {
-		match t {
-			0 => Row::R1,
-			1 => Row::R2,
-			2 => Row::R3,
-			3 => Row::R4,
-			4 => Row::R5,
-			5 => Row::R6,
-			6 => Row::R7,
-			7 => Row::R8,
-			_ => panic!("Invalid row index: {}", t),
+impl Row {
+	/// Get the row at the given index, counting from row `1`, or `None` if there is no such row
+	pub fn from_index(index: u32) -> Option<Row> {
+		match index {
+			0 => Some(Row::R1),
+			1 => Some(Row::R2),
+			2 => Some(Row::R3),
+			3 => Some(Row::R4),
+			4 => Some(Row::R5),
+			5 => Some(Row::R6),
+			6 => Some(Row::R7),
+			7 => Some(Row::R8),
+			8 => Some(Row::R9),
+			9 => Some(Row::R10),
+			_ => None,
+		}
+	}
+
+	/// Get the row at an index that is already known to be on the board. Panics if the index is out of
+	/// bounds, so parsers should use `from_index` instead.
+	pub(crate) fn nth(index: u32) -> Row {
+		Row::from_index(index).unwrap_or_else(|| panic!("Invalid row index: {}", index))
+	}
+
+	/// Parse the number used for a row in algebraic notation, from `1` to `10`
+	pub fn parse(input: &str) -> Option<Row> {
+		if !input.starts_with(|c: char| ('1'..='9').contains(&c)) {
+			return None;
 		}
+		input
+			.parse::<u32>()
+			.ok()
+			.filter(|&number| number <= MAX_SIZE)
+			.and_then(|number| Row::from_index(number - 1))
+	}
+}
+
+impl fmt::Display for Row {
+	/// Write the number used for this row in algebraic notation
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}", u32::from(*self) + 1)
 	}
 }
 
@@ -193,6 +717,8 @@ impl From<Row> for u32 {
 			Row::R6 => 5,
 			Row::R7 => 6,
 			Row::R8 => 7,
+			Row::R9 => 8,
+			Row::R10 => 9,
 		}
 	}
 }
@@ -207,6 +733,23 @@ impl GamePiece {
 	pub fn new(piece: Piece, color: Color) -> Self {
 		Self { piece, color }
 	}
+
+	/// Get the letter used for this piece in FEN, uppercase for white and lowercase for black
+	pub fn to_char(self) -> char {
+		match self.color {
+			Color::White => self.piece.to_char(),
+			Color::Black => self.piece.to_char().to_ascii_lowercase(),
+		}
+	}
+
+	pub fn from_char(c: char) -> Option<GamePiece> {
+		let color = if c.is_ascii_uppercase() {
+			Color::White
+		} else {
+			Color::Black
+		};
+		Piece::from_char(c.to_ascii_uppercase()).map(|piece| GamePiece::new(piece, color))
+	}
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -217,6 +760,10 @@ pub enum Piece {
 	Rook,
 	Queen,
 	King,
+	/// Moves as a bishop or a knight, in variants such as Capablanca chess
+	Archbishop,
+	/// Moves as a rook or a knight, in variants such as Capablanca chess
+	Chancellor,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -224,3 +771,43 @@ pub enum Color {
 	Black,
 	White,
 }
+
+impl Piece {
+	/// Get the uppercase letter used for this piece in algebraic notation
+	pub fn to_char(self) -> char {
+		match self {
+			Piece::Pawn => 'P',
+			Piece::Bishop => 'B',
+			Piece::Knight => 'N',
+			Piece::Rook => 'R',
+			Piece::Queen => 'Q',
+			Piece::King => 'K',
+			Piece::Archbishop => 'A',
+			Piece::Chancellor => 'C',
+		}
+	}
+
+	/// Parse an uppercase piece letter
+	pub fn from_char(c: char) -> Option<Piece> {
+		match c {
+			'P' => Some(Piece::Pawn),
+			'B' => Some(Piece::Bishop),
+			'N' => Some(Piece::Knight),
+			'R' => Some(Piece::Rook),
+			'Q' => Some(Piece::Queen),
+			'K' => Some(Piece::King),
+			'A' => Some(Piece::Archbishop),
+			'C' => Some(Piece::Chancellor),
+			_ => None,
+		}
+	}
+}
+
+impl Color {
+	pub fn opposite(self) -> Self {
+		match self {
+			Color::Black => Color::White,
+			Color::White => Color::Black,
+		}
+	}
+}
@@ -0,0 +1,312 @@
+use serde::{Deserialize, Serialize};
+
+use crate::game::*;
+
+/// Information about a move that was applied to a game state, including everything about the previous
+/// state that can not be recovered from the move itself
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MoveOutcome {
+	/// The piece that was captured by the move, if any
+	pub captured: Option<GamePiece>,
+	/// The castling rights before the move was made
+	pub castling: CastlingRights,
+	/// The en passant target square before the move was made
+	pub en_passant: Option<BoardIndex>,
+	/// The halfmove clock before the move was made
+	pub halfmove_clock: u32,
+}
+
+impl GameBoard {
+	/// Move the pieces on the board as the given move describes, without checking whether the move is
+	/// legal or updating any other state. When a king moves onto a rook of its own color or two squares
+	/// along its row, it is castling and the rook it castles with is moved as well. When a pawn moves
+	/// diagonally to an empty square, it is capturing en passant and the pawn it passed is removed.
+	/// Returns the piece that was captured, if any. Drops leave the board unchanged, as the board does not
+	/// know which side is dropping a piece; they are made through `GameState::apply_move`.
+	pub fn make_move(&mut self, mv: Move) -> Option<GamePiece> {
+		if let Some(castling) = Castling::from_board(mv, self.size(), |index| self.get_board_index(index)) {
+			let king = self.get_board_index_mut(castling.king_start).take();
+			let rook = self.get_board_index_mut(castling.rook_start).take();
+			*self.get_board_index_mut(castling.king_end) = king;
+			*self.get_board_index_mut(castling.rook_end) = rook;
+			return None;
+		}
+
+		let piece = self.get_board_index_mut(mv.start).take();
+		let placed = match (piece, mv.promotion) {
+			(Some(piece), Some(promotion)) => Some(GamePiece::new(promotion, piece.color)),
+			(piece, _) => piece,
+		};
+		let mut captured = std::mem::replace(self.get_board_index_mut(mv.end), placed);
+
+		if let Some(GamePiece { piece: Piece::Pawn, .. }) = piece {
+			if mv.start.column != mv.end.column && captured.is_none() {
+				captured = self
+					.get_board_index_mut(BoardIndex::new(mv.end.column, mv.start.row))
+					.take();
+			}
+		}
+
+		captured
+	}
+
+	/// Take back a move made with `make_move`, given the piece that was moved as it was before the move,
+	/// the piece it captured, whether it captured en passant and how it castled, if it did
+	pub fn unmake_move(
+		&mut self,
+		mv: Move,
+		piece: GamePiece,
+		captured: Option<GamePiece>,
+		en_passant: bool,
+		castling: Option<Castling>,
+	) {
+		if let Some(castling) = castling {
+			let king = self.get_board_index_mut(castling.king_end).take();
+			let rook = self.get_board_index_mut(castling.rook_end).take();
+			*self.get_board_index_mut(castling.king_start) = king;
+			*self.get_board_index_mut(castling.rook_start) = rook;
+			return;
+		}
+
+		*self.get_board_index_mut(mv.end) = None;
+		*self.get_board_index_mut(mv.start) = Some(piece);
+		if en_passant {
+			*self.get_board_index_mut(BoardIndex::new(mv.end.column, mv.start.row)) = captured;
+		} else {
+			*self.get_board_index_mut(mv.end) = captured;
+		}
+	}
+}
+
+impl GameState {
+	/// Make a move for the side whose turn it is, after checking that it is legal
+	pub fn apply_move(&mut self, mv: Move) -> Result<MoveOutcome, MoveError> {
+		self.validate_move(mv)?;
+		Ok(self.apply_move_unchecked(mv))
+	}
+
+	/// Make a move for the side whose turn it is without checking that it is legal. Making a move that
+	/// is not legal will leave the game state in an unspecified (but memory safe) condition.
+	pub fn apply_move_unchecked(&mut self, mv: Move) -> MoveOutcome {
+		if let Some(piece) = mv.drop {
+			return self.apply_drop(mv.end, piece);
+		}
+		let piece = self
+			.board
+			.get_board_index(mv.start)
+			.expect("Tried to apply a move from an empty square");
+		let castling = self.move_castling(mv, piece, self.castling);
+		let outcome = MoveOutcome {
+			captured: self.board.make_move(mv),
+			castling: self.castling,
+			en_passant: self.en_passant,
+			halfmove_clock: self.halfmove_clock,
+		};
+		self.zobrist ^= move_key(
+			mv,
+			piece,
+			outcome.captured,
+			is_en_passant(mv, piece, self.en_passant),
+			castling,
+		);
+
+		self.update_castling_rights(mv, piece);
+
+		self.en_passant = None;
+		self.zobrist ^= castling_key(outcome.castling)
+			^ castling_key(self.castling)
+			^ en_passant_key(outcome.en_passant)
+			^ turn_key(Color::Black);
+
+		if piece.piece == Piece::Pawn || outcome.captured.is_some() {
+			self.halfmove_clock = 0;
+		} else {
+			self.halfmove_clock = self.halfmove_clock.saturating_add(1);
+		}
+		if self.turn == Color::Black {
+			self.fullmove_number = self.fullmove_number.saturating_add(1);
+		}
+		self.turn = self.turn.opposite();
+
+		// The square a pawn skipped over is only kept when the opponent can capture onto it, as positions
+		// that differ only by an en passant square that can't be used are the same position
+		if piece.piece == Piece::Pawn {
+			let rows = u32::from(mv.end.row) as i32 - u32::from(mv.start.row) as i32;
+			if rows.abs() == 2 {
+				self.en_passant = mv
+					.start
+					.offset(0, rows / 2)
+					.filter(|&target| self.can_capture_en_passant(target));
+				self.zobrist ^= en_passant_key(self.en_passant);
+			}
+		}
+
+		outcome
+	}
+
+	/// Take back the last move that was applied to this game state, given the outcome that applying it
+	/// returned
+	pub fn undo_move(&mut self, mv: Move, outcome: MoveOutcome) {
+		self.turn = self.turn.opposite();
+		if self.turn == Color::Black {
+			self.fullmove_number -= 1;
+		}
+		if let Some(piece) = mv.drop {
+			let placed = GamePiece::new(piece, self.turn);
+			*self.board.get_board_index_mut(mv.end) = None;
+			self.add_to_pocket(self.turn, piece);
+			self.zobrist ^= piece_key(placed, mv.end)
+				^ en_passant_key(outcome.en_passant)
+				^ en_passant_key(self.en_passant)
+				^ turn_key(Color::Black);
+			self.en_passant = outcome.en_passant;
+			self.halfmove_clock = outcome.halfmove_clock;
+			return;
+		}
+		// With the right to castle, the king's own rook stood on the end square of a castling move, so only
+		// the king can have made it
+		let king = GamePiece::new(Piece::King, self.turn);
+		let castling = self.move_castling(mv, king, outcome.castling);
+		let piece = match (castling, mv.promotion) {
+			(Some(_), _) => king,
+			(None, Some(_)) => GamePiece::new(Piece::Pawn, self.turn),
+			(None, None) => self
+				.board
+				.get_board_index(mv.end)
+				.expect("Tried to undo a move that ended on an empty square"),
+		};
+		let en_passant = is_en_passant(mv, piece, outcome.en_passant);
+		self.board
+			.unmake_move(mv, piece, outcome.captured, en_passant, castling);
+
+		self.zobrist ^= move_key(mv, piece, outcome.captured, en_passant, castling)
+			^ castling_key(outcome.castling)
+			^ castling_key(self.castling)
+			^ en_passant_key(outcome.en_passant)
+			^ en_passant_key(self.en_passant)
+			^ turn_key(Color::Black);
+		self.castling = outcome.castling;
+		self.en_passant = outcome.en_passant;
+		self.halfmove_clock = outcome.halfmove_clock;
+	}
+
+	/// Drop a piece from the pocket of the side whose turn it is onto an empty square
+	fn apply_drop(&mut self, index: BoardIndex, piece: Piece) -> MoveOutcome {
+		let outcome = MoveOutcome {
+			captured: None,
+			castling: self.castling,
+			en_passant: self.en_passant,
+			halfmove_clock: self.halfmove_clock,
+		};
+		let placed = GamePiece::new(piece, self.turn);
+		self.remove_from_pocket(self.turn, piece);
+		*self.board.get_board_index_mut(index) = Some(placed);
+		self.en_passant = None;
+		self.zobrist ^= piece_key(placed, index) ^ en_passant_key(outcome.en_passant) ^ turn_key(Color::Black);
+
+		if piece == Piece::Pawn {
+			self.halfmove_clock = 0;
+		} else {
+			self.halfmove_clock = self.halfmove_clock.saturating_add(1);
+		}
+		if self.turn == Color::Black {
+			self.fullmove_number = self.fullmove_number.saturating_add(1);
+		}
+		self.turn = self.turn.opposite();
+
+		outcome
+	}
+
+	fn update_castling_rights(&mut self, mv: Move, piece: GamePiece) {
+		if piece.piece == Piece::King {
+			*self.castling.kingside_mut(piece.color) = false;
+			*self.castling.queenside_mut(piece.color) = false;
+		}
+		// A rook leaving its starting square, or being captured on it, loses the right to castle with it
+		for &color in &[Color::White, Color::Black] {
+			for &index in &[mv.start, mv.end] {
+				if index.row == self.board.size().home_row(color) {
+					if index.column == self.castling_columns.kingside(color) {
+						*self.castling.kingside_mut(color) = false;
+					}
+					if index.column == self.castling_columns.queenside(color) {
+						*self.castling.queenside_mut(color) = false;
+					}
+				}
+			}
+		}
+	}
+}
+
+/// Check whether a move captures en passant, given the piece being moved and the en passant target square
+/// before the move
+fn is_en_passant(mv: Move, piece: GamePiece, en_passant: Option<BoardIndex>) -> bool {
+	piece.piece == Piece::Pawn && Some(mv.end) == en_passant
+}
+
+#[test]
+fn apply_move_test() {
+	use self::Column::*;
+	use self::Row::*;
+
+	let mut game_state = GameState::standard();
+	let outcome = game_state
+		.apply_move(Move::new(BoardIndex::new(E, R2), BoardIndex::new(E, R4)))
+		.unwrap();
+	assert_eq!(outcome.captured, None);
+	assert_eq!(game_state.turn, Color::Black);
+	// No black pawn can capture the pawn that skipped over e3
+	assert_eq!(game_state.en_passant, None);
+	assert_eq!(game_state.fullmove_number, 1);
+	assert_eq!(
+		game_state.apply_move(Move::new(BoardIndex::new(E, R4), BoardIndex::new(E, R5))),
+		Err(MoveError::WrongColor)
+	);
+
+	game_state
+		.apply_move(Move::new(BoardIndex::new(G, R8), BoardIndex::new(F, R6)))
+		.unwrap();
+	assert_eq!(game_state.en_passant, None);
+	assert_eq!(game_state.halfmove_clock, 1);
+	assert_eq!(game_state.fullmove_number, 2);
+
+	game_state
+		.apply_move(Move::new(BoardIndex::new(E, R1), BoardIndex::new(E, R2)))
+		.unwrap();
+	assert!(!game_state.castling.white_kingside && !game_state.castling.white_queenside);
+	assert!(game_state.castling.black_kingside && game_state.castling.black_queenside);
+
+	game_state
+		.apply_move(Move::new(BoardIndex::new(H, R8), BoardIndex::new(G, R8)))
+		.unwrap();
+	assert!(!game_state.castling.black_kingside && game_state.castling.black_queenside);
+	assert_eq!(game_state.halfmove_clock, 3);
+
+	// The clocks stop at their largest value rather than overflowing
+	let mut game_state = GameState::from_fen("4k3/8/8/8/8/8/8/4K3 b - - 4294967295 4294967295").unwrap();
+	game_state
+		.apply_move(Move::new(BoardIndex::new(E, R8), BoardIndex::new(D, R8)))
+		.unwrap();
+	assert_eq!(game_state.halfmove_clock, u32::MAX);
+	assert_eq!(game_state.fullmove_number, u32::MAX);
+}
+
+#[test]
+fn undo_move_test() {
+	let fens = [
+		"r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+		"rnbqkbnr/pp1p1ppp/8/2pPp3/8/8/PPP1PPPP/RNBQKBNR w KQkq e6 0 3",
+		"n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+		"1r4kr/8/8/8/8/8/8/1R4KR w HBhb - 0 1",
+	];
+	for fen in &fens {
+		let game_state = GameState::from_fen(fen).unwrap();
+		for mv in game_state.legal_moves() {
+			let mut after = game_state.clone();
+			let outcome = after.apply_move(mv).unwrap();
+			assert_eq!(after.zobrist_key(), after.compute_zobrist_key(), "{} {}", fen, mv);
+			after.undo_move(mv, outcome);
+			assert_eq!(after, game_state, "{} {}", fen, mv);
+		}
+	}
+}
@@ -0,0 +1,529 @@
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not};
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::*;
+
+/// The number of squares on a board of the largest size, which every table below has an entry for
+const SQUARES: usize = (MAX_SIZE * MAX_SIZE) as usize;
+
+/// The attacks of a knight on each square
+const KNIGHT_ATTACKS: [u128; SQUARES] = step_attacks(&KNIGHT_OFFSETS);
+/// The attacks of a king on each square
+const KING_ATTACKS: [u128; SQUARES] = step_attacks(&KING_OFFSETS);
+/// The squares attacked by a black pawn and a white pawn on each square
+const PAWN_ATTACKS: [[u128; SQUARES]; 2] = [step_attacks(&[(-1, -1), (1, -1)]), step_attacks(&[(-1, 1), (1, 1)])];
+
+/// The directions sliding pieces move in. The first four move towards higher linear indices and the last
+/// four move towards lower linear indices.
+const DIRECTIONS: [(i32, i32); 8] = [(0, 1), (1, 1), (1, 0), (-1, 1), (0, -1), (-1, -1), (-1, 0), (1, -1)];
+/// For each direction, the squares a sliding piece on each square would attack on an empty board
+const RAYS: [[u128; SQUARES]; 8] = rays();
+
+// The tables cover a board of the largest size, and the squares off of a smaller board are masked out of
+// the attacks they give. A line leaving a board never comes back onto it, as every board is a rectangle
+// in the same corner, so the rays of a smaller board are those of the largest one with that mask applied.
+
+const fn step_attacks(offsets: &[(i32, i32)]) -> [u128; SQUARES] {
+	let size = MAX_SIZE as i32;
+	let mut attacks = [0; SQUARES];
+	let mut square = 0;
+	while square < SQUARES {
+		let (column, row) = (square as i32 % size, square as i32 / size);
+		let mut i = 0;
+		while i < offsets.len() {
+			let (to_column, to_row) = (column + offsets[i].0, row + offsets[i].1);
+			if to_column >= 0 && to_column < size && to_row >= 0 && to_row < size {
+				attacks[square] |= 1 << (to_row * size + to_column);
+			}
+			i += 1;
+		}
+		square += 1;
+	}
+	attacks
+}
+
+const fn rays() -> [[u128; SQUARES]; 8] {
+	let size = MAX_SIZE as i32;
+	let mut rays = [[0; SQUARES]; 8];
+	let mut direction = 0;
+	while direction < 8 {
+		let (columns, rows) = DIRECTIONS[direction];
+		let mut square = 0;
+		while square < SQUARES {
+			let (mut column, mut row) = (square as i32 % size + columns, square as i32 / size + rows);
+			while column >= 0 && column < size && row >= 0 && row < size {
+				rays[direction][square] |= 1 << (row * size + column);
+				column += columns;
+				row += rows;
+			}
+			square += 1;
+		}
+		direction += 1;
+	}
+	rays
+}
+
+/// The squares attacked by a piece sliding in the given directions from a square, stopping at (and
+/// including) the first occupied square in each direction
+fn slide_attacks(square: usize, occupied: u128, directions: &[usize]) -> u128 {
+	let mut attacks = 0;
+	for &direction in directions {
+		let ray = RAYS[direction][square];
+		let blockers = ray & occupied;
+		attacks |= if blockers == 0 {
+			ray
+		} else if direction < 4 {
+			ray ^ RAYS[direction][blockers.trailing_zeros() as usize]
+		} else {
+			ray ^ RAYS[direction][127 - blockers.leading_zeros() as usize]
+		};
+	}
+	attacks
+}
+
+fn rook_attacks(square: usize, occupied: u128) -> u128 {
+	slide_attacks(square, occupied, &[0, 2, 4, 6])
+}
+
+fn bishop_attacks(square: usize, occupied: u128) -> u128 {
+	slide_attacks(square, occupied, &[1, 3, 5, 7])
+}
+
+/// A set of squares, stored as one bit for each square in the order given by `BoardIndex::to_linear`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct Bitboard(pub u128);
+
+impl Bitboard {
+	pub const EMPTY: Bitboard = Bitboard(0);
+
+	/// The set of every square on a board of the given size
+	pub fn board(size: BoardSize) -> Self {
+		let row = (1 << size.columns) - 1;
+		Bitboard((0..size.rows).fold(0, |set, i| set | row << (i * MAX_SIZE)))
+	}
+
+	pub fn from_index(index: BoardIndex) -> Self {
+		Bitboard(1 << index.to_linear())
+	}
+
+	pub fn contains(self, index: BoardIndex) -> bool {
+		self.0 & (1 << index.to_linear()) != 0
+	}
+
+	pub fn is_empty(self) -> bool {
+		self.0 == 0
+	}
+
+	pub fn count(self) -> u32 {
+		self.0.count_ones()
+	}
+
+	/// Get the square with the lowest linear index in the set
+	pub fn first(self) -> Option<BoardIndex> {
+		if self.is_empty() {
+			None
+		} else {
+			Some(BoardIndex::from_linear(self.0.trailing_zeros() as usize))
+		}
+	}
+
+	/// Iterate over the squares in the set, in linear order
+	pub fn iter(self) -> impl Iterator<Item = BoardIndex> {
+		let mut remaining = self.0;
+		std::iter::from_fn(move || {
+			if remaining == 0 {
+				return None;
+			}
+			let square = remaining.trailing_zeros() as usize;
+			remaining &= remaining - 1;
+			Some(BoardIndex::from_linear(square))
+		})
+	}
+}
+
+impl BitAnd for Bitboard {
+	type Output = Bitboard;
+
+	fn bitand(self, rhs: Bitboard) -> Bitboard {
+		Bitboard(self.0 & rhs.0)
+	}
+}
+
+impl BitAndAssign for Bitboard {
+	fn bitand_assign(&mut self, rhs: Bitboard) {
+		self.0 &= rhs.0;
+	}
+}
+
+impl BitOr for Bitboard {
+	type Output = Bitboard;
+
+	fn bitor(self, rhs: Bitboard) -> Bitboard {
+		Bitboard(self.0 | rhs.0)
+	}
+}
+
+impl BitOrAssign for Bitboard {
+	fn bitor_assign(&mut self, rhs: Bitboard) {
+		self.0 |= rhs.0;
+	}
+}
+
+impl BitXor for Bitboard {
+	type Output = Bitboard;
+
+	fn bitxor(self, rhs: Bitboard) -> Bitboard {
+		Bitboard(self.0 ^ rhs.0)
+	}
+}
+
+impl BitXorAssign for Bitboard {
+	fn bitxor_assign(&mut self, rhs: Bitboard) {
+		self.0 ^= rhs.0;
+	}
+}
+
+impl Not for Bitboard {
+	type Output = Bitboard;
+
+	fn not(self) -> Bitboard {
+		Bitboard(!self.0)
+	}
+}
+
+pub(crate) fn piece_index(piece: Piece) -> usize {
+	match piece {
+		Piece::Pawn => 0,
+		Piece::Bishop => 1,
+		Piece::Knight => 2,
+		Piece::Rook => 3,
+		Piece::Queen => 4,
+		Piece::King => 5,
+		Piece::Archbishop => 6,
+		Piece::Chancellor => 7,
+	}
+}
+
+pub(crate) fn color_index(color: Color) -> usize {
+	match color {
+		Color::Black => 0,
+		Color::White => 1,
+	}
+}
+
+const PIECES: [Piece; 8] = [
+	Piece::Pawn,
+	Piece::Bishop,
+	Piece::Knight,
+	Piece::Rook,
+	Piece::Queen,
+	Piece::King,
+	Piece::Archbishop,
+	Piece::Chancellor,
+];
+
+/// A board stored as one bitboard for each kind of piece and one for each color. This offers the same
+/// operations as `GameBoard`, but is much faster for generating moves and finding attacks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BitGameBoard {
+	pieces: [Bitboard; 8],
+	colors: [Bitboard; 2],
+	size: BoardSize,
+	/// Every square on the board, which attacks and moves are kept within
+	squares: Bitboard,
+}
+
+impl BitGameBoard {
+	/// Create an empty board of the standard size
+	pub fn new() -> Self {
+		Self::with_size(BoardSize::STANDARD)
+	}
+
+	/// Create an empty board of the given size
+	pub fn with_size(size: BoardSize) -> Self {
+		Self {
+			pieces: [Bitboard::EMPTY; 8],
+			colors: [Bitboard::EMPTY; 2],
+			size,
+			squares: Bitboard::board(size),
+		}
+	}
+
+	/// The number of columns and rows of the board
+	pub fn size(&self) -> BoardSize {
+		self.size
+	}
+
+	/// Set the board to the standard layout
+	pub fn set_standard(&mut self) {
+		let mut board = GameBoard::new();
+		board.set_standard();
+		*self = BitGameBoard::from(&board);
+	}
+
+	/// Get the current value of the cell at the given board index
+	pub fn get_board_index(&self, index: BoardIndex) -> Option<GamePiece> {
+		let color = if self.colors[color_index(Color::White)].contains(index) {
+			Color::White
+		} else if self.colors[color_index(Color::Black)].contains(index) {
+			Color::Black
+		} else {
+			return None;
+		};
+		PIECES
+			.iter()
+			.find(|&&piece| self.pieces[piece_index(piece)].contains(index))
+			.map(|&piece| GamePiece::new(piece, color))
+	}
+
+	/// Set the value of the cell at the given board index
+	pub fn set_board_index(&mut self, index: BoardIndex, value: Option<GamePiece>) {
+		let mask = !Bitboard::from_index(index);
+		for set in self.pieces.iter_mut().chain(self.colors.iter_mut()) {
+			*set &= mask;
+		}
+		if let Some(piece) = value {
+			self.pieces[piece_index(piece.piece)] |= Bitboard::from_index(index);
+			self.colors[color_index(piece.color)] |= Bitboard::from_index(index);
+		}
+	}
+
+	/// Iterate over every occupied square on the board along with the piece on it
+	pub fn pieces(&self) -> impl Iterator<Item = (BoardIndex, GamePiece)> + '_ {
+		self.occupied()
+			.iter()
+			.map(move |index| (index, self.get_board_index(index).unwrap()))
+	}
+
+	/// Get the squares holding the given piece
+	pub fn piece_set(&self, piece: GamePiece) -> Bitboard {
+		self.pieces[piece_index(piece.piece)] & self.colors[color_index(piece.color)]
+	}
+
+	/// Get the squares holding pieces of the given color
+	pub fn color_set(&self, color: Color) -> Bitboard {
+		self.colors[color_index(color)]
+	}
+
+	/// Get the squares holding any piece
+	pub fn occupied(&self) -> Bitboard {
+		self.colors[0] | self.colors[1]
+	}
+
+	/// Get the squares attacked by the given piece standing on the given square
+	pub fn attacks(&self, index: BoardIndex, piece: GamePiece) -> Bitboard {
+		let square = index.to_linear();
+		let occupied = self.occupied().0;
+		Bitboard(match piece.piece {
+			Piece::Pawn => PAWN_ATTACKS[color_index(piece.color)][square],
+			Piece::Knight => KNIGHT_ATTACKS[square],
+			Piece::King => KING_ATTACKS[square],
+			Piece::Bishop => bishop_attacks(square, occupied),
+			Piece::Rook => rook_attacks(square, occupied),
+			Piece::Queen => bishop_attacks(square, occupied) | rook_attacks(square, occupied),
+			Piece::Archbishop => bishop_attacks(square, occupied) | KNIGHT_ATTACKS[square],
+			Piece::Chancellor => rook_attacks(square, occupied) | KNIGHT_ATTACKS[square],
+		}) & self.squares
+	}
+
+	/// Generate every move that the pieces of the given color could make according to how each piece
+	/// moves and the given rules for pawns, without considering whether the move would leave that color's
+	/// king in check.
+	pub fn pseudo_legal_moves(&self, color: Color, pawn_rules: PawnRules) -> Vec<Move> {
+		let mut moves = Vec::with_capacity(64);
+		let own = self.color_set(color);
+		let enemy = self.color_set(color.opposite());
+		let empty = !self.occupied() & self.squares;
+		let promotions = pawn_rules.promotions(|piece| self.piece_set(GamePiece::new(piece, color)).count());
+		let double_step_row = pawn_rules.double_step_row.map(|rows| self.size.row_from(color, rows));
+		for start in own.iter() {
+			let piece = self.get_board_index(start).unwrap();
+			if piece.piece != Piece::Pawn {
+				for end in (self.attacks(start, piece) & !own).iter() {
+					moves.push(Move::new(start, end));
+				}
+				continue;
+			}
+
+			let mut ends = self.attacks(start, piece) & enemy;
+			if let Some(single) = start.offset(0, color.pawn_direction()) {
+				if empty.contains(single) {
+					ends |= Bitboard::from_index(single);
+					if Some(start.row) == double_step_row {
+						if let Some(double) = single.offset(0, color.pawn_direction()) {
+							if empty.contains(double) {
+								ends |= Bitboard::from_index(double);
+							}
+						}
+					}
+				}
+			}
+			for end in ends.iter() {
+				let rows_ahead = self.size.rows_ahead(color, end.row);
+				if rows_ahead < pawn_rules.promotion_rows {
+					moves.extend(
+						promotions
+							.iter()
+							.map(|&promotion| Move::with_promotion(start, end, promotion)),
+					);
+				}
+				if rows_ahead > 0 {
+					moves.push(Move::new(start, end));
+				}
+			}
+		}
+		moves
+	}
+
+	/// Check whether any piece of color `by` attacks the given square
+	pub fn is_attacked(&self, index: BoardIndex, by: Color) -> bool {
+		let square = index.to_linear();
+		let attackers = self.color_set(by).0;
+		let occupied = self.occupied().0;
+		let pawns = self.pieces[piece_index(Piece::Pawn)].0;
+		let kings = self.pieces[piece_index(Piece::King)].0;
+		let queens = self.pieces[piece_index(Piece::Queen)].0;
+		let archbishops = self.pieces[piece_index(Piece::Archbishop)].0;
+		let chancellors = self.pieces[piece_index(Piece::Chancellor)].0;
+		let knights = self.pieces[piece_index(Piece::Knight)].0 | archbishops | chancellors;
+		let rooks = self.pieces[piece_index(Piece::Rook)].0 | queens | chancellors;
+		let bishops = self.pieces[piece_index(Piece::Bishop)].0 | queens | archbishops;
+
+		// A square is attacked by a pawn of one color if a pawn of the other color on it would attack
+		// that pawn
+		PAWN_ATTACKS[color_index(by.opposite())][square] & pawns & attackers != 0
+			|| KNIGHT_ATTACKS[square] & knights & attackers != 0
+			|| KING_ATTACKS[square] & kings & attackers != 0
+			|| rook_attacks(square, occupied) & rooks & attackers != 0
+			|| bishop_attacks(square, occupied) & bishops & attackers != 0
+	}
+
+	/// Find the square the king of the given color is on, if it is on the board
+	pub fn find_king(&self, color: Color) -> Option<BoardIndex> {
+		self.piece_set(GamePiece::new(Piece::King, color)).first()
+	}
+
+	/// Check whether the king of the given color is attacked by any of the opponent's pieces. A board
+	/// without a king of that color is never considered to be in check.
+	pub fn is_king_attacked(&self, color: Color) -> bool {
+		match self.find_king(color) {
+			Some(king) => self.is_attacked(king, color.opposite()),
+			None => false,
+		}
+	}
+
+	/// Move the pieces on the board as the given move describes, in the same way as
+	/// `GameBoard::make_move`. Returns the piece that was captured, if any.
+	pub fn make_move(&mut self, mv: Move) -> Option<GamePiece> {
+		if let Some(castling) = Castling::from_board(mv, self.size, |index| self.get_board_index(index)) {
+			let king = self.get_board_index(castling.king_start);
+			let rook = self.get_board_index(castling.rook_start);
+			self.set_board_index(castling.king_start, None);
+			self.set_board_index(castling.rook_start, None);
+			self.set_board_index(castling.king_end, king);
+			self.set_board_index(castling.rook_end, rook);
+			return None;
+		}
+
+		let piece = self.get_board_index(mv.start);
+		let mut captured = self.get_board_index(mv.end);
+		let placed = match (piece, mv.promotion) {
+			(Some(piece), Some(promotion)) => Some(GamePiece::new(promotion, piece.color)),
+			(piece, _) => piece,
+		};
+		self.set_board_index(mv.start, None);
+		self.set_board_index(mv.end, placed);
+
+		if let Some(GamePiece { piece: Piece::Pawn, .. }) = piece {
+			if mv.start.column != mv.end.column && captured.is_none() {
+				let passed = BoardIndex::new(mv.end.column, mv.start.row);
+				captured = self.get_board_index(passed);
+				self.set_board_index(passed, None);
+			}
+		}
+
+		captured
+	}
+}
+
+impl Default for BitGameBoard {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl From<&GameBoard> for BitGameBoard {
+	fn from(t: &GameBoard) -> Self {
+		let mut board = BitGameBoard::with_size(t.size());
+		for (index, piece) in t.pieces() {
+			board.set_board_index(index, Some(piece));
+		}
+		board
+	}
+}
+
+impl From<&BitGameBoard> for GameBoard {
+	fn from(t: &BitGameBoard) -> Self {
+		let mut board = GameBoard::with_size(t.size);
+		for (index, piece) in t.pieces() {
+			*board.get_board_index_mut(index) = Some(piece);
+		}
+		board
+	}
+}
+
+#[test]
+fn bitboard_conversion_test() {
+	let game_state =
+		GameState::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+	let board = BitGameBoard::from(&game_state.board);
+	for index in game_state.board.size().indices() {
+		assert_eq!(board.get_board_index(index), game_state.board.get_board_index(index));
+	}
+	assert_eq!(GameBoard::from(&board), game_state.board);
+
+	let mut standard = BitGameBoard::new();
+	standard.set_standard();
+	assert_eq!(GameBoard::from(&standard), GameState::standard().board);
+}
+
+#[test]
+fn bitboard_matches_game_board_test() {
+	let fens = [
+		STANDARD_FEN,
+		"r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+		"8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+		"r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+		"rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+		"rnabqkbcnr/pppppppppp/10/10/10/10/PPPPPPPPPP/RNABQKBCNR w KQkq - 0 1",
+		"r1a1k4r/1P6c1/10/3A6/10/2p7/10/R3K1C2R b KQkq - 0 1",
+		"rnbqk/ppppp/5/PPPPP/RNBQK w - - 0 1",
+	];
+	for fen in &fens {
+		let game_state = GameState::from_fen(fen).unwrap();
+		let board = BitGameBoard::from(&game_state.board);
+		for &color in &[Color::White, Color::Black] {
+			let mut expected = game_state.board.pseudo_legal_moves(color, game_state.pawn_rules());
+			let mut actual = board.pseudo_legal_moves(color, game_state.pawn_rules());
+			expected.sort_by_key(|mv| mv.to_uci());
+			actual.sort_by_key(|mv| mv.to_uci());
+			assert_eq!(actual, expected, "{}", fen);
+			for index in game_state.board.size().indices() {
+				assert_eq!(
+					board.is_attacked(index, color),
+					game_state.board.is_attacked(index, color),
+					"{} {}",
+					fen,
+					index
+				);
+			}
+		}
+		for mv in game_state.legal_moves() {
+			let mut expected = game_state.board.clone();
+			let mut actual = board;
+			assert_eq!(actual.make_move(mv), expected.make_move(mv));
+			assert_eq!(GameBoard::from(&actual), expected);
+		}
+	}
+}
@@ -0,0 +1,135 @@
+use std::{
+	collections::hash_map::RandomState,
+	hash::{BuildHasher, Hasher},
+};
+
+use crate::game::*;
+
+/// The number of Chess960 starting positions
+pub const CHESS960_POSITIONS: u32 = 960;
+/// The Scharnagl number of the standard starting position
+pub const STANDARD_CHESS960_POSITION: u32 = 518;
+
+/// The placements of the two knights among the five squares left after placing the bishops and queen,
+/// in the order of the Scharnagl numbering
+const KNIGHT_PLACEMENTS: [(usize, usize); 10] = [
+	(0, 1),
+	(0, 2),
+	(0, 3),
+	(0, 4),
+	(1, 2),
+	(1, 3),
+	(1, 4),
+	(2, 3),
+	(2, 4),
+	(3, 4),
+];
+
+/// Get the pieces on the home row of the Chess960 starting position with the given Scharnagl number, from
+/// the A column to the H column
+///
+/// Panics if the number is not less than 960.
+pub fn chess960_home_row(n: u32) -> [Piece; 8] {
+	assert!(n < CHESS960_POSITIONS, "There is no Chess960 position number {}", n);
+	let mut home_row = [None; 8];
+	let n = n as usize;
+
+	home_row[n % 4 * 2 + 1] = Some(Piece::Bishop);
+	home_row[n / 4 % 4 * 2] = Some(Piece::Bishop);
+	let mut empty: Vec<usize> = (0..8).filter(|&column| home_row[column].is_none()).collect();
+	home_row[empty.remove(n / 16 % 6)] = Some(Piece::Queen);
+	let (first, second) = KNIGHT_PLACEMENTS[n / 96];
+	home_row[empty[first]] = Some(Piece::Knight);
+	home_row[empty[second]] = Some(Piece::Knight);
+	let rest: Vec<usize> = (0..8).filter(|&column| home_row[column].is_none()).collect();
+	home_row[rest[0]] = Some(Piece::Rook);
+	home_row[rest[1]] = Some(Piece::King);
+	home_row[rest[2]] = Some(Piece::Rook);
+
+	let mut pieces = [Piece::Pawn; 8];
+	for (piece, placed) in pieces.iter_mut().zip(home_row.iter()) {
+		*piece = placed.unwrap();
+	}
+	pieces
+}
+
+/// Pick one of the Chess960 starting positions at random, returning its Scharnagl number
+pub fn random_chess960_position() -> u32 {
+	// The hasher of a new `RandomState` is randomly keyed, so hashing nothing gives a random number
+	(RandomState::new().build_hasher().finish() % u64::from(CHESS960_POSITIONS)) as u32
+}
+
+impl GameBoard {
+	/// Set the board to the Chess960 starting position with the given Scharnagl number. Position 518 is
+	/// the standard layout.
+	///
+	/// Panics if the number is not less than 960.
+	pub fn set_chess960(&mut self, n: u32) {
+		*self = GameBoard::new();
+		let size = BoardSize::STANDARD;
+		for (column, &piece) in chess960_home_row(n).iter().enumerate() {
+			let column = Column::nth(column as u32);
+			for &color in &[Color::White, Color::Black] {
+				*self.get_board_index_mut(BoardIndex::new(column, size.home_row(color))) =
+					Some(GamePiece::new(piece, color));
+				*self.get_board_index_mut(BoardIndex::new(column, size.row_from(color, 1))) =
+					Some(GamePiece::new(Piece::Pawn, color));
+			}
+		}
+	}
+}
+
+impl GameState {
+	/// Create a game state for the start of a Chess960 game from the starting position with the given
+	/// Scharnagl number
+	///
+	/// Panics if the number is not less than 960.
+	pub fn chess960(n: u32) -> Self {
+		let mut game_state = Self::new();
+		game_state.board.set_chess960(n);
+		game_state.castling = CastlingRights::all();
+		game_state.chess960 = true;
+		let home_row = chess960_home_row(n);
+		let mut rooks = (0..8).filter(|&column| home_row[column] == Piece::Rook);
+		let queenside = rooks.next().unwrap();
+		let kingside = rooks.next().unwrap();
+		game_state.castling_columns =
+			CastlingColumns::both(Column::nth(kingside as u32), Column::nth(queenside as u32));
+		game_state.refresh_zobrist_key();
+		game_state
+	}
+
+	/// Create a game state for the start of a Chess960 game from a random starting position
+	pub fn random_chess960() -> Self {
+		Self::chess960(random_chess960_position())
+	}
+}
+
+#[test]
+fn chess960_position_test() {
+	let fen_row = |n| {
+		chess960_home_row(n)
+			.iter()
+			.map(|piece| piece.to_char())
+			.collect::<String>()
+	};
+	assert_eq!(fen_row(0), "BBQNNRKR");
+	assert_eq!(fen_row(STANDARD_CHESS960_POSITION), "RNBQKBNR");
+	assert_eq!(fen_row(959), "RKRNNQBB");
+
+	let mut standard = GameBoard::new();
+	standard.set_chess960(STANDARD_CHESS960_POSITION);
+	assert_eq!(standard, GameState::standard().board);
+
+	// Every position is different and has the king between the rooks and bishops on both square colors
+	let mut home_rows: Vec<String> = (0..CHESS960_POSITIONS).map(fen_row).collect();
+	for home_row in &home_rows {
+		let king = home_row.find('K').unwrap();
+		assert!(home_row.find('R').unwrap() < king && home_row.rfind('R').unwrap() > king);
+		let bishops: Vec<usize> = home_row.match_indices('B').map(|(i, _)| i).collect();
+		assert_ne!(bishops[0] % 2, bishops[1] % 2);
+	}
+	home_rows.sort();
+	home_rows.dedup();
+	assert_eq!(home_rows.len(), CHESS960_POSITIONS as usize);
+}
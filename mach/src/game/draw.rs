@@ -0,0 +1,213 @@
+use crate::game::*;
+
+/// The number of halfmoves without a capture or pawn move after which either player may claim a draw
+pub const FIFTY_MOVE_RULE: u32 = 100;
+/// The number of halfmoves without a capture or pawn move after which the game is drawn automatically
+pub const SEVENTY_FIVE_MOVE_RULE: u32 = 150;
+
+/// The Zobrist keys of every position that has occurred in a game, used to detect repeated positions
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PositionHistory {
+	keys: Vec<u64>,
+}
+
+impl PositionHistory {
+	/// Start the history of a game at the given position
+	pub fn new(game_state: &GameState) -> Self {
+		Self {
+			keys: vec![game_state.zobrist_key()],
+		}
+	}
+
+	/// Record the position reached after a move was made
+	pub fn push(&mut self, game_state: &GameState) {
+		self.keys.push(game_state.zobrist_key());
+	}
+
+	/// Record a change to the latest position that was not made by a move, such as a piece being passed
+	/// into a pocket from the partner board in Bughouse
+	pub fn replace_latest(&mut self, game_state: &GameState) {
+		if let Some(latest) = self.keys.last_mut() {
+			*latest = game_state.zobrist_key();
+		}
+	}
+
+	/// Forget the most recently recorded position, after the move that reached it was taken back
+	pub fn pop(&mut self) {
+		if self.keys.len() > 1 {
+			self.keys.pop();
+		}
+	}
+
+	/// Count how many times the given position, which should be the latest one recorded, has occurred in
+	/// the game. Only positions since the last capture or pawn move are compared, as no position before it
+	/// can occur again.
+	pub fn repetitions(&self, game_state: &GameState) -> usize {
+		let key = game_state.zobrist_key();
+		self.keys
+			.iter()
+			.rev()
+			.take(game_state.halfmove_clock as usize + 1)
+			.step_by(2)
+			.filter(|&&previous| previous == key)
+			.count()
+	}
+
+	/// Check whether the side whose turn it is may claim a draw in the given position, and if so why.
+	/// A draw may be claimed once a position has occurred three times, or after fifty moves by each side
+	/// without a capture or pawn move.
+	pub fn draw_claim(&self, game_state: &GameState) -> Option<Termination> {
+		if self.repetitions(game_state) >= 3 {
+			Some(Termination::ThreefoldRepetition)
+		} else if game_state.halfmove_clock >= FIFTY_MOVE_RULE {
+			Some(Termination::FiftyMoveRule)
+		} else {
+			None
+		}
+	}
+
+	/// Check whether the game has ended in the given position, and if so how. In addition to the endings
+	/// that `GameState::game_over` detects, a game is drawn automatically once a position has occurred five
+	/// times.
+	pub fn game_over(&self, game_state: &GameState) -> Option<GameOver> {
+		game_state.game_over().or_else(|| self.automatic_draw(game_state))
+	}
+
+	/// Check whether the game is drawn automatically in the given position by the fivefold repetition or
+	/// seventy-five-move rules, which apply whatever the rules for how pieces move and how games are won
+	pub fn automatic_draw(&self, game_state: &GameState) -> Option<GameOver> {
+		let termination = if self.repetitions(game_state) >= 5 {
+			Termination::FivefoldRepetition
+		} else if game_state.halfmove_clock >= SEVENTY_FIVE_MOVE_RULE {
+			Termination::SeventyFiveMoveRule
+		} else {
+			return None;
+		};
+		Some(GameOver {
+			result: GameResult::Draw,
+			termination,
+		})
+	}
+}
+
+impl GameState {
+	/// Check whether neither side has enough material left to checkmate the other by any series of legal
+	/// moves. This is the case when only kings are left, when one side has a single minor piece besides
+	/// its king, or when every minor piece left is a bishop on squares of the same color. Pieces held in a
+	/// pocket can always be dropped to add material, so there is never insufficient material while either
+	/// side holds one.
+	pub fn is_insufficient_material(&self) -> bool {
+		if !self.pockets.is_empty() {
+			return false;
+		}
+		let mut knights = 0;
+		let mut bishops = 0;
+		let mut bishop_squares = [false; 2];
+		for (index, piece) in self.board.pieces() {
+			match piece.piece {
+				Piece::King => {}
+				Piece::Knight => knights += 1,
+				Piece::Bishop => {
+					bishops += 1;
+					let square_color = (u32::from(index.column) + u32::from(index.row)) % 2;
+					bishop_squares[square_color as usize] = true;
+				}
+				Piece::Pawn | Piece::Rook | Piece::Queen | Piece::Archbishop | Piece::Chancellor => return false,
+			}
+		}
+		match (knights, bishops) {
+			(0, 0) | (1, 0) | (0, 1) => true,
+			(0, _) => !(bishop_squares[0] && bishop_squares[1]),
+			_ => false,
+		}
+	}
+}
+
+#[test]
+fn repetition_test() {
+	let mut game_state = GameState::standard();
+	let mut history = PositionHistory::new(&game_state);
+	let shuffle = ["Nf3", "Nf6", "Ng1", "Ng8"];
+	for (i, mv) in shuffle.iter().cycle().take(16).enumerate() {
+		assert_eq!(history.game_over(&game_state), None);
+		let mv = game_state.parse_move(mv).unwrap();
+		game_state.apply_move(mv).unwrap();
+		history.push(&game_state);
+		let repetitions = if i % 4 == 3 { i / 4 + 2 } else { i / 4 + 1 };
+		assert_eq!(history.repetitions(&game_state), repetitions);
+		if i == 7 {
+			assert_eq!(history.draw_claim(&game_state), Some(Termination::ThreefoldRepetition));
+		}
+	}
+	assert_eq!(
+		history.game_over(&game_state),
+		Some(GameOver {
+			result: GameResult::Draw,
+			termination: Termination::FivefoldRepetition,
+		})
+	);
+
+	// The position right after a double pawn push repeats, as no pawn can capture en passant
+	let mut game_state = GameState::standard();
+	let mut history = PositionHistory::new(&game_state);
+	for mv in ["e4", "Nf6", "Nf3", "Ng8", "Ng1", "Nf6", "Nf3", "Ng8", "Ng1"] {
+		let mv = game_state.parse_move(mv).unwrap();
+		game_state.apply_move(mv).unwrap();
+		history.push(&game_state);
+	}
+	assert_eq!(history.repetitions(&game_state), 3);
+}
+
+#[test]
+fn move_rule_test() {
+	let history = |game_state: &GameState| PositionHistory::new(game_state);
+
+	let game_state = GameState::from_fen("4k3/8/8/8/8/8/4P3/R3K3 w - - 99 80").unwrap();
+	assert_eq!(history(&game_state).draw_claim(&game_state), None);
+	let game_state = GameState::from_fen("4k3/8/8/8/8/8/4P3/R3K3 w - - 100 80").unwrap();
+	assert_eq!(
+		history(&game_state).draw_claim(&game_state),
+		Some(Termination::FiftyMoveRule)
+	);
+	assert_eq!(game_state.game_over(), None);
+	let game_state = GameState::from_fen("4k3/8/8/8/8/8/4P3/R3K3 w - - 150 80").unwrap();
+	assert_eq!(
+		game_state.game_over(),
+		Some(GameOver {
+			result: GameResult::Draw,
+			termination: Termination::SeventyFiveMoveRule,
+		})
+	);
+	// Checkmate on the last move takes precedence over the seventy-five-move rule
+	let game_state = GameState::from_fen("R3k3/8/4K3/8/8/8/8/8 b - - 150 80").unwrap();
+	assert_eq!(game_state.game_over().unwrap().termination, Termination::Checkmate);
+}
+
+#[test]
+fn insufficient_material_test() {
+	let insufficient = [
+		"4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+		"4k3/8/8/8/8/8/8/2N1K3 w - - 0 1",
+		"4k3/8/8/8/8/8/8/2B1K3 w - - 0 1",
+		"2b1k3/8/8/8/8/8/8/3BK3 w - - 0 1",
+	];
+	for fen in &insufficient {
+		let game_state = GameState::from_fen(fen).unwrap();
+		assert!(game_state.is_insufficient_material(), "{}", fen);
+		assert_eq!(
+			game_state.game_over().unwrap().termination,
+			Termination::InsufficientMaterial
+		);
+	}
+
+	let sufficient = [
+		"4k3/8/8/8/8/8/8/1NN1K3 w - - 0 1",
+		"3bk3/8/8/8/8/8/8/3BK3 w - - 0 1",
+		"4k3/8/8/8/8/8/8/1NB1K3 w - - 0 1",
+		"4k3/8/8/8/8/8/4P3/4K3 w - - 0 1",
+		"4k3/8/8/8/8/8/8/R3K3 w - - 0 1",
+	];
+	for fen in &sufficient {
+		assert!(!GameState::from_fen(fen).unwrap().is_insufficient_material(), "{}", fen);
+	}
+}
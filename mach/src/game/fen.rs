@@ -0,0 +1,535 @@
+use std::fmt;
+
+use crate::game::*;
+
+/// The FEN of the standard starting position
+pub const STANDARD_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/// An error encountered while parsing a FEN string
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FenError {
+	/// The FEN did not have 6 space separated fields, or 4 if the move counters are left out
+	FieldCount(usize),
+	/// The piece placement field had more entries than a board can have rows
+	RowCount(usize),
+	/// The entry in the piece placement field for the given row did not describe as many squares as the
+	/// first row, or described more than a board can have columns
+	RowLength(Row),
+	/// A character in the piece placement field was not a piece letter or a digit
+	InvalidPiece(char),
+	/// The side to move was not `w` or `b`
+	InvalidTurn(String),
+	/// The castling rights were not `-` or a combination of `KQkq` and the columns of castling rooks
+	InvalidCastling(String),
+	/// The en passant target was not `-` or a square that the pawn which just moved skipped over
+	InvalidEnPassant(String),
+	/// The halfmove clock was not a non-negative integer
+	InvalidHalfmoveClock(String),
+	/// The fullmove number was not a positive integer
+	InvalidFullmoveNumber(String),
+}
+
+impl fmt::Display for FenError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			FenError::FieldCount(count) => write!(f, "expected 4 or 6 fields but found {}", count),
+			FenError::RowCount(count) => write!(f, "expected at most {} rows but found {}", MAX_SIZE, count),
+			FenError::RowLength(row) => write!(f, "row {} does not describe as many squares as the first row", row),
+			FenError::InvalidPiece(c) => write!(f, "'{}' is not a valid piece", c),
+			FenError::InvalidTurn(turn) => write!(f, "'{}' is not a valid side to move", turn),
+			FenError::InvalidCastling(castling) => write!(f, "'{}' is not valid castling availability", castling),
+			FenError::InvalidEnPassant(en_passant) => write!(f, "'{}' is not a valid en passant square", en_passant),
+			FenError::InvalidHalfmoveClock(clock) => write!(f, "'{}' is not a valid halfmove clock", clock),
+			FenError::InvalidFullmoveNumber(number) => write!(f, "'{}' is not a valid fullmove number", number),
+		}
+	}
+}
+
+impl std::error::Error for FenError {}
+
+impl GameState {
+	/// Parse a game state from Forsyth-Edwards Notation. The halfmove clock and fullmove number may be
+	/// left out, in which case they default to 0 and 1. The size of the board is given by the number of rows
+	/// and the number of squares the first of them describes, so that `10` stands for ten empty squares.
+	/// For Crazyhouse, the pieces in each side's pocket may follow the piece placement either in brackets,
+	/// as in `.../RNBQKBNR[Qn]`, or as a ninth row of nothing but pieces to drop after eight rows of eight
+	/// squares, and pieces that were promoted from pawns are marked with a `~` after them. An en passant target square is left out when no
+	/// pawn can legally capture onto it.
+	pub fn from_fen(fen: &str) -> Result<GameState, FenError> {
+		let fields: Vec<&str> = fen.split_whitespace().collect();
+		if fields.len() != 4 && fields.len() != 6 {
+			return Err(FenError::FieldCount(fields.len()));
+		}
+
+		let mut game_state = GameState::new();
+		let (board, pockets, promoted) = parse_placement(fields[0])?;
+		game_state.board = board;
+		game_state.pockets = pockets;
+		game_state.promoted = promoted;
+		game_state.turn = match fields[1] {
+			"w" => Color::White,
+			"b" => Color::Black,
+			turn => return Err(FenError::InvalidTurn(turn.to_owned())),
+		};
+		let (castling, castling_columns, chess960) = parse_castling(fields[2], &game_state.board)?;
+		game_state.castling = castling;
+		game_state.castling_columns = castling_columns;
+		game_state.chess960 = chess960;
+		game_state.en_passant = match fields[3] {
+			"-" => None,
+			en_passant => Some(
+				BoardIndex::parse(en_passant)
+					.filter(|&index| skipped_by_pawn(&game_state.board, index, game_state.turn.opposite()))
+					.ok_or_else(|| FenError::InvalidEnPassant(en_passant.to_owned()))?,
+			)
+			.filter(|&target| game_state.can_capture_en_passant(target)),
+		};
+		if fields.len() == 6 {
+			game_state.halfmove_clock = fields[4]
+				.parse()
+				.map_err(|_| FenError::InvalidHalfmoveClock(fields[4].to_owned()))?;
+			game_state.fullmove_number = fields[5]
+				.parse()
+				.ok()
+				.filter(|&number| number > 0)
+				.ok_or_else(|| FenError::InvalidFullmoveNumber(fields[5].to_owned()))?;
+		}
+		game_state.refresh_zobrist_key();
+		Ok(game_state)
+	}
+
+	/// Write this game state in Forsyth-Edwards Notation. In Chess960, castling rights are written as in
+	/// Shredder-FEN, by the columns of the rooks castled with, so that the position reads back as Chess960
+	/// even when its rooks and king stand where they do in standard chess. Pieces in either side's pocket are
+	/// written in brackets after the piece placement.
+	pub fn to_fen(&self) -> String {
+		self.write_fen(false)
+	}
+
+	/// Write this game state in Shredder-FEN, which is the same as Forsyth-Edwards Notation except that
+	/// castling rights are always written as the columns of the rooks castled with, such as `HAha`
+	pub fn to_shredder_fen(&self) -> String {
+		self.write_fen(true)
+	}
+
+	fn write_fen(&self, shredder: bool) -> String {
+		let mut fen = String::new();
+		let size = self.board.size();
+		for row in (0..size.rows).rev() {
+			let mut empty = 0;
+			for column in 0..size.columns {
+				match self
+					.board
+					.get_board_index(BoardIndex::new(Column::nth(column), Row::nth(row)))
+				{
+					Some(piece) => {
+						if empty > 0 {
+							fen.push_str(&empty.to_string());
+							empty = 0;
+						}
+						fen.push(piece.to_char());
+						if self
+							.promoted
+							.contains(BoardIndex::new(Column::nth(column), Row::nth(row)))
+						{
+							fen.push('~');
+						}
+					}
+					None => empty += 1,
+				}
+			}
+			if empty > 0 {
+				fen.push_str(&empty.to_string());
+			}
+			if row > 0 {
+				fen.push('/');
+			}
+		}
+		if !self.pockets.is_empty() {
+			fen.push('[');
+			for &color in &[Color::White, Color::Black] {
+				for &piece in POCKET_PIECES.iter().rev() {
+					for _ in 0..self.pockets.get(color).count(piece) {
+						fen.push(GamePiece::new(piece, color).to_char());
+					}
+				}
+			}
+			fen.push(']');
+		}
+
+		fen.push(' ');
+		fen.push(match self.turn {
+			Color::White => 'w',
+			Color::Black => 'b',
+		});
+
+		fen.push(' ');
+		let columns = self.castling_columns;
+		let castling = [
+			(Color::White, self.castling.white_kingside, columns.white_kingside, 'K'),
+			(
+				Color::White,
+				self.castling.white_queenside,
+				columns.white_queenside,
+				'Q',
+			),
+			(Color::Black, self.castling.black_kingside, columns.black_kingside, 'K'),
+			(
+				Color::Black,
+				self.castling.black_queenside,
+				columns.black_queenside,
+				'Q',
+			),
+		];
+		let castling: String = castling
+			.iter()
+			.filter(|&&(_, allowed, _, _)| allowed)
+			.map(|&(color, _, column, side)| {
+				let c = if shredder || self.chess960 {
+					column.to_char().to_ascii_uppercase()
+				} else {
+					side
+				};
+				match color {
+					Color::White => c,
+					Color::Black => c.to_ascii_lowercase(),
+				}
+			})
+			.collect();
+		if castling.is_empty() {
+			fen.push('-');
+		} else {
+			fen.push_str(&castling);
+		}
+
+		fen.push(' ');
+		match self.en_passant {
+			Some(en_passant) => fen.push_str(&en_passant.to_string()),
+			None => fen.push('-'),
+		}
+
+		fen.push_str(&format!(" {} {}", self.halfmove_clock, self.fullmove_number));
+		fen
+	}
+}
+
+/// Check whether a pawn of the given color stands just past the given square, as it does after moving
+/// two squares forward over it
+fn skipped_by_pawn(board: &GameBoard, index: BoardIndex, color: Color) -> bool {
+	board.size().contains(index)
+		&& board
+			.size()
+			.offset(index, 0, color.pawn_direction())
+			.and_then(|pawn| board.get_board_index(pawn))
+			== Some(GamePiece::new(Piece::Pawn, color))
+}
+
+/// Parse the piece placement, along with the pockets and promoted pieces written in Crazyhouse positions
+fn parse_placement(placement: &str) -> Result<(GameBoard, Pockets, Bitboard), FenError> {
+	let (placement, pocket) = match placement.find('[') {
+		Some(start) if placement.ends_with(']') => (&placement[..start], &placement[start + 1..placement.len() - 1]),
+		_ => (placement, ""),
+	};
+	let mut rows: Vec<&str> = placement.split('/').collect();
+	let pocket = if pocket.is_empty() && has_pocket_row(&rows) {
+		rows.pop().unwrap()
+	} else {
+		pocket
+	};
+	if rows.len() > MAX_SIZE as usize {
+		return Err(FenError::RowCount(rows.len()));
+	}
+
+	let row_count = rows.len() as u32;
+	let mut entries = Vec::with_capacity(rows.len());
+	for (i, entry) in rows.iter().enumerate() {
+		let row = Row::from_index(row_count - 1 - i as u32).ok_or(FenError::RowCount(rows.len()))?;
+		entries.push((row, parse_row(entry, row)?));
+	}
+	let columns = entries[0].1.len() as u32;
+	let size = BoardSize::new(columns, row_count).ok_or(FenError::RowLength(entries[0].0))?;
+
+	let mut board = GameBoard::with_size(size);
+	let mut promoted = Bitboard::EMPTY;
+	for (row, squares) in entries {
+		if squares.len() as u32 != columns {
+			return Err(FenError::RowLength(row));
+		}
+		for (column, square) in squares.into_iter().enumerate() {
+			if let Some((piece, was_promoted)) = square {
+				let column = Column::from_index(column as u32).ok_or(FenError::RowLength(row))?;
+				let index = BoardIndex::new(column, row);
+				*board.get_board_index_mut(index) = Some(piece);
+				if was_promoted {
+					promoted |= Bitboard::from_index(index);
+				}
+			}
+		}
+	}
+
+	let mut pockets = Pockets::default();
+	for c in pocket.chars() {
+		match GamePiece::from_char(c) {
+			Some(piece) if piece.piece != Piece::King => pockets.get_mut(piece.color).add(piece.piece),
+			_ => return Err(FenError::InvalidPiece(c)),
+		}
+	}
+	Ok((board, pockets, promoted))
+}
+
+/// Check whether the rows of a piece placement are those of a standard board followed by a ninth row of
+/// pieces in either pocket. Other boards need their pockets written in brackets, since a ninth row could
+/// otherwise be a row of the board.
+fn has_pocket_row(rows: &[&str]) -> bool {
+	let standard = BoardSize::STANDARD;
+	let is_standard_row =
+		|entry: &&str| parse_row(entry, Row::R8).is_ok_and(|squares| squares.len() == standard.columns as usize);
+	let is_pocket_piece = |c: char| GamePiece::from_char(c).is_some_and(|piece| piece.piece != Piece::King);
+	match rows.split_last() {
+		Some((pocket, board)) => {
+			board.len() == standard.rows as usize
+				&& board.iter().all(is_standard_row)
+				&& pocket.chars().all(is_pocket_piece)
+		}
+		None => false,
+	}
+}
+
+/// Parse the entry of the piece placement for a single row into the piece on each of its squares, along
+/// with whether that piece was promoted from a pawn. A run of empty squares may be written with more than
+/// one digit, as in `10`.
+fn parse_row(entry: &str, row: Row) -> Result<Vec<Option<(GamePiece, bool)>>, FenError> {
+	let mut squares = Vec::new();
+	let mut chars = entry.chars().peekable();
+	while let Some(c) = chars.next() {
+		if let Some(digit) = c.to_digit(10) {
+			let mut empty = digit;
+			while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+				empty = empty * 10 + digit;
+				chars.next();
+				if empty > MAX_SIZE {
+					break;
+				}
+			}
+			if squares.len() as u32 + empty > MAX_SIZE {
+				return Err(FenError::RowLength(row));
+			}
+			squares.extend((0..empty).map(|_| None));
+		} else if c == '~' && !squares.is_empty() {
+			if let Some(Some((_, promoted))) = squares.last_mut() {
+				*promoted = true;
+			}
+		} else {
+			let piece = GamePiece::from_char(c).ok_or(FenError::InvalidPiece(c))?;
+			if squares.len() as u32 >= MAX_SIZE {
+				return Err(FenError::RowLength(row));
+			}
+			squares.push(Some((piece, false)));
+		}
+	}
+	Ok(squares)
+}
+
+/// Parse the castling rights and the columns of the rooks castled with. Besides `KQkq`, the rights may be
+/// given as the columns of the rooks as in X-FEN and Shredder-FEN, which means the position is from
+/// Chess960, as does castling with a king or rook that is not on its standard square.
+fn parse_castling(castling: &str, board: &GameBoard) -> Result<(CastlingRights, CastlingColumns, bool), FenError> {
+	let size = board.size();
+	let mut rights = CastlingRights::none();
+	let mut columns = CastlingColumns::both(size.last_column(), Column::A);
+	let mut chess960 = false;
+	if castling == "-" {
+		return Ok((rights, columns, chess960));
+	}
+	let invalid = || FenError::InvalidCastling(castling.to_owned());
+	for c in castling.chars() {
+		let color = if c.is_ascii_uppercase() {
+			Color::White
+		} else {
+			Color::Black
+		};
+		let king = board
+			.pieces()
+			.find(|&(index, piece)| piece == GamePiece::new(Piece::King, color) && index.row == size.home_row(color))
+			.map_or(size.king_column(), |(index, _)| index.column);
+		let rook_column = match c.to_ascii_lowercase() {
+			'k' => outermost_rook(board, color, true).unwrap_or(size.last_column()),
+			'q' => outermost_rook(board, color, false).unwrap_or(Column::A),
+			c => {
+				chess960 = true;
+				Column::from_char(c)
+					.filter(|&column| column <= size.last_column())
+					.ok_or_else(invalid)?
+			}
+		};
+		let kingside = rook_column > king;
+		let (right, column) = if kingside {
+			(rights.kingside_mut(color), columns.kingside_mut(color))
+		} else {
+			(rights.queenside_mut(color), columns.queenside_mut(color))
+		};
+		if *right {
+			return Err(invalid());
+		}
+		*right = true;
+		*column = rook_column;
+		if king != size.king_column() || (rook_column != size.last_column() && rook_column != Column::A) {
+			chess960 = true;
+		}
+	}
+	Ok((rights, columns, chess960))
+}
+
+/// Find the column of the rook of the given color on its home row that is furthest from its king towards
+/// the kingside or queenside
+fn outermost_rook(board: &GameBoard, color: Color, kingside: bool) -> Option<Column> {
+	let rook = GamePiece::new(Piece::Rook, color);
+	let king = GamePiece::new(Piece::King, color);
+	let size = board.size();
+	let mut columns: Vec<Column> = (0..size.columns).map(Column::nth).collect();
+	if kingside {
+		columns.reverse();
+	}
+	columns
+		.into_iter()
+		.map(|column| {
+			(
+				column,
+				board.get_board_index(BoardIndex::new(column, size.home_row(color))),
+			)
+		})
+		.take_while(|&(_, piece)| piece != Some(king))
+		.find(|&(_, piece)| piece == Some(rook))
+		.map(|(column, _)| column)
+}
+
+#[test]
+fn fen_round_trip_test() {
+	assert_eq!(GameState::from_fen(STANDARD_FEN), Ok(GameState::standard()));
+	assert_eq!(GameState::standard().to_fen(), STANDARD_FEN);
+
+	let fens = [
+		"r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+		"8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+		"rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+		"r3k2r/8/8/8/8/8/8/R3K2R b Kq - 17 42",
+		"rnabqkbcnr/pppppppppp/10/10/10/10/PPPPPPPPPP/RNABQKBCNR w KQkq - 0 1",
+		"r8r/1nbqkcabn1/pppppppppp/10/10/10/10/PPPPPPPPPP/1NBQKCABN1/R8R w - - 0 1",
+		"rnbqk/ppppp/5/PPPPP/RNBQK w - - 0 1",
+	];
+	for fen in &fens {
+		assert_eq!(&GameState::from_fen(fen).unwrap().to_fen(), fen);
+	}
+
+	// An en passant square no pawn can capture onto is left out
+	let unusable = GameState::from_fen("rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR w KQkq c6 0 2").unwrap();
+	assert_eq!(unusable.en_passant, None);
+	assert_eq!(
+		unusable.to_fen(),
+		"rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2"
+	);
+
+	// The size of the board follows from the piece placement
+	let grand = GameState::from_fen("r8r/10/10/10/10/10/10/10/10/R8R w - - 0 1").unwrap();
+	assert_eq!(grand.board.size(), BoardSize::new(10, 10).unwrap());
+	assert_eq!(grand.castling_columns, CastlingColumns::both(Column::J, Column::A));
+
+	// A ninth row after a standard board holds the pockets, but only if it is made up of pieces to drop
+	let crazyhouse = GameState::from_fen("4k3/8/8/8/8/8/8/4K3/Pn w - - 0 1").unwrap();
+	assert_eq!(crazyhouse.board.size(), BoardSize::STANDARD);
+	assert_eq!(crazyhouse.to_fen(), "4k3/8/8/8/8/8/8/4K3[Pn] w - - 0 1");
+	let tall = GameState::from_fen("4k3/8/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+	assert_eq!(tall.board.size(), BoardSize::new(8, 9).unwrap());
+	assert!(tall.pockets.is_empty());
+
+	let short = GameState::from_fen("8/8/8/8/8/8/8/K6k b - -").unwrap();
+	assert_eq!(short.to_fen(), "8/8/8/8/8/8/8/K6k b - - 0 1");
+}
+
+#[test]
+fn chess960_fen_test() {
+	let game_state = GameState::chess960(0);
+	let fen = "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w HFhf - 0 1";
+	assert_eq!(game_state.to_fen(), fen);
+	assert_eq!(game_state.to_shredder_fen(), fen);
+	assert_eq!(GameState::from_fen(fen), Ok(game_state.clone()));
+	assert_eq!(
+		GameState::from_fen("bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1"),
+		Ok(game_state)
+	);
+
+	// The standard layout reads back as Chess960 because its rights are written by column
+	let game_state = GameState::chess960(STANDARD_CHESS960_POSITION);
+	assert_eq!(
+		game_state.to_fen(),
+		"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 1"
+	);
+	for n in 0..CHESS960_POSITIONS {
+		let game_state = GameState::chess960(n);
+		assert_eq!(GameState::from_fen(&game_state.to_fen()), Ok(game_state));
+	}
+
+	// X-FEN names a rook that is not the outermost one on its side by its column
+	let game_state = GameState::from_fen("rk2r2r/8/8/8/8/8/8/RK2R2R w EQeq - 0 1").unwrap();
+	assert!(game_state.chess960);
+	assert_eq!(game_state.castling_columns.kingside(Color::White), Column::E);
+	assert_eq!(game_state.to_fen(), "rk2r2r/8/8/8/8/8/8/RK2R2R w EAea - 0 1");
+	let outermost = GameState::from_fen("rk2r2r/8/8/8/8/8/8/RK2R2R w KQkq - 0 1").unwrap();
+	assert_eq!(outermost.castling_columns.kingside(Color::White), Column::H);
+
+	// Each side may castle with rooks on different columns
+	let game_state = GameState::from_fen("1r2k2r/8/8/8/8/8/8/R3K2R w HAkb - 0 1").unwrap();
+	assert!(game_state.chess960);
+	assert_eq!(game_state.castling_columns.queenside(Color::White), Column::A);
+	assert_eq!(game_state.castling_columns.queenside(Color::Black), Column::B);
+	assert_eq!(game_state.to_fen(), "1r2k2r/8/8/8/8/8/8/R3K2R w HAhb - 0 1");
+
+	let standard = GameState::from_fen(STANDARD_FEN).unwrap();
+	assert!(!standard.chess960);
+	assert_eq!(standard.to_fen(), STANDARD_FEN);
+	assert_eq!(
+		standard.to_shredder_fen(),
+		"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 1"
+	);
+}
+
+#[test]
+fn fen_error_test() {
+	assert_eq!(
+		GameState::from_fen("8/8/8/8/8/8/8/8/8/8/8 w - - 0 1"),
+		Err(FenError::RowCount(11))
+	);
+	assert_eq!(
+		GameState::from_fen("8/8/8/8/8/8/8/9 w - - 0 1"),
+		Err(FenError::RowLength(Row::R1))
+	);
+	assert_eq!(
+		GameState::from_fen("8/8/8/8/8/8/8/KKKKKKKKK w - - 0 1"),
+		Err(FenError::RowLength(Row::R1))
+	);
+	assert_eq!(
+		GameState::from_fen("11/11/11/11/11/11/11/11 w - - 0 1"),
+		Err(FenError::RowLength(Row::R8))
+	);
+	assert_eq!(
+		GameState::from_fen("8/8/8/8/8/8/8/7x w - - 0 1"),
+		Err(FenError::InvalidPiece('x'))
+	);
+	assert_eq!(
+		GameState::from_fen("8/8/8/8/8/8/8/8 x - - 0 1"),
+		Err(FenError::InvalidTurn("x".to_owned()))
+	);
+	assert_eq!(
+		GameState::from_fen("8/8/8/8/8/8/8/8 w KK - 0 1"),
+		Err(FenError::InvalidCastling("KK".to_owned()))
+	);
+	assert_eq!(
+		GameState::from_fen("8/8/8/8/8/8/8/8 w - e4 0 1"),
+		Err(FenError::InvalidEnPassant("e4".to_owned()))
+	);
+	assert_eq!(
+		GameState::from_fen("8/8/8/8/8/8/8/8 w - - 0 0"),
+		Err(FenError::InvalidFullmoveNumber("0".to_owned()))
+	);
+	assert_eq!(GameState::from_fen("8/8/8/8/8/8/8/8 w -"), Err(FenError::FieldCount(3)));
+}
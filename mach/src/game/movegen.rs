@@ -0,0 +1,830 @@
+use serde::{Deserialize, Serialize};
+
+use crate::game::*;
+
+pub(crate) const KNIGHT_OFFSETS: [(i32, i32); 8] =
+	[(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)];
+pub(crate) const KING_OFFSETS: [(i32, i32); 8] = [(0, 1), (1, 1), (1, 0), (1, -1), (0, -1), (-1, -1), (-1, 0), (-1, 1)];
+pub(crate) const ROOK_DIRECTIONS: [(i32, i32); 4] = [(0, 1), (1, 0), (0, -1), (-1, 0)];
+pub(crate) const BISHOP_DIRECTIONS: [(i32, i32); 4] = [(1, 1), (1, -1), (-1, -1), (-1, 1)];
+
+pub(crate) const PROMOTION_PIECES: [Piece; 4] = [Piece::Queen, Piece::Rook, Piece::Bishop, Piece::Knight];
+const FAIRY_PROMOTION_PIECES: [Piece; 6] = [
+	Piece::Queen,
+	Piece::Chancellor,
+	Piece::Archbishop,
+	Piece::Rook,
+	Piece::Bishop,
+	Piece::Knight,
+];
+
+/// How pawns move and promote, which differs between variants played on boards of different sizes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PawnRules {
+	/// The number of rows between the row pawns may move two squares forward from and the end of the
+	/// board their side starts at, or `None` if pawns never move two squares
+	pub double_step_row: Option<u32>,
+	/// The number of rows at the far end of the board a pawn may promote on. A pawn must promote when it
+	/// reaches the last row, and may choose to on any of the others.
+	pub promotion_rows: u32,
+	/// Whether pawns may also promote to an archbishop or a chancellor
+	pub fairy_promotions: bool,
+	/// Whether a pawn may only promote to a kind of piece that its side has fewer of than it starts
+	/// Grand Chess with, as if the pawn were replacing a captured piece
+	pub limited_promotion: bool,
+}
+
+impl PawnRules {
+	/// The rules of standard chess
+	pub const STANDARD: PawnRules = PawnRules {
+		double_step_row: Some(1),
+		promotion_rows: 1,
+		fairy_promotions: false,
+		limited_promotion: false,
+	};
+	/// The rules of Capablanca chess, in which pawns may also promote to an archbishop or a chancellor
+	pub const CAPABLANCA: PawnRules = PawnRules {
+		fairy_promotions: true,
+		..PawnRules::STANDARD
+	};
+	/// The rules of Grand Chess, in which pawns start on the third row and promote on any of the last three
+	/// rows, but only to a kind of piece their side has lost
+	pub const GRAND: PawnRules = PawnRules {
+		double_step_row: Some(2),
+		promotion_rows: 3,
+		fairy_promotions: true,
+		limited_promotion: true,
+	};
+	/// The rules of Minichess, in which pawns never move two squares
+	pub const MINICHESS: PawnRules = PawnRules {
+		double_step_row: None,
+		..PawnRules::STANDARD
+	};
+
+	/// The rules of the variant played on a board of the given size: Capablanca chess on ten columns and
+	/// eight rows, Grand Chess on ten of each and Minichess on five of each. Boards of any other size use
+	/// the rules of standard chess.
+	pub fn for_size(size: BoardSize) -> PawnRules {
+		match (size.columns, size.rows) {
+			(10, 8) => PawnRules::CAPABLANCA,
+			(10, 10) => PawnRules::GRAND,
+			(5, 5) => PawnRules::MINICHESS,
+			_ => PawnRules::STANDARD,
+		}
+	}
+
+	/// The pieces a pawn may promote to, ignoring `limited_promotion`
+	pub fn promotion_pieces(self) -> &'static [Piece] {
+		if self.fairy_promotions {
+			&FAIRY_PROMOTION_PIECES
+		} else {
+			&PROMOTION_PIECES
+		}
+	}
+
+	/// The pieces a pawn may promote to, given a way to count the pieces of each kind its side has on the
+	/// board
+	pub(crate) fn promotions(self, count: impl Fn(Piece) -> u32) -> Vec<Piece> {
+		self.promotion_pieces()
+			.iter()
+			.copied()
+			.filter(|&piece| {
+				let army = match piece {
+					Piece::Queen | Piece::Archbishop | Piece::Chancellor => 1,
+					_ => 2,
+				};
+				!self.limited_promotion || count(piece) < army
+			})
+			.collect()
+	}
+}
+
+/// A single move of a piece from one square to another, or a drop of a piece from the pocket of the side
+/// to move onto an empty square
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Move {
+	pub start: BoardIndex,
+	pub end: BoardIndex,
+	/// The piece a pawn is promoted to when it reaches the last row. Must be present for those moves
+	/// and absent for every other move.
+	#[serde(default)]
+	pub promotion: Option<Piece>,
+	/// The piece taken from the pocket of the side to move if this move drops a piece, in which case
+	/// `start` and `end` are both the square the piece is dropped on
+	#[serde(default)]
+	pub drop: Option<Piece>,
+}
+
+impl Move {
+	pub fn new(start: BoardIndex, end: BoardIndex) -> Self {
+		Self {
+			start,
+			end,
+			promotion: None,
+			drop: None,
+		}
+	}
+
+	pub fn with_promotion(start: BoardIndex, end: BoardIndex, promotion: Piece) -> Self {
+		Self {
+			start,
+			end,
+			promotion: Some(promotion),
+			drop: None,
+		}
+	}
+
+	/// Create a move dropping a piece from the pocket of the side to move onto the given square
+	pub fn new_drop(piece: Piece, index: BoardIndex) -> Self {
+		Self {
+			start: index,
+			end: index,
+			promotion: None,
+			drop: Some(piece),
+		}
+	}
+
+	pub fn is_drop(self) -> bool {
+		self.drop.is_some()
+	}
+}
+
+/// The reason a move could not be made
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MoveError {
+	/// There is no piece on the start square of the move
+	EmptySquare,
+	/// The piece on the start square belongs to the side whose turn it is not
+	WrongColor,
+	/// The piece can not make this move, or making it would leave its own king in check
+	IllegalMove,
+	/// The start or end square of the move is not on the board
+	OffBoard,
+}
+
+/// Where the king and rook move from and to when castling
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Castling {
+	pub king_start: BoardIndex,
+	pub king_end: BoardIndex,
+	pub rook_start: BoardIndex,
+	pub rook_end: BoardIndex,
+}
+
+impl Castling {
+	/// Describe castling between a king and a rook on the same row of a board of the given size. Whatever
+	/// columns they start on, the king ends on the second to last column and the rook on the one before it
+	/// when castling kingside, which are the G and F columns of a standard board, and the king ends on the
+	/// C column and the rook on the D column when castling queenside.
+	pub fn new(king_start: BoardIndex, rook_start: BoardIndex, size: BoardSize) -> Self {
+		let last_column = u32::from(size.last_column());
+		let (king_column, rook_column) = if rook_start.column > king_start.column {
+			(Column::nth(last_column - 1), Column::nth(last_column - 2))
+		} else {
+			(Column::C, Column::D)
+		};
+		Self {
+			king_start,
+			king_end: BoardIndex::new(king_column, king_start.row),
+			rook_start,
+			rook_end: BoardIndex::new(rook_column, king_start.row),
+		}
+	}
+
+	/// Check whether this is castling on the kingside
+	pub fn is_kingside(self) -> bool {
+		self.rook_start.column > self.king_start.column
+	}
+
+	/// Find how the pieces on a board move if the given move castles. The move castles if a king moves
+	/// onto a rook of its own color, as castling is written in Chess960, or if a king moves two squares
+	/// along its row, as castling is written in standard chess.
+	pub(crate) fn from_board(mv: Move, size: BoardSize, get: impl Fn(BoardIndex) -> Option<GamePiece>) -> Option<Self> {
+		let piece = get(mv.start).filter(|piece| piece.piece == Piece::King)?;
+		if get(mv.end) == Some(GamePiece::new(Piece::Rook, piece.color)) {
+			return Some(Castling::new(mv.start, mv.end, size));
+		}
+		let columns = u32::from(mv.end.column) as i32 - u32::from(mv.start.column) as i32;
+		if columns.abs() >= 2 && mv.start.row == mv.end.row {
+			let rook_column = if columns > 0 { size.last_column() } else { Column::A };
+			return Some(Castling::new(
+				mv.start,
+				BoardIndex::new(rook_column, mv.start.row),
+				size,
+			));
+		}
+		None
+	}
+}
+
+impl Color {
+	/// The direction along the rows that pawns of this color advance in
+	pub fn pawn_direction(self) -> i32 {
+		match self {
+			Color::White => 1,
+			Color::Black => -1,
+		}
+	}
+}
+
+impl GameBoard {
+	/// Generate every move that the pieces of the given color could make according to how each piece
+	/// moves and the given rules for pawns, without considering whether the move would leave that color's
+	/// king in check.
+	pub fn pseudo_legal_moves(&self, color: Color, pawn_rules: PawnRules) -> Vec<Move> {
+		let promotions = pawn_rules.promotions(|piece| {
+			self.pieces()
+				.filter(|&(_, found)| found == GamePiece::new(piece, color))
+				.count() as u32
+		});
+		let mut moves = Vec::new();
+		for (index, piece) in self.pieces() {
+			if piece.color == color {
+				self.piece_moves(index, piece, pawn_rules, &promotions, &mut moves);
+			}
+		}
+		moves
+	}
+
+	fn piece_moves(
+		&self,
+		start: BoardIndex,
+		piece: GamePiece,
+		pawn_rules: PawnRules,
+		promotions: &[Piece],
+		moves: &mut Vec<Move>,
+	) {
+		match piece.piece {
+			Piece::Pawn => self.pawn_moves(start, piece.color, pawn_rules, promotions, moves),
+			Piece::Knight => self.step_moves(start, piece.color, &KNIGHT_OFFSETS, moves),
+			Piece::King => self.step_moves(start, piece.color, &KING_OFFSETS, moves),
+			Piece::Bishop => self.slide_moves(start, piece.color, &BISHOP_DIRECTIONS, moves),
+			Piece::Rook => self.slide_moves(start, piece.color, &ROOK_DIRECTIONS, moves),
+			Piece::Queen => {
+				self.slide_moves(start, piece.color, &BISHOP_DIRECTIONS, moves);
+				self.slide_moves(start, piece.color, &ROOK_DIRECTIONS, moves);
+			}
+			Piece::Archbishop => {
+				self.slide_moves(start, piece.color, &BISHOP_DIRECTIONS, moves);
+				self.step_moves(start, piece.color, &KNIGHT_OFFSETS, moves);
+			}
+			Piece::Chancellor => {
+				self.slide_moves(start, piece.color, &ROOK_DIRECTIONS, moves);
+				self.step_moves(start, piece.color, &KNIGHT_OFFSETS, moves);
+			}
+		}
+	}
+
+	fn pawn_moves(
+		&self,
+		start: BoardIndex,
+		color: Color,
+		pawn_rules: PawnRules,
+		promotions: &[Piece],
+		moves: &mut Vec<Move>,
+	) {
+		let size = self.size();
+		let direction = color.pawn_direction();
+		// A pawn reaching the last row must promote, so each of those moves is made once for every piece
+		// it could promote to. On the rows before it the pawn may also choose not to promote.
+		let mut push = |end: BoardIndex| {
+			let rows_ahead = size.rows_ahead(color, end.row);
+			if rows_ahead < pawn_rules.promotion_rows {
+				moves.extend(
+					promotions
+						.iter()
+						.map(|&promotion| Move::with_promotion(start, end, promotion)),
+				);
+			}
+			if rows_ahead > 0 {
+				moves.push(Move::new(start, end));
+			}
+		};
+		let double_step_row = pawn_rules.double_step_row.map(|rows| size.row_from(color, rows));
+		if let Some(single) = size.offset(start, 0, direction) {
+			if self.get_board_index(single).is_none() {
+				push(single);
+				if Some(start.row) == double_step_row {
+					if let Some(double) = size.offset(single, 0, direction) {
+						if self.get_board_index(double).is_none() {
+							push(double);
+						}
+					}
+				}
+			}
+		}
+		for &columns in &[-1, 1] {
+			if let Some(end) = size.offset(start, columns, direction) {
+				match self.get_board_index(end) {
+					Some(target) if target.color != color => push(end),
+					_ => {}
+				}
+			}
+		}
+	}
+
+	fn step_moves(&self, start: BoardIndex, color: Color, offsets: &[(i32, i32)], moves: &mut Vec<Move>) {
+		for &(columns, rows) in offsets {
+			if let Some(end) = self.size().offset(start, columns, rows) {
+				match self.get_board_index(end) {
+					Some(target) if target.color == color => {}
+					_ => moves.push(Move::new(start, end)),
+				}
+			}
+		}
+	}
+
+	fn slide_moves(&self, start: BoardIndex, color: Color, directions: &[(i32, i32)], moves: &mut Vec<Move>) {
+		for &(columns, rows) in directions {
+			let mut current = start;
+			while let Some(end) = self.size().offset(current, columns, rows) {
+				match self.get_board_index(end) {
+					None => moves.push(Move::new(start, end)),
+					Some(target) => {
+						if target.color != color {
+							moves.push(Move::new(start, end));
+						}
+						break;
+					}
+				}
+				current = end;
+			}
+		}
+	}
+
+	/// Check whether any piece of color `by` attacks the given square
+	pub fn is_attacked(&self, index: BoardIndex, by: Color) -> bool {
+		let size = self.size();
+		let is = |index: Option<BoardIndex>, pieces: &[Piece]| match index.and_then(|i| self.get_board_index(i)) {
+			Some(found) => found.color == by && pieces.contains(&found.piece),
+			None => false,
+		};
+
+		let pawn_row = -by.pawn_direction();
+		if is(size.offset(index, -1, pawn_row), &[Piece::Pawn]) || is(size.offset(index, 1, pawn_row), &[Piece::Pawn]) {
+			return true;
+		}
+		if KNIGHT_OFFSETS.iter().any(|&(columns, rows)| {
+			is(
+				size.offset(index, columns, rows),
+				&[Piece::Knight, Piece::Archbishop, Piece::Chancellor],
+			)
+		}) {
+			return true;
+		}
+		if KING_OFFSETS
+			.iter()
+			.any(|&(columns, rows)| is(size.offset(index, columns, rows), &[Piece::King]))
+		{
+			return true;
+		}
+
+		let slides = |directions: &[(i32, i32)], pieces: &[Piece]| {
+			directions.iter().any(|&(columns, rows)| {
+				let mut current = index;
+				while let Some(next) = size.offset(current, columns, rows) {
+					if let Some(found) = self.get_board_index(next) {
+						return found.color == by && pieces.contains(&found.piece);
+					}
+					current = next;
+				}
+				false
+			})
+		};
+		slides(&ROOK_DIRECTIONS, &[Piece::Rook, Piece::Queen, Piece::Chancellor])
+			|| slides(&BISHOP_DIRECTIONS, &[Piece::Bishop, Piece::Queen, Piece::Archbishop])
+	}
+
+	/// Find the square the king of the given color is on, if it is on the board
+	pub fn find_king(&self, color: Color) -> Option<BoardIndex> {
+		self.pieces()
+			.find(|&(_, piece)| piece == GamePiece::new(Piece::King, color))
+			.map(|(index, _)| index)
+	}
+
+	/// Check whether the king of the given color is attacked by any of the opponent's pieces. A board
+	/// without a king of that color is never considered to be in check.
+	pub fn is_king_attacked(&self, color: Color) -> bool {
+		match self.find_king(color) {
+			Some(king) => self.is_attacked(king, color.opposite()),
+			None => false,
+		}
+	}
+}
+
+impl GameState {
+	/// Generate every legal move for the side whose turn it is
+	pub fn legal_moves(&self) -> Vec<Move> {
+		let board = BitGameBoard::from(&self.board);
+		let mut moves = self.pseudo_legal_moves_on(&board);
+		moves.retain(|&mv| keeps_king_safe(&board, mv, self.turn));
+		moves
+	}
+
+	/// Check whether the side whose turn it is has at least one legal move
+	pub fn has_legal_moves(&self) -> bool {
+		let board = BitGameBoard::from(&self.board);
+		self.pseudo_legal_moves_on(&board)
+			.into_iter()
+			.any(|mv| keeps_king_safe(&board, mv, self.turn))
+	}
+
+	/// Check whether the given move is legal for the side whose turn it is
+	pub fn is_legal(&self, mv: Move) -> bool {
+		self.validate_move(mv).is_ok()
+	}
+
+	/// Check whether the given move is legal for the side whose turn it is, giving the reason if it is
+	/// not
+	pub fn validate_move(&self, mv: Move) -> Result<(), MoveError> {
+		let size = self.board.size();
+		if !size.contains(mv.start) || !size.contains(mv.end) {
+			return Err(MoveError::OffBoard);
+		}
+		if !mv.is_drop() {
+			let piece = self.board.get_board_index(mv.start).ok_or(MoveError::EmptySquare)?;
+			if piece.color != self.turn {
+				return Err(MoveError::WrongColor);
+			}
+		}
+		if self.legal_moves().contains(&mv) {
+			Ok(())
+		} else {
+			Err(MoveError::IllegalMove)
+		}
+	}
+
+	/// Generate every move the side whose turn it is could make, including castling, capturing en
+	/// passant and dropping pieces from its pocket, without considering whether the move would leave its own king in check
+	pub fn pseudo_legal_moves(&self) -> Vec<Move> {
+		self.pseudo_legal_moves_on(&BitGameBoard::from(&self.board))
+	}
+
+	fn pseudo_legal_moves_on(&self, board: &BitGameBoard) -> Vec<Move> {
+		let mut moves = board.pseudo_legal_moves(self.turn, self.pawn_rules());
+		self.castling_moves(board, &mut moves);
+		self.en_passant_moves(board, &mut moves);
+		self.drop_moves(board, &mut moves);
+		moves
+	}
+
+	/// Generate the moves capturing a pawn that skipped over the en passant target square on the
+	/// previous move
+	fn en_passant_moves(&self, board: &BitGameBoard, moves: &mut Vec<Move>) {
+		if let Some(target) = self.en_passant {
+			moves.extend(self.en_passant_captures(target, |index| board.get_board_index(index)));
+		}
+	}
+
+	/// Get the moves of the pawns of the side whose turn it is that stand next to the pawn that skipped
+	/// over the given square, given a way to look up the piece on a square
+	fn en_passant_captures(
+		&self,
+		target: BoardIndex,
+		get: impl Fn(BoardIndex) -> Option<GamePiece>,
+	) -> impl Iterator<Item = Move> {
+		let pawn = GamePiece::new(Piece::Pawn, self.turn);
+		let row = -self.turn.pawn_direction();
+		let size = self.board.size();
+		[-1, 1]
+			.iter()
+			.filter_map(move |&columns| size.offset(target, columns, row))
+			.filter(move |&start| get(start) == Some(pawn))
+			.map(move |start| Move::new(start, target))
+	}
+
+	/// Check whether the side whose turn it is could legally capture en passant onto the given square if
+	/// a pawn had just skipped over it. The en passant target square is only kept when this is the case.
+	pub(crate) fn can_capture_en_passant(&self, target: BoardIndex) -> bool {
+		let captures: Vec<Move> = self
+			.en_passant_captures(target, |index| self.board.get_board_index(index))
+			.collect();
+		if captures.is_empty() {
+			return false;
+		}
+		let board = BitGameBoard::from(&self.board);
+		captures.into_iter().any(|mv| keeps_king_safe(&board, mv, self.turn))
+	}
+
+	/// Find how the king and rook move if a move by the given piece castles, given the castling rights
+	/// in the position before the move. In standard chess castling is written as the king moving two
+	/// squares towards the rook it castles with, and in Chess960 as the king moving onto that rook.
+	pub fn move_castling(&self, mv: Move, piece: GamePiece, rights: CastlingRights) -> Option<Castling> {
+		let size = self.board.size();
+		let home_row = size.home_row(piece.color);
+		if piece.piece != Piece::King || mv.start.row != home_row || mv.end.row != home_row {
+			return None;
+		}
+		let sides = [
+			(
+				rights.kingside(piece.color),
+				self.castling_columns.kingside(piece.color),
+			),
+			(
+				rights.queenside(piece.color),
+				self.castling_columns.queenside(piece.color),
+			),
+		];
+		sides
+			.iter()
+			.filter(|&&(allowed, _)| allowed)
+			.map(|&(_, rook_column)| Castling::new(mv.start, BoardIndex::new(rook_column, home_row), size))
+			.find(|castling| {
+				if self.chess960 {
+					mv.end == castling.rook_start
+				} else {
+					mv.end == castling.king_end && mv.start.column == size.king_column()
+				}
+			})
+	}
+
+	/// Generate the castling moves available to the side whose turn it is. The king may not castle out
+	/// of, through or into check, and every square the king and rook pass over or end on must be empty
+	/// apart from the king and rook themselves.
+	fn castling_moves(&self, board: &BitGameBoard, moves: &mut Vec<Move>) {
+		let color = self.turn;
+		let size = board.size();
+		let home_row = size.home_row(color);
+		let king = match board.find_king(color) {
+			Some(king) if king.row == home_row => king,
+			_ => return,
+		};
+		let sides = [
+			(self.castling.kingside(color), self.castling_columns.kingside(color)),
+			(self.castling.queenside(color), self.castling_columns.queenside(color)),
+		];
+		for &(allowed, rook_column) in &sides {
+			if !allowed {
+				continue;
+			}
+			let rook = BoardIndex::new(rook_column, home_row);
+			if board.get_board_index(rook) != Some(GamePiece::new(Piece::Rook, color)) {
+				continue;
+			}
+			let castling = Castling::new(king, rook, size);
+			if !self.chess960 && king.column != size.king_column() {
+				continue;
+			}
+
+			let columns = [
+				king.column,
+				rook.column,
+				castling.king_end.column,
+				castling.rook_end.column,
+			];
+			let (first, last) = (*columns.iter().min().unwrap(), *columns.iter().max().unwrap());
+			let clear = (u32::from(first)..=u32::from(last))
+				.map(|column| BoardIndex::new(Column::nth(column), home_row))
+				.all(|index| index == king || index == rook || board.get_board_index(index).is_none());
+
+			let (first, last) = if king.column < castling.king_end.column {
+				(king.column, castling.king_end.column)
+			} else {
+				(castling.king_end.column, king.column)
+			};
+			let opponent = color.opposite();
+			let safe = (u32::from(first)..=u32::from(last))
+				.map(|column| BoardIndex::new(Column::nth(column), home_row))
+				.all(|index| !board.is_attacked(index, opponent));
+
+			if clear && safe {
+				moves.push(Move::new(king, if self.chess960 { rook } else { castling.king_end }));
+			}
+		}
+	}
+}
+
+/// Check whether making a move leaves the king of the color making it safe from attack
+fn keeps_king_safe(board: &BitGameBoard, mv: Move, color: Color) -> bool {
+	let mut board = *board;
+	match mv.drop {
+		Some(piece) => board.set_board_index(mv.end, Some(GamePiece::new(piece, color))),
+		None => {
+			board.make_move(mv);
+		}
+	}
+	!board.is_king_attacked(color)
+}
+
+#[test]
+fn standard_opening_moves_test() {
+	let mut game_state = GameState::new();
+	game_state.board.set_standard();
+	assert_eq!(game_state.legal_moves().len(), 20);
+	game_state.turn = Color::Black;
+	assert_eq!(game_state.legal_moves().len(), 20);
+}
+
+#[test]
+fn pinned_piece_test() {
+	use self::Column::*;
+	use self::Row::*;
+
+	let mut game_state = GameState::new();
+	let board = &mut game_state.board;
+	*board.get_board_index_mut(BoardIndex::new(E, R1)) = Some(GamePiece::new(Piece::King, Color::White));
+	*board.get_board_index_mut(BoardIndex::new(E, R2)) = Some(GamePiece::new(Piece::Rook, Color::White));
+	*board.get_board_index_mut(BoardIndex::new(E, R8)) = Some(GamePiece::new(Piece::Rook, Color::Black));
+	*board.get_board_index_mut(BoardIndex::new(A, R8)) = Some(GamePiece::new(Piece::King, Color::Black));
+
+	let moves = game_state.legal_moves();
+	let rook_moves: Vec<_> = moves.iter().filter(|mv| mv.start == BoardIndex::new(E, R2)).collect();
+	// The pinned rook may only move along the file of the pin
+	assert_eq!(rook_moves.len(), 6);
+	assert!(rook_moves.iter().all(|mv| mv.end.column == E));
+	assert!(game_state.is_legal(Move::new(BoardIndex::new(E, R2), BoardIndex::new(E, R8))));
+	assert!(!game_state.is_legal(Move::new(BoardIndex::new(E, R2), BoardIndex::new(D, R2))));
+}
+
+#[test]
+fn validate_move_test() {
+	use self::Column::*;
+	use self::Row::*;
+
+	let mut game_state = GameState::new();
+	game_state.board.set_standard();
+	let mv = |start, end| Move::new(start, end);
+	assert_eq!(
+		game_state.validate_move(mv(BoardIndex::new(E, R2), BoardIndex::new(E, R4))),
+		Ok(())
+	);
+	assert_eq!(
+		game_state.validate_move(mv(BoardIndex::new(E, R4), BoardIndex::new(E, R5))),
+		Err(MoveError::EmptySquare)
+	);
+	assert_eq!(
+		game_state.validate_move(mv(BoardIndex::new(E, R7), BoardIndex::new(E, R5))),
+		Err(MoveError::WrongColor)
+	);
+	assert_eq!(
+		game_state.validate_move(mv(BoardIndex::new(E, R2), BoardIndex::new(E, R5))),
+		Err(MoveError::IllegalMove)
+	);
+}
+
+#[test]
+fn castling_test() {
+	use self::Column::*;
+	use self::Row::*;
+
+	let mut game_state = GameState::new();
+	game_state.castling = CastlingRights::all();
+	let board = &mut game_state.board;
+	*board.get_board_index_mut(BoardIndex::new(E, R1)) = Some(GamePiece::new(Piece::King, Color::White));
+	*board.get_board_index_mut(BoardIndex::new(A, R1)) = Some(GamePiece::new(Piece::Rook, Color::White));
+	*board.get_board_index_mut(BoardIndex::new(H, R1)) = Some(GamePiece::new(Piece::Rook, Color::White));
+	*board.get_board_index_mut(BoardIndex::new(E, R8)) = Some(GamePiece::new(Piece::King, Color::Black));
+	let kingside = Move::new(BoardIndex::new(E, R1), BoardIndex::new(G, R1));
+	let queenside = Move::new(BoardIndex::new(E, R1), BoardIndex::new(C, R1));
+	assert!(game_state.is_legal(kingside));
+	assert!(game_state.is_legal(queenside));
+
+	// A bishop attacking f1 stops the king from passing through it
+	*game_state.board.get_board_index_mut(BoardIndex::new(B, R5)) = Some(GamePiece::new(Piece::Bishop, Color::Black));
+	assert!(!game_state.is_legal(kingside));
+	assert!(game_state.is_legal(queenside));
+
+	// A piece between the king and rook blocks castling, even if the king does not pass over it
+	*game_state.board.get_board_index_mut(BoardIndex::new(B, R1)) = Some(GamePiece::new(Piece::Knight, Color::White));
+	assert!(!game_state.is_legal(queenside));
+	*game_state.board.get_board_index_mut(BoardIndex::new(B, R1)) = None;
+
+	game_state.castling.white_queenside = false;
+	assert!(!game_state.is_legal(queenside));
+	game_state.castling.white_queenside = true;
+
+	game_state.apply_move(queenside).unwrap();
+	assert_eq!(
+		game_state.board.get_board_index(BoardIndex::new(D, R1)),
+		Some(GamePiece::new(Piece::Rook, Color::White))
+	);
+	assert_eq!(game_state.board.get_board_index(BoardIndex::new(A, R1)), None);
+	assert!(!game_state.castling.white_kingside && !game_state.castling.white_queenside);
+}
+
+#[test]
+fn chess960_castling_test() {
+	let game_state = GameState::from_fen("1r4kr/8/8/8/8/8/8/1R4KR w HBhb - 0 1").unwrap();
+	let kingside = Move::from_uci("g1h1").unwrap();
+	let queenside = Move::from_uci("g1b1").unwrap();
+	assert!(game_state.is_legal(kingside));
+	assert!(game_state.is_legal(queenside));
+	assert!(!game_state.is_legal(Move::from_uci("g1e1").unwrap()));
+	assert_eq!(game_state.move_to_san(kingside), "O-O");
+	assert_eq!(game_state.parse_move("O-O-O"), Ok(queenside));
+
+	// The king stays where it is when castling kingside from the G column
+	let mut after = game_state.clone();
+	after.apply_move(kingside).unwrap();
+	assert_eq!(after.to_fen(), "1r4kr/8/8/8/8/8/8/1R3RK1 b hb - 1 1");
+	let mut after = game_state;
+	after.apply_move(queenside).unwrap();
+	assert_eq!(after.to_fen(), "1r4kr/8/8/8/8/8/8/2KR3R b hb - 1 1");
+}
+
+#[test]
+fn promotion_test() {
+	use self::Column::*;
+	use self::Row::*;
+
+	let mut game_state = GameState::new();
+	let board = &mut game_state.board;
+	*board.get_board_index_mut(BoardIndex::new(A, R1)) = Some(GamePiece::new(Piece::King, Color::White));
+	*board.get_board_index_mut(BoardIndex::new(H, R1)) = Some(GamePiece::new(Piece::King, Color::Black));
+	*board.get_board_index_mut(BoardIndex::new(B, R7)) = Some(GamePiece::new(Piece::Pawn, Color::White));
+	*board.get_board_index_mut(BoardIndex::new(C, R8)) = Some(GamePiece::new(Piece::Rook, Color::Black));
+
+	let (start, push, capture) = (BoardIndex::new(B, R7), BoardIndex::new(B, R8), BoardIndex::new(C, R8));
+	let pawn_moves: Vec<_> = game_state
+		.legal_moves()
+		.into_iter()
+		.filter(|mv| mv.start == start)
+		.collect();
+	assert_eq!(pawn_moves.len(), 8);
+	assert!(!game_state.is_legal(Move::new(start, push)));
+
+	game_state
+		.apply_move(Move::with_promotion(start, capture, Piece::Knight))
+		.unwrap();
+	assert_eq!(
+		game_state.board.get_board_index(capture),
+		Some(GamePiece::new(Piece::Knight, Color::White))
+	);
+	assert_eq!(game_state.board.get_board_index(start), None);
+}
+
+#[test]
+fn en_passant_test() {
+	use self::Column::*;
+	use self::Row::*;
+
+	let mut game_state = GameState::standard();
+	let moves = [
+		((E, R2), (E, R4)),
+		((A, R7), (A, R6)),
+		((E, R4), (E, R5)),
+		((D, R7), (D, R5)),
+	];
+	for &((start_column, start_row), (end_column, end_row)) in &moves {
+		let mv = Move::new(
+			BoardIndex::new(start_column, start_row),
+			BoardIndex::new(end_column, end_row),
+		);
+		game_state.apply_move(mv).unwrap();
+	}
+	let en_passant = Move::new(BoardIndex::new(E, R5), BoardIndex::new(D, R6));
+	assert!(game_state.is_legal(en_passant));
+	let outcome = game_state.apply_move(en_passant).unwrap();
+	assert_eq!(outcome.captured, Some(GamePiece::new(Piece::Pawn, Color::Black)));
+	assert_eq!(game_state.board.get_board_index(BoardIndex::new(D, R5)), None);
+	assert_eq!(game_state.halfmove_clock, 0);
+
+	// The chance to capture en passant only lasts for a single move
+	let mut game_state = GameState::standard();
+	let moves = [
+		((E, R2), (E, R4)),
+		((A, R7), (A, R6)),
+		((E, R4), (E, R5)),
+		((D, R7), (D, R5)),
+		((A, R2), (A, R3)),
+		((A, R6), (A, R5)),
+	];
+	for &((start_column, start_row), (end_column, end_row)) in &moves {
+		let mv = Move::new(
+			BoardIndex::new(start_column, start_row),
+			BoardIndex::new(end_column, end_row),
+		);
+		game_state.apply_move(mv).unwrap();
+	}
+	assert!(!game_state.is_legal(en_passant));
+}
+
+#[test]
+fn fairy_piece_test() {
+	// The archbishop moves as a bishop or a knight, and the chancellor as a rook or a knight
+	let game_state = GameState::from_fen("4k5/10/10/10/4A5/10/10/C3K5 w - - 0 1").unwrap();
+	let moves_from = |square| {
+		let start = BoardIndex::parse(square).unwrap();
+		game_state.legal_moves().iter().filter(|mv| mv.start == start).count()
+	};
+	assert_eq!(moves_from("e4"), 22);
+	assert_eq!(moves_from("a1"), 12);
+
+	// Both give check the way a knight does
+	let game_state = GameState::from_fen("4k5/10/3A6/10/10/10/10/4K5 b - - 0 1").unwrap();
+	assert!(game_state.is_check());
+}
+
+#[test]
+fn off_board_test() {
+	let game_state = GameState::from_fen("rnbqk/ppppp/5/PPPPP/RNBQK w - - 0 1").unwrap();
+	let mv = Move::from_uci("j9j10").unwrap();
+	assert_eq!(game_state.validate_move(mv), Err(MoveError::OffBoard));
+	let mut after = game_state.clone();
+	assert_eq!(after.apply_move(mv), Err(MoveError::OffBoard));
+	assert_eq!(after, game_state);
+	assert_eq!(
+		game_state.validate_move(Move::from_uci("a2f3").unwrap()),
+		Err(MoveError::OffBoard)
+	);
+}
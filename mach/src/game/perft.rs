@@ -0,0 +1,144 @@
+use crate::game::*;
+
+impl GameState {
+	/// Count the leaf nodes of the tree of legal moves from this position to the given depth. Used to
+	/// check the move generator against known results.
+	pub fn perft(&self, depth: u32) -> u64 {
+		if depth == 0 {
+			return 1;
+		}
+		let moves = self.legal_moves();
+		if depth == 1 {
+			return moves.len() as u64;
+		}
+		moves
+			.into_iter()
+			.map(|mv| {
+				let mut next = self.clone();
+				next.apply_move_unchecked(mv);
+				next.perft(depth - 1)
+			})
+			.sum()
+	}
+
+	/// Count the leaf nodes of the tree of legal moves from this position to the given depth, separately
+	/// for each legal move in this position. Useful for finding where a move generator goes wrong.
+	pub fn perft_divide(&self, depth: u32) -> Vec<(Move, u64)> {
+		self.legal_moves()
+			.into_iter()
+			.map(|mv| {
+				let mut next = self.clone();
+				next.apply_move_unchecked(mv);
+				(mv, next.perft(depth.saturating_sub(1)))
+			})
+			.collect()
+	}
+}
+
+/// Positions with well known perft results, along with the results for increasing depths starting at 1
+#[cfg(test)]
+const PERFT_POSITIONS: [(&str, &[u64]); 10] = [
+	(STANDARD_FEN, &[20, 400, 8902, 197_281, 4_865_609]),
+	(
+		"r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+		&[48, 2039, 97862, 4_085_603],
+	),
+	(
+		"8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+		&[14, 191, 2812, 43238, 674_624],
+	),
+	(
+		"r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+		&[6, 264, 9467, 422_333],
+	),
+	(
+		"rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+		&[44, 1486, 62379, 2_103_487],
+	),
+	(
+		"r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+		&[46, 2079, 89890, 3_894_594],
+	),
+	(
+		"bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+		&[21, 528, 12189, 326_672],
+	),
+	(
+		"2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9",
+		&[21, 807, 18002, 667_366],
+	),
+	(
+		"b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9",
+		&[20, 479, 10471, 273_318],
+	),
+	(
+		"1nbbnrkr/p1p1ppp1/3p4/1p3P1p/3Pq2P/8/PPP1P1P1/QNBBNRKR w HFhf - 0 9",
+		&[28, 1120, 31058, 1_171_749],
+	),
+];
+
+#[cfg(test)]
+fn check_perft_positions(max_nodes: u64) {
+	for &(fen, results) in &PERFT_POSITIONS {
+		let game_state = GameState::from_fen(fen).unwrap();
+		for (depth, &expected) in results.iter().enumerate() {
+			if expected > max_nodes {
+				break;
+			}
+			assert_eq!(
+				game_state.perft(depth as u32 + 1),
+				expected,
+				"{} at depth {}",
+				fen,
+				depth + 1
+			);
+		}
+	}
+}
+
+#[test]
+fn perft_test() {
+	check_perft_positions(100_000);
+}
+
+#[test]
+#[ignore]
+fn perft_deep_test() {
+	check_perft_positions(u64::MAX);
+}
+
+#[test]
+fn perft_divide_test() {
+	let game_state = GameState::standard();
+	let divide = game_state.perft_divide(3);
+	assert_eq!(divide.len(), 20);
+	assert_eq!(divide.iter().map(|&(_, nodes)| nodes).sum::<u64>(), 8902);
+	let e2e4 = Move::from_uci("e2e4").unwrap();
+	assert_eq!(
+		divide.iter().find(|&&(mv, _)| mv == e2e4).map(|&(_, nodes)| nodes),
+		Some(600)
+	);
+}
+
+#[test]
+fn variant_perft_test() {
+	use crate::variant::VariantKind;
+
+	let variants: [(VariantKind, &[u64]); 3] = [
+		(VariantKind::Capablanca, &[28, 784, 25228]),
+		(VariantKind::Grand, &[65, 4225]),
+		(VariantKind::Minichess, &[7, 53, 506, 4775]),
+	];
+	for &(variant, results) in &variants {
+		let game_state = variant.rules().start_position().unwrap();
+		for (depth, &expected) in results.iter().enumerate() {
+			assert_eq!(
+				game_state.perft(depth as u32 + 1),
+				expected,
+				"{} at depth {}",
+				variant.name(),
+				depth + 1
+			);
+		}
+	}
+}
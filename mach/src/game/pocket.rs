@@ -0,0 +1,193 @@
+use serde::{Deserialize, Serialize};
+
+use crate::game::*;
+
+/// The pieces that can be held in a pocket and dropped onto the board, in the order they are written
+pub const POCKET_PIECES: [Piece; 5] = [Piece::Pawn, Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen];
+
+/// The pieces one side holds in hand, which can be dropped onto the board instead of moving a piece in
+/// variants such as Crazyhouse and Bughouse
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct Pocket {
+	pub pawns: u32,
+	pub knights: u32,
+	pub bishops: u32,
+	pub rooks: u32,
+	pub queens: u32,
+}
+
+impl Pocket {
+	/// The number of pieces of the given kind in the pocket. A pocket never holds a king, nor an archbishop
+	/// or chancellor, as no variant with drops has them.
+	pub fn count(self, piece: Piece) -> u32 {
+		match piece {
+			Piece::Pawn => self.pawns,
+			Piece::Knight => self.knights,
+			Piece::Bishop => self.bishops,
+			Piece::Rook => self.rooks,
+			Piece::Queen => self.queens,
+			Piece::King | Piece::Archbishop | Piece::Chancellor => 0,
+		}
+	}
+
+	fn count_mut(&mut self, piece: Piece) -> Option<&mut u32> {
+		match piece {
+			Piece::Pawn => Some(&mut self.pawns),
+			Piece::Knight => Some(&mut self.knights),
+			Piece::Bishop => Some(&mut self.bishops),
+			Piece::Rook => Some(&mut self.rooks),
+			Piece::Queen => Some(&mut self.queens),
+			Piece::King | Piece::Archbishop | Piece::Chancellor => None,
+		}
+	}
+
+	/// Put a piece into the pocket. Pieces a pocket can not hold, such as kings, are ignored.
+	pub fn add(&mut self, piece: Piece) {
+		if let Some(count) = self.count_mut(piece) {
+			*count += 1;
+		}
+	}
+
+	pub fn is_empty(self) -> bool {
+		POCKET_PIECES.iter().all(|&piece| self.count(piece) == 0)
+	}
+
+	/// Iterate over the kinds of pieces there is at least one of in the pocket
+	pub fn pieces(self) -> impl Iterator<Item = Piece> {
+		POCKET_PIECES
+			.iter()
+			.copied()
+			.filter(move |&piece| self.count(piece) > 0)
+	}
+}
+
+/// The pockets of both sides
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct Pockets {
+	pub white: Pocket,
+	pub black: Pocket,
+}
+
+impl Pockets {
+	pub fn get(self, color: Color) -> Pocket {
+		match color {
+			Color::White => self.white,
+			Color::Black => self.black,
+		}
+	}
+
+	pub fn get_mut(&mut self, color: Color) -> &mut Pocket {
+		match color {
+			Color::White => &mut self.white,
+			Color::Black => &mut self.black,
+		}
+	}
+
+	pub fn is_empty(self) -> bool {
+		self.white.is_empty() && self.black.is_empty()
+	}
+}
+
+impl GameState {
+	/// Put a piece into the pocket of the given color, keeping the Zobrist key up to date. Kings can not be
+	/// held in a pocket and are ignored.
+	pub fn add_to_pocket(&mut self, color: Color, piece: Piece) {
+		if let Some(count) = self.pockets.get_mut(color).count_mut(piece) {
+			*count += 1;
+			self.zobrist ^= pocket_key(color, piece, *count);
+		}
+	}
+
+	/// Take a piece out of the pocket of the given color, keeping the Zobrist key up to date. Returns
+	/// whether the pocket held that piece.
+	pub fn remove_from_pocket(&mut self, color: Color, piece: Piece) -> bool {
+		match self.pockets.get_mut(color).count_mut(piece) {
+			Some(count) if *count > 0 => {
+				self.zobrist ^= pocket_key(color, piece, *count);
+				*count -= 1;
+				true
+			}
+			_ => false,
+		}
+	}
+
+	/// The piece that goes into a pocket when the given move is made by the side whose turn it is: the piece
+	/// it captures, or a pawn if that piece was promoted from one
+	pub fn pocket_capture(&self, mv: Move) -> Option<Piece> {
+		if mv.is_drop() {
+			return None;
+		}
+		let mover = self.board.get_board_index(mv.start)?;
+		match self.board.get_board_index(mv.end) {
+			Some(captured) if captured.color != mover.color => Some(if self.promoted.contains(mv.end) {
+				Piece::Pawn
+			} else {
+				captured.piece
+			}),
+			Some(_) => None,
+			None if mover.piece == Piece::Pawn && Some(mv.end) == self.en_passant => Some(Piece::Pawn),
+			None => None,
+		}
+	}
+
+	/// Generate the moves dropping a piece from the pocket of the side whose turn it is onto an empty
+	/// square. Pawns can not be dropped on the first or last row.
+	pub(crate) fn drop_moves(&self, board: &BitGameBoard, moves: &mut Vec<Move>) {
+		let pocket = self.pockets.get(self.turn);
+		if pocket.is_empty() {
+			return;
+		}
+		let size = board.size();
+		let empty = !board.occupied() & Bitboard::board(size);
+		for piece in pocket.pieces() {
+			for index in empty.iter() {
+				if piece == Piece::Pawn
+					&& (index.row == size.home_row(Color::White) || index.row == size.home_row(Color::Black))
+				{
+					continue;
+				}
+				moves.push(Move::new_drop(piece, index));
+			}
+		}
+	}
+}
+
+#[test]
+fn pocket_test() {
+	let game_state = GameState::from_fen("4k3/8/8/8/8/8/8/4K3[Pn] w - - 0 1").unwrap();
+	assert_eq!(game_state.pockets.white.pawns, 1);
+	assert_eq!(game_state.pockets.black.knights, 1);
+
+	// 62 empty squares for the knight, and the 48 of them off the first and last rows for the pawn
+	let drops = game_state.legal_moves().into_iter().filter(|mv| mv.is_drop()).count();
+	assert_eq!(drops, 48);
+	let game_state = GameState::from_fen("4k3/8/8/8/8/8/8/4K3[Pn] b - - 0 1").unwrap();
+	assert_eq!(
+		game_state.legal_moves().into_iter().filter(|mv| mv.is_drop()).count(),
+		62
+	);
+
+	let mut after = game_state.clone();
+	let drop = Move::from_uci("N@d3").unwrap();
+	let outcome = after.apply_move(drop).unwrap();
+	assert_eq!(
+		after.board.get_board_index(BoardIndex::parse("d3").unwrap()),
+		Some(GamePiece::new(Piece::Knight, Color::Black))
+	);
+	assert!(after.pockets.black.is_empty());
+	assert!(after.is_check());
+	assert_eq!(after.zobrist_key(), after.compute_zobrist_key());
+	assert_eq!(after.to_fen(), "4k3/8/8/8/8/3n4/8/4K3[P] w - - 1 2");
+	after.undo_move(drop, outcome);
+	assert_eq!(after, game_state);
+
+	// A drop that leaves the king in check is not legal, but one blocking a check is
+	let game_state = GameState::from_fen("4k3/8/8/8/8/8/8/r3K3[N] w - - 0 1").unwrap();
+	let drops: Vec<_> = game_state.legal_moves().into_iter().filter(|mv| mv.is_drop()).collect();
+	assert_eq!(drops.len(), 3);
+	assert_eq!(game_state.move_to_san(Move::from_uci("N@c1").unwrap()), "N@c1");
+	assert_eq!(
+		game_state.parse_move("N@d1"),
+		Ok(Move::new_drop(Piece::Knight, BoardIndex::parse("d1").unwrap()))
+	);
+}
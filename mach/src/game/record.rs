@@ -0,0 +1,217 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{game::*, variant::*};
+
+/// A move that was played in a game, along with what is needed to take it back
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PlayedMove {
+	pub mv: Move,
+	pub outcome: MoveOutcome,
+}
+
+/// The record of a game from its starting position, keeping every move played so they can be undone,
+/// redone and replayed. Moves that are undone are kept until a different move is made in their place.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Game {
+	variant: VariantKind,
+	start: GameState,
+	state: GameState,
+	history: PositionHistory,
+	moves: Vec<PlayedMove>,
+	/// The number of moves in `moves` that are currently applied to `state`
+	ply: usize,
+}
+
+impl Game {
+	/// Start a game from the given position
+	pub fn new(start: GameState) -> Self {
+		Self::with_variant(start, VariantKind::Standard)
+	}
+
+	/// Start a game of the given variant from the given position
+	pub fn with_variant(start: GameState, variant: VariantKind) -> Self {
+		Self {
+			variant,
+			history: PositionHistory::new(&start),
+			state: start.clone(),
+			start,
+			moves: Vec::new(),
+			ply: 0,
+		}
+	}
+
+	/// Start a standard game
+	pub fn standard() -> Self {
+		Self::new(GameState::standard())
+	}
+
+	/// The variant whose rules the game is played by
+	pub fn variant(&self) -> VariantKind {
+		self.variant
+	}
+
+	/// The position the game started from
+	pub fn start(&self) -> &GameState {
+		&self.start
+	}
+
+	/// The current position of the game
+	pub fn state(&self) -> &GameState {
+		&self.state
+	}
+
+	/// The positions that have occurred up to the current position
+	pub fn history(&self) -> &PositionHistory {
+		&self.history
+	}
+
+	/// The moves that were played up to the current position
+	pub fn moves(&self) -> &[PlayedMove] {
+		&self.moves[..self.ply]
+	}
+
+	/// Every move in the record, including moves that were undone and can be redone
+	pub fn all_moves(&self) -> &[PlayedMove] {
+		&self.moves
+	}
+
+	/// The number of halfmoves played up to the current position
+	pub fn ply(&self) -> usize {
+		self.ply
+	}
+
+	/// Make a move in the current position, after checking that it is legal. Any moves that were undone
+	/// are forgotten, unless the move is the same as the next one that would be redone.
+	pub fn make_move(&mut self, mv: Move) -> Result<MoveOutcome, MoveError> {
+		if self.moves.get(self.ply).is_some_and(|next| next.mv == mv) {
+			self.redo();
+			return Ok(self.moves[self.ply - 1].outcome);
+		}
+		let rules = self.variant.rules();
+		rules.validate_move(&self.state, mv)?;
+		let outcome = rules.apply_move(&mut self.state, mv);
+		self.history.push(&self.state);
+		self.moves.truncate(self.ply);
+		self.moves.push(PlayedMove { mv, outcome });
+		self.ply += 1;
+		Ok(outcome)
+	}
+
+	/// Take back the last move played, returning it if there was one
+	pub fn undo(&mut self) -> Option<Move> {
+		let played = *self.moves().last()?;
+		let rules = self.variant.rules();
+		if !rules.undo_move(&mut self.state, played.mv, played.outcome) {
+			let mut state = self.start.clone();
+			for previous in &self.moves[..self.ply - 1] {
+				rules.apply_move(&mut state, previous.mv);
+			}
+			self.state = state;
+		}
+		self.history.pop();
+		self.ply -= 1;
+		Some(played.mv)
+	}
+
+	/// Play the next move that was undone again, returning it if there was one
+	pub fn redo(&mut self) -> Option<Move> {
+		let played = *self.moves.get(self.ply)?;
+		self.variant.rules().apply_move(&mut self.state, played.mv);
+		self.history.push(&self.state);
+		self.ply += 1;
+		Some(played.mv)
+	}
+
+	/// Undo or redo moves until the given number of halfmoves have been played. Returns false without
+	/// changing the position if the record does not have that many moves.
+	pub fn jump_to(&mut self, ply: usize) -> bool {
+		if ply > self.moves.len() {
+			return false;
+		}
+		while self.ply > ply {
+			self.undo();
+		}
+		while self.ply < ply {
+			self.redo();
+		}
+		true
+	}
+
+	/// Put a piece into the pocket of the given color from outside the game, as when a piece captured on the
+	/// partner board in Bughouse is passed on. The piece is counted as held from the start of the game, so it
+	/// stays in the pocket when moves are undone.
+	pub fn add_to_pocket(&mut self, color: Color, piece: Piece) {
+		self.start.add_to_pocket(color, piece);
+		self.state.add_to_pocket(color, piece);
+		self.history.replace_latest(&self.state);
+	}
+
+	/// Check whether the game has ended in the current position, and if so how
+	pub fn game_over(&self) -> Option<GameOver> {
+		self.variant.rules().game_over(&self.state, &self.history)
+	}
+
+	/// Check whether the side whose turn it is may claim a draw in the current position, and if so why
+	pub fn draw_claim(&self) -> Option<Termination> {
+		self.history.draw_claim(&self.state)
+	}
+}
+
+impl Default for Game {
+	fn default() -> Self {
+		Self::standard()
+	}
+}
+
+#[test]
+fn game_record_test() {
+	let mut game = Game::standard();
+	assert_eq!(game.undo(), None);
+	assert_eq!(game.redo(), None);
+
+	let mut states = vec![game.state().clone()];
+	for &san in &["e4", "d5", "exd5", "Qxd5", "Nc3"] {
+		let mv = game.state().parse_move(san).unwrap();
+		game.make_move(mv).unwrap();
+		states.push(game.state().clone());
+	}
+	assert_eq!(game.ply(), 5);
+	assert_eq!(
+		game.moves()[2].outcome.captured,
+		Some(GamePiece::new(Piece::Pawn, Color::Black))
+	);
+
+	for ply in (0..5).rev() {
+		assert!(game.undo().is_some());
+		assert_eq!(game.state(), &states[ply]);
+	}
+	assert_eq!(game.state(), game.start());
+	assert_eq!(game.all_moves().len(), 5);
+
+	assert!(game.jump_to(3));
+	assert_eq!(game.state(), &states[3]);
+	assert!(game.redo().is_some());
+	assert_eq!(game.state(), &states[4]);
+	assert!(!game.jump_to(6));
+	assert_eq!(game.ply(), 4);
+
+	// Making a different move forgets the moves that were undone
+	game.jump_to(3);
+	let mv = game.state().parse_move("Nf6").unwrap();
+	game.make_move(mv).unwrap();
+	assert_eq!(game.all_moves().len(), 4);
+	assert_eq!(game.redo(), None);
+}
+
+#[test]
+fn variant_record_test() {
+	// Captures in Atomic can not be undone from their outcome, so the position is replayed instead
+	let start = GameState::from_fen("4k3/8/4b3/3pn3/8/4N3/8/4K3 w - - 0 1").unwrap();
+	let mut game = Game::with_variant(start.clone(), VariantKind::Atomic);
+	game.make_move(Move::from_uci("e3d5").unwrap()).unwrap();
+	assert_eq!(game.state().to_fen(), "4k3/8/8/8/8/8/8/4K3 b - - 0 1");
+	game.undo();
+	assert_eq!(game.state(), &start);
+	game.redo();
+	assert_eq!(game.state().to_fen(), "4k3/8/8/8/8/8/8/4K3 b - - 0 1");
+}
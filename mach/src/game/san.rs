@@ -0,0 +1,455 @@
+use std::fmt;
+
+use crate::game::*;
+
+/// An error encountered while reading a move written in Standard Algebraic Notation, long algebraic
+/// notation or UCI notation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SanError {
+	/// The text could not be read as a move at all
+	Syntax(String),
+	/// The text describes a move that no piece can legally make
+	IllegalMove(String),
+	/// The text describes a move that more than one piece could legally make
+	AmbiguousMove(String),
+}
+
+impl fmt::Display for SanError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			SanError::Syntax(san) => write!(f, "'{}' is not a valid move", san),
+			SanError::IllegalMove(san) => write!(f, "'{}' is not a legal move", san),
+			SanError::AmbiguousMove(san) => write!(f, "'{}' could be more than one move", san),
+		}
+	}
+}
+
+impl std::error::Error for SanError {}
+
+impl Move {
+	/// Write the move in the notation used by the Universal Chess Interface, such as `e2e4` or `e7e8q`.
+	/// Drops are written with the piece and the square it is dropped on, such as `N@f3`.
+	pub fn to_uci(self) -> String {
+		if let Some(piece) = self.drop {
+			return format!("{}@{}", piece.to_char(), self.end);
+		}
+		let mut uci = format!("{}{}", self.start, self.end);
+		if let Some(promotion) = self.promotion {
+			uci.push(promotion.to_char().to_ascii_lowercase());
+		}
+		uci
+	}
+
+	/// Read a move written in the notation used by the Universal Chess Interface. This does not check
+	/// that the move is legal in any position.
+	pub fn from_uci(uci: &str) -> Option<Move> {
+		if !uci.is_ascii() || uci.len() < 4 {
+			return None;
+		}
+		if &uci[1..2] == "@" {
+			let piece = Piece::from_char(uci[0..1].chars().next()?.to_ascii_uppercase())
+				.filter(|&piece| piece != Piece::King)?;
+			return BoardIndex::parse(&uci[2..]).map(|index| Move::new_drop(piece, index));
+		}
+		let (start, rest) = BoardIndex::parse_prefix(uci)?;
+		let (end, rest) = BoardIndex::parse_prefix(rest)?;
+		let promotion = match rest {
+			"" => None,
+			_ if rest.len() == 1 => Some(
+				Piece::from_char(rest.chars().next()?.to_ascii_uppercase()).filter(|&piece| piece != Piece::Pawn)?,
+			),
+			_ => return None,
+		};
+		Some(Move {
+			start,
+			end,
+			promotion,
+			drop: None,
+		})
+	}
+}
+
+impl fmt::Display for Move {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}", self.to_uci())
+	}
+}
+
+impl GameState {
+	/// Write a legal move in Standard Algebraic Notation, such as `Nf3`, `exd5`, `O-O` or `e8=Q+`
+	pub fn move_to_san(&self, mv: Move) -> String {
+		let mut san = String::new();
+		let piece = match mv.drop {
+			Some(piece) => GamePiece::new(piece, self.turn),
+			None => self
+				.board
+				.get_board_index(mv.start)
+				.expect("Tried to write a move from an empty square"),
+		};
+
+		if mv.is_drop() {
+			san.push_str(&mv.to_uci());
+		} else if let Some(castling) = self.move_castling(mv, piece, self.castling) {
+			if castling.is_kingside() {
+				san.push_str("O-O");
+			} else {
+				san.push_str("O-O-O");
+			}
+		} else {
+			let capture = self.board.get_board_index(mv.end).is_some()
+				|| (piece.piece == Piece::Pawn && mv.start.column != mv.end.column);
+			if piece.piece == Piece::Pawn {
+				if capture {
+					san.push(mv.start.column.to_char());
+				}
+			} else {
+				san.push(piece.piece.to_char());
+				san.push_str(&self.disambiguation(mv, piece));
+			}
+			if capture {
+				san.push('x');
+			}
+			san.push_str(&mv.end.to_string());
+			if let Some(promotion) = mv.promotion {
+				san.push('=');
+				san.push(promotion.to_char());
+			}
+		}
+
+		let mut after = self.clone();
+		after.apply_move_unchecked(mv);
+		if after.is_check() {
+			san.push(if after.has_legal_moves() { '+' } else { '#' });
+		}
+		san
+	}
+
+	/// Write a legal move in long algebraic notation, which always includes the start square, such as
+	/// `Ng1-f3`, `e4xd5`, `O-O` or `e7-e8=Q+`
+	pub fn move_to_lan(&self, mv: Move) -> String {
+		let piece = match mv.drop {
+			Some(piece) => GamePiece::new(piece, self.turn),
+			None => self
+				.board
+				.get_board_index(mv.start)
+				.expect("Tried to write a move from an empty square"),
+		};
+		let san = self.move_to_san(mv);
+		if mv.is_drop() || self.move_castling(mv, piece, self.castling).is_some() {
+			return san;
+		}
+
+		let mut lan = String::new();
+		if piece.piece != Piece::Pawn {
+			lan.push(piece.piece.to_char());
+		}
+		lan.push_str(&mv.start.to_string());
+		lan.push(if san.contains('x') { 'x' } else { '-' });
+		lan.push_str(&mv.end.to_string());
+		if let Some(promotion) = mv.promotion {
+			lan.push('=');
+			lan.push(promotion.to_char());
+		}
+		lan.extend(san.chars().filter(|&c| c == '+' || c == '#'));
+		lan
+	}
+
+	/// Write a legal move in the coordinate notation of the XBoard protocol, which is UCI notation except
+	/// that castling in Chess960 is written `O-O` or `O-O-O`, since the king may not move at all
+	pub fn move_to_coordinate(&self, mv: Move) -> String {
+		let castling = self
+			.board
+			.get_board_index(mv.start)
+			.filter(|_| self.chess960 && !mv.is_drop())
+			.and_then(|piece| self.move_castling(mv, piece, self.castling));
+		match castling {
+			Some(castling) if castling.is_kingside() => String::from("O-O"),
+			Some(_) => String::from("O-O-O"),
+			None => mv.to_uci(),
+		}
+	}
+
+	/// Read a move in any of the notations this crate understands: Standard Algebraic Notation, long
+	/// algebraic notation or UCI notation, finding the legal move in this position that it describes
+	pub fn parse_move(&self, input: &str) -> Result<Move, SanError> {
+		let input = input.trim();
+		if let Some(mv) = Move::from_uci(input) {
+			return if self.is_legal(mv) {
+				Ok(mv)
+			} else {
+				Err(SanError::IllegalMove(input.to_owned()))
+			};
+		}
+		if let Some(mv) = self.parse_lan(input)? {
+			return Ok(mv);
+		}
+		self.parse_san(input)
+	}
+
+	/// Read a move written in long algebraic notation, or return `None` if the input is not in that form
+	fn parse_lan(&self, lan: &str) -> Result<Option<Move>, SanError> {
+		let trimmed = lan.trim_end_matches(['+', '#', '!', '?']);
+		let separator = match trimmed.find(['-', 'x']) {
+			Some(separator) if !trimmed.starts_with('O') && !trimmed.starts_with('0') => separator,
+			_ => return Ok(None),
+		};
+		let (start, rest) = trimmed.split_at(separator);
+		let (piece, start) = match start.chars().next().and_then(Piece::from_char) {
+			Some(piece) => (piece, &start[1..]),
+			None => (Piece::Pawn, start),
+		};
+		let rest = &rest[1..];
+		let (start, (end, rest)) = match (BoardIndex::parse(start), BoardIndex::parse_prefix(rest)) {
+			(Some(start), Some(end)) => (start, end),
+			_ => return Ok(None),
+		};
+		let promotion = match rest.trim_start_matches('=') {
+			"" => None,
+			promotion => Some(
+				promotion
+					.chars()
+					.next()
+					.and_then(Piece::from_char)
+					.filter(|_| promotion.len() == 1)
+					.ok_or_else(|| SanError::Syntax(lan.to_owned()))?,
+			),
+		};
+
+		let mv = Move {
+			start,
+			end,
+			promotion,
+			drop: None,
+		};
+		if self.board.get_board_index(start) == Some(GamePiece::new(piece, self.turn)) && self.is_legal(mv) {
+			Ok(Some(mv))
+		} else {
+			Err(SanError::IllegalMove(lan.to_owned()))
+		}
+	}
+
+	/// Get the start column and/or row needed to tell a move apart from the same kind of piece moving to
+	/// the same square
+	fn disambiguation(&self, mv: Move, piece: GamePiece) -> String {
+		let others: Vec<Move> = self
+			.legal_moves()
+			.into_iter()
+			.filter(|other| {
+				other.end == mv.end && other.start != mv.start && self.board.get_board_index(other.start) == Some(piece)
+			})
+			.collect();
+		if others.is_empty() {
+			String::new()
+		} else if others.iter().all(|other| other.start.column != mv.start.column) {
+			mv.start.column.to_char().to_string()
+		} else if others.iter().all(|other| other.start.row != mv.start.row) {
+			mv.start.row.to_string()
+		} else {
+			mv.start.to_string()
+		}
+	}
+
+	/// Read a move written in Standard Algebraic Notation, finding the legal move in this position that
+	/// it describes. Check and annotation suffixes such as `+`, `#` and `!?` are ignored.
+	pub fn parse_san(&self, san: &str) -> Result<Move, SanError> {
+		let syntax = || SanError::Syntax(san.to_owned());
+		let trimmed = san.trim_end_matches(['+', '#', '!', '?']);
+
+		let castling = match trimmed {
+			"O-O" | "0-0" => Some(1),
+			"O-O-O" | "0-0-0" => Some(-1),
+			_ => None,
+		};
+		if let Some(direction) = castling {
+			let mut candidates = self.legal_moves().into_iter().filter(|&mv| {
+				self.board
+					.get_board_index(mv.start)
+					.and_then(|piece| self.move_castling(mv, piece, self.castling))
+					.is_some_and(|castling| castling.is_kingside() == (direction > 0))
+			});
+			return candidates.next().ok_or_else(|| SanError::IllegalMove(san.to_owned()));
+		}
+
+		if let Some(at) = trimmed.find('@') {
+			let piece = match &trimmed[..at] {
+				"" => Some(Piece::Pawn),
+				piece => piece
+					.chars()
+					.next()
+					.and_then(Piece::from_char)
+					.filter(|_| piece.len() == 1),
+			};
+			let index = BoardIndex::parse(&trimmed[at + 1..]);
+			let mv = match (piece, index) {
+				(Some(piece), Some(index)) => Move::new_drop(piece, index),
+				_ => return Err(syntax()),
+			};
+			return if self.is_legal(mv) {
+				Ok(mv)
+			} else {
+				Err(SanError::IllegalMove(san.to_owned()))
+			};
+		}
+
+		let mut chars: Vec<char> = trimmed.chars().collect();
+		let mut promotion = None;
+		if let Some(&last) = chars.last() {
+			if let Some(piece) = Piece::from_char(last) {
+				promotion = Some(piece);
+				chars.pop();
+				if chars.last() == Some(&'=') {
+					chars.pop();
+				}
+			}
+		}
+		// The end square is a column letter followed by a row number, which may have two digits
+		let digits = chars.iter().rev().take_while(|c| c.is_ascii_digit()).count();
+		if digits == 0 || chars.len() < digits + 1 {
+			return Err(syntax());
+		}
+		let end_chars: String = chars.split_off(chars.len() - digits - 1).into_iter().collect();
+		let end = BoardIndex::parse(&end_chars).ok_or_else(syntax)?;
+
+		let mut prefix = chars.into_iter().peekable();
+		let piece = match prefix.peek().and_then(|&c| Piece::from_char(c)) {
+			Some(piece) => {
+				prefix.next();
+				piece
+			}
+			None => Piece::Pawn,
+		};
+		let mut start_column = None;
+		let mut start_row = None;
+		while let Some(c) = prefix.next() {
+			if c == 'x' {
+				continue;
+			} else if let Some(column) = Column::from_char(c) {
+				start_column = Some(column);
+			} else if c.is_ascii_digit() {
+				let mut number = c.to_string();
+				while let Some(c) = prefix.next_if(|c| c.is_ascii_digit()) {
+					number.push(c);
+				}
+				start_row = Some(Row::parse(&number).ok_or_else(syntax)?);
+			} else {
+				return Err(syntax());
+			}
+		}
+
+		let candidates: Vec<Move> =
+			self.legal_moves()
+				.into_iter()
+				.filter(|mv| {
+					mv.end == end
+						&& !mv.is_drop() && mv.promotion == promotion
+						&& self.board.get_board_index(mv.start) == Some(GamePiece::new(piece, self.turn))
+						&& start_column.is_none_or(|column| mv.start.column == column)
+						&& start_row.is_none_or(|row| mv.start.row == row)
+				})
+				.collect();
+		match candidates.len() {
+			0 => Err(SanError::IllegalMove(san.to_owned())),
+			1 => Ok(candidates[0]),
+			_ => Err(SanError::AmbiguousMove(san.to_owned())),
+		}
+	}
+}
+
+#[test]
+fn san_round_trip_test() {
+	let game_state =
+		GameState::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+	for mv in game_state.legal_moves() {
+		let san = game_state.move_to_san(mv);
+		assert_eq!(game_state.parse_san(&san), Ok(mv), "{}", san);
+	}
+}
+
+#[test]
+fn san_format_test() {
+	use self::Column::*;
+	use self::Row::*;
+
+	let game_state =
+		GameState::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+	let san = |start_column, start_row, end_column, end_row| {
+		game_state.move_to_san(Move::new(
+			BoardIndex::new(start_column, start_row),
+			BoardIndex::new(end_column, end_row),
+		))
+	};
+	assert_eq!(san(E, R1, G, R1), "O-O");
+	assert_eq!(san(E, R1, C, R1), "O-O-O");
+	assert_eq!(san(E, R5, F, R7), "Nxf7");
+	assert_eq!(san(D, R5, E, R6), "dxe6");
+	assert_eq!(san(G, R2, H, R3), "gxh3");
+	assert_eq!(san(E, R5, G, R6), "Nxg6");
+	assert_eq!(san(C, R3, B, R5), "Nb5");
+	assert_eq!(san(F, R3, F, R6), "Qxf6");
+
+	// Both rooks can reach d1, so the start column is needed
+	let game_state = GameState::from_fen("4k3/8/8/8/8/8/4K3/R6R w - - 0 1").unwrap();
+	let mv = game_state.parse_san("Rad1").unwrap();
+	assert_eq!(mv.start, BoardIndex::new(A, R1));
+	assert_eq!(game_state.move_to_san(mv), "Rad1");
+	assert_eq!(
+		game_state.parse_san("Rd1"),
+		Err(SanError::AmbiguousMove("Rd1".to_owned()))
+	);
+
+	// Promotion with check and mate
+	let game_state = GameState::from_fen("k7/7P/1K6/8/8/8/8/8 w - - 0 1").unwrap();
+	let mv = game_state.parse_san("h8=Q#").unwrap();
+	assert_eq!(mv.promotion, Some(Piece::Queen));
+	assert_eq!(game_state.move_to_san(mv), "h8=Q#");
+	assert_eq!(game_state.move_to_san(game_state.parse_san("h8=R").unwrap()), "h8=R#");
+	assert_eq!(game_state.move_to_san(game_state.parse_san("h8N").unwrap()), "h8=N");
+	assert_eq!(game_state.parse_san("h8"), Err(SanError::IllegalMove("h8".to_owned())));
+	assert_eq!(game_state.parse_san("z9"), Err(SanError::Syntax("z9".to_owned())));
+}
+
+#[test]
+fn long_algebraic_test() {
+	use self::Column::*;
+	use self::Row::*;
+
+	let game_state =
+		GameState::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+	for mv in game_state.legal_moves() {
+		let lan = game_state.move_to_lan(mv);
+		assert_eq!(game_state.parse_move(&lan), Ok(mv), "{}", lan);
+		assert_eq!(game_state.parse_move(&mv.to_uci()), Ok(mv), "{}", mv);
+	}
+
+	let nxf7 = Move::new(BoardIndex::new(E, R5), BoardIndex::new(F, R7));
+	assert_eq!(game_state.move_to_lan(nxf7), "Ne5xf7");
+	assert_eq!(game_state.parse_move("e5f7"), Ok(nxf7));
+	assert_eq!(game_state.parse_move("Nxf7"), Ok(nxf7));
+	assert_eq!(
+		game_state.parse_move("e5e6"),
+		Err(SanError::IllegalMove("e5e6".to_owned()))
+	);
+	assert_eq!(
+		game_state.parse_move("Bd2-e5"),
+		Err(SanError::IllegalMove("Bd2-e5".to_owned()))
+	);
+
+	let game_state = GameState::from_fen("8/4P3/8/8/8/k7/8/K7 w - - 0 1").unwrap();
+	let promotion = Move::with_promotion(BoardIndex::new(E, R7), BoardIndex::new(E, R8), Piece::Knight);
+	assert_eq!(promotion.to_uci(), "e7e8n");
+	assert_eq!(Move::from_uci("e7e8n"), Some(promotion));
+	assert_eq!(Move::from_uci("e7e8p"), None);
+	assert_eq!(game_state.move_to_lan(promotion), "e7-e8=N");
+	assert_eq!(game_state.parse_move("e7-e8=N"), Ok(promotion));
+	assert_eq!(game_state.parse_move("e8=N"), Ok(promotion));
+}
+
+#[test]
+fn coordinate_notation_test() {
+	let standard = GameState::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+	assert_eq!(standard.move_to_coordinate(Move::from_uci("e1g1").unwrap()), "e1g1");
+	let chess960 = GameState::from_fen("4k3/8/8/8/8/8/8/5KR1 w G - 0 1").unwrap();
+	assert_eq!(chess960.move_to_coordinate(Move::from_uci("f1g1").unwrap()), "O-O");
+	assert_eq!(chess960.parse_move("O-O"), Ok(Move::from_uci("f1g1").unwrap()));
+	assert_eq!(chess960.move_to_coordinate(Move::from_uci("g1g8").unwrap()), "g1g8");
+}
@@ -0,0 +1,163 @@
+use serde::{Deserialize, Serialize};
+
+use crate::game::*;
+
+/// The final result of a game
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GameResult {
+	WhiteWins,
+	BlackWins,
+	Draw,
+}
+
+impl GameResult {
+	/// The result of a game won by the given color
+	pub fn win_for(color: Color) -> Self {
+		match color {
+			Color::White => GameResult::WhiteWins,
+			Color::Black => GameResult::BlackWins,
+		}
+	}
+}
+
+/// The reason a game ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Termination {
+	/// The side to move is in check and has no legal moves
+	Checkmate,
+	/// The side to move is not in check but has no legal moves
+	Stalemate,
+	/// The same position occurred three times and a player claimed a draw
+	ThreefoldRepetition,
+	/// The same position occurred five times
+	FivefoldRepetition,
+	/// Fifty moves were made by each side without a capture or pawn move and a player claimed a draw
+	FiftyMoveRule,
+	/// Seventy-five moves were made by each side without a capture or pawn move
+	SeventyFiveMoveRule,
+	/// Neither side has enough material left to checkmate the other
+	InsufficientMaterial,
+	/// A king reached the center of the board in King of the Hill
+	KingInCenter,
+	/// A side gave check for the third time in Three-check
+	ThirdCheck,
+	/// A king was caught in an explosion in Atomic
+	KingExploded,
+	/// A side lost all of its pieces, which wins in Antichess and loses for the white pawns in Horde
+	NoPiecesLeft,
+	/// A king reached the last row in Racing Kings
+	KingReachedLastRow,
+	/// An endgame tablebase showed the position to be drawn with perfect play, and the server ended the
+	/// game
+	TablebaseDraw,
+	/// A player resigned
+	Resignation,
+	/// The game on the partner board in Bughouse ended, which decides this game for the same teams
+	PartnerGameOver,
+	/// An engine playing one side failed to move, by exiting, taking too long or choosing no move or one
+	/// that is not legal, and lost the game
+	Forfeit,
+}
+
+/// How and with what result a game ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct GameOver {
+	pub result: GameResult,
+	pub termination: Termination,
+}
+
+impl GameState {
+	/// Check whether the king of the side whose turn it is is attacked
+	pub fn is_check(&self) -> bool {
+		self.board.is_king_attacked(self.turn)
+	}
+
+	/// Check whether the side whose turn it is has been checkmated
+	pub fn is_checkmate(&self) -> bool {
+		self.is_check() && !self.has_legal_moves()
+	}
+
+	/// Check whether the side whose turn it is has been stalemated
+	pub fn is_stalemate(&self) -> bool {
+		!self.is_check() && !self.has_legal_moves()
+	}
+
+	/// Check whether the game has ended in this position, and if so how. Repetitions can not be detected
+	/// from a single position; use `PositionHistory::game_over` to detect those as well.
+	pub fn game_over(&self) -> Option<GameOver> {
+		let termination = if !self.has_legal_moves() {
+			if self.is_check() {
+				return Some(GameOver {
+					result: GameResult::win_for(self.turn.opposite()),
+					termination: Termination::Checkmate,
+				});
+			}
+			Termination::Stalemate
+		} else if self.halfmove_clock >= SEVENTY_FIVE_MOVE_RULE {
+			Termination::SeventyFiveMoveRule
+		} else if self.is_insufficient_material() {
+			Termination::InsufficientMaterial
+		} else {
+			return None;
+		};
+		Some(GameOver {
+			result: GameResult::Draw,
+			termination,
+		})
+	}
+}
+
+#[test]
+fn fools_mate_test() {
+	use self::Column::*;
+	use self::Row::*;
+
+	let mut game_state = GameState::standard();
+	let moves = [
+		((F, R2), (F, R3)),
+		((E, R7), (E, R5)),
+		((G, R2), (G, R4)),
+		((D, R8), (H, R4)),
+	];
+	for &((start_column, start_row), (end_column, end_row)) in &moves {
+		assert_eq!(game_state.game_over(), None);
+		let mv = Move::new(
+			BoardIndex::new(start_column, start_row),
+			BoardIndex::new(end_column, end_row),
+		);
+		game_state.apply_move(mv).unwrap();
+	}
+	assert!(game_state.is_check());
+	assert!(game_state.is_checkmate());
+	assert!(!game_state.is_stalemate());
+	assert_eq!(
+		game_state.game_over(),
+		Some(GameOver {
+			result: GameResult::BlackWins,
+			termination: Termination::Checkmate,
+		})
+	);
+}
+
+#[test]
+fn stalemate_test() {
+	use self::Column::*;
+	use self::Row::*;
+
+	let mut game_state = GameState::new();
+	let board = &mut game_state.board;
+	*board.get_board_index_mut(BoardIndex::new(H, R8)) = Some(GamePiece::new(Piece::King, Color::Black));
+	*board.get_board_index_mut(BoardIndex::new(F, R7)) = Some(GamePiece::new(Piece::King, Color::White));
+	*board.get_board_index_mut(BoardIndex::new(G, R6)) = Some(GamePiece::new(Piece::Queen, Color::White));
+	game_state.turn = Color::Black;
+
+	assert!(!game_state.is_check());
+	assert!(game_state.is_stalemate());
+	assert_eq!(
+		game_state.game_over(),
+		Some(GameOver {
+			result: GameResult::Draw,
+			termination: Termination::Stalemate,
+		})
+	);
+}
@@ -0,0 +1,226 @@
+use crate::game::*;
+
+/// The number of squares on a board of the largest size
+const SQUARES: usize = (MAX_SIZE * MAX_SIZE) as usize;
+
+/// The keys of each piece of each color on each square, indexed the same way as the piece and color sets of
+/// a `BitGameBoard`
+const PIECE_KEYS: [[[u64; SQUARES]; 8]; 2] = piece_keys();
+/// The keys of the white kingside, white queenside, black kingside and black queenside castling rights
+const CASTLING_KEYS: [u64; 4] = [random(768), random(769), random(770), random(771)];
+/// The keys of the column of the en passant target square
+const EN_PASSANT_KEYS: [u64; MAX_SIZE as usize] = en_passant_keys();
+/// The key that is included when it is black's turn
+const BLACK_KEY: u64 = random(780);
+
+/// Generate the pseudo-random key with the given number using the SplitMix64 generator, so that the keys
+/// are fixed at compile time and are the same across runs
+const fn random(n: u64) -> u64 {
+	let mut z = (n + 1).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+	z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+	z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+	z ^ (z >> 31)
+}
+
+const fn piece_keys() -> [[[u64; SQUARES]; 8]; 2] {
+	let mut keys = [[[0; SQUARES]; 8]; 2];
+	let mut n = 0;
+	while n < 2 * 8 * SQUARES {
+		keys[n / (8 * SQUARES)][n / SQUARES % 8][n % SQUARES] = random(4096 + n as u64);
+		n += 1;
+	}
+	keys
+}
+
+const fn en_passant_keys() -> [u64; MAX_SIZE as usize] {
+	let mut keys = [0; MAX_SIZE as usize];
+	let mut column = 0;
+	while column < MAX_SIZE as usize {
+		keys[column] = random(3072 + column as u64);
+		column += 1;
+	}
+	keys
+}
+
+/// The key of a piece standing on a square
+pub fn piece_key(piece: GamePiece, index: BoardIndex) -> u64 {
+	PIECE_KEYS[color_index(piece.color)][piece_index(piece.piece)][index.to_linear()]
+}
+
+/// The key of a set of castling rights
+pub fn castling_key(castling: CastlingRights) -> u64 {
+	let rights = [
+		castling.white_kingside,
+		castling.white_queenside,
+		castling.black_kingside,
+		castling.black_queenside,
+	];
+	rights
+		.iter()
+		.zip(CASTLING_KEYS.iter())
+		.filter(|&(&allowed, _)| allowed)
+		.fold(0, |key, (_, &right)| key ^ right)
+}
+
+/// The key of an en passant target square. Only the column of the square is included, as the row follows
+/// from the side to move.
+pub fn en_passant_key(en_passant: Option<BoardIndex>) -> u64 {
+	en_passant.map_or(0, |index| EN_PASSANT_KEYS[u32::from(index.column) as usize])
+}
+
+/// The key of holding at least `count` pieces of a kind in the pocket of a color. A pocket holding `n` of a
+/// piece includes the keys for each count from 1 up to `n`, so adding or removing one piece changes a
+/// single key.
+pub fn pocket_key(color: Color, piece: Piece, count: u32) -> u64 {
+	random(1024 + (color_index(color) * 6 + piece_index(piece)) as u64 * 64 + u64::from(count))
+}
+
+/// The key of the pockets of both sides
+pub fn pockets_key(pockets: Pockets) -> u64 {
+	let mut key = 0;
+	for &color in &[Color::White, Color::Black] {
+		for &piece in &POCKET_PIECES {
+			for count in 1..=pockets.get(color).count(piece) {
+				key ^= pocket_key(color, piece, count);
+			}
+		}
+	}
+	key
+}
+
+/// The key of having given check at least `count` times with the given color. As with pockets, a side that
+/// has given `n` checks includes the keys for each count from 1 up to `n`.
+pub fn check_key(color: Color, count: u32) -> u64 {
+	random(2048 + color_index(color) as u64 * 64 + u64::from(count))
+}
+
+/// The key of the number of checks each side has given
+pub fn checks_key(checks: CheckCount) -> u64 {
+	let mut key = 0;
+	for &color in &[Color::White, Color::Black] {
+		for count in 1..=checks.get(color) {
+			key ^= check_key(color, count);
+		}
+	}
+	key
+}
+
+/// The key of the side to move
+pub fn turn_key(turn: Color) -> u64 {
+	match turn {
+		Color::White => 0,
+		Color::Black => BLACK_KEY,
+	}
+}
+
+impl GameBoard {
+	/// Compute the Zobrist key of the pieces on the board from scratch
+	pub fn zobrist_key(&self) -> u64 {
+		self.pieces()
+			.fold(0, |key, (index, piece)| key ^ piece_key(piece, index))
+	}
+}
+
+impl GameState {
+	/// The Zobrist key of this game state, covering the pieces on the board, the side to move, the castling
+	/// rights, the column of the en passant target square, the pieces in each side's pocket and the number
+	/// of checks each side has given. The key is updated incrementally as moves are applied and undone, so
+	/// it is cheap to get.
+	pub fn zobrist_key(&self) -> u64 {
+		self.zobrist
+	}
+
+	/// Compute the Zobrist key of this game state from scratch, without using the incrementally updated key
+	pub fn compute_zobrist_key(&self) -> u64 {
+		self.board.zobrist_key()
+			^ turn_key(self.turn)
+			^ castling_key(self.castling)
+			^ en_passant_key(self.en_passant)
+			^ pockets_key(self.pockets)
+			^ checks_key(self.checks)
+	}
+
+	/// Recompute the incrementally updated Zobrist key. This needs to be called after changing the board or
+	/// any other public field of the game state directly, rather than through `apply_move` and `undo_move`.
+	pub fn refresh_zobrist_key(&mut self) {
+		self.zobrist = self.compute_zobrist_key();
+	}
+}
+
+/// The change to the Zobrist key of the board made by moving a piece. `piece` is the piece that was moved
+/// as it was before the move, `en_passant` is whether the move captured en passant and `castling` is how
+/// the move castled, if it did.
+pub(crate) fn move_key(
+	mv: Move,
+	piece: GamePiece,
+	captured: Option<GamePiece>,
+	en_passant: bool,
+	castling: Option<Castling>,
+) -> u64 {
+	if let Some(castling) = castling {
+		let rook = GamePiece::new(Piece::Rook, piece.color);
+		return piece_key(piece, castling.king_start)
+			^ piece_key(piece, castling.king_end)
+			^ piece_key(rook, castling.rook_start)
+			^ piece_key(rook, castling.rook_end);
+	}
+
+	let placed = match mv.promotion {
+		Some(promotion) => GamePiece::new(promotion, piece.color),
+		None => piece,
+	};
+	let mut key = piece_key(piece, mv.start) ^ piece_key(placed, mv.end);
+
+	if let Some(captured) = captured {
+		let index = if en_passant {
+			BoardIndex::new(mv.end.column, mv.start.row)
+		} else {
+			mv.end
+		};
+		key ^= piece_key(captured, index);
+	}
+
+	key
+}
+
+#[test]
+fn zobrist_key_test() {
+	let mut game_state = GameState::standard();
+	assert_eq!(game_state.zobrist_key(), game_state.compute_zobrist_key());
+	assert_eq!(
+		game_state.zobrist_key(),
+		GameState::from_fen(STANDARD_FEN).unwrap().zobrist_key()
+	);
+
+	// The same position reached by different move orders has the same key
+	let mut transposed = GameState::standard();
+	for &mv in &["Nf3", "Nf6", "Nc3", "Nc6"] {
+		let mv = game_state.parse_move(mv).unwrap();
+		game_state.apply_move(mv).unwrap();
+	}
+	for &mv in &["Nc3", "Nc6", "Nf3", "Nf6"] {
+		let mv = transposed.parse_move(mv).unwrap();
+		transposed.apply_move(mv).unwrap();
+	}
+	assert_eq!(game_state.zobrist_key(), transposed.zobrist_key());
+	assert_eq!(game_state.zobrist_key(), game_state.compute_zobrist_key());
+
+	// The en passant column and castling rights are part of the key
+	let with_en_passant = GameState::from_fen("4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1").unwrap();
+	let without_en_passant = GameState::from_fen("4k3/8/8/8/3pP3/8/8/4K3 b - - 0 1").unwrap();
+	assert_ne!(with_en_passant.zobrist_key(), without_en_passant.zobrist_key());
+	// unless no pawn can capture en passant
+	let unusable = GameState::from_fen("4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1").unwrap();
+	let without_en_passant = GameState::from_fen("4k3/8/8/8/4P3/8/8/4K3 b - - 0 1").unwrap();
+	assert_eq!(unusable.zobrist_key(), without_en_passant.zobrist_key());
+	let with_castling = GameState::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+	let without_castling = GameState::from_fen("4k3/8/8/8/8/8/8/4K2R w - - 0 1").unwrap();
+	assert_ne!(with_castling.zobrist_key(), without_castling.zobrist_key());
+
+	// The key is not serialized, and is recomputed when deserializing
+	let json = json::to_value(&game_state).unwrap();
+	assert!(json.get("zobrist").is_none());
+	let deserialized: GameState = json::from_value(json).unwrap();
+	assert_eq!(deserialized, game_state);
+	assert_eq!(deserialized.zobrist_key(), game_state.zobrist_key());
+}
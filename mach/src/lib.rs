@@ -1,5 +1,15 @@
+pub mod book;
+pub mod engine;
 pub mod game;
+pub mod pgn;
 pub mod proto;
+pub mod tablebase;
+pub mod variant;
 
+pub use self::book::*;
+pub use self::engine::*;
 pub use self::game::*;
+pub use self::pgn::*;
 pub use self::proto::*;
+pub use self::tablebase::*;
+pub use self::variant::*;
@@ -0,0 +1,728 @@
+use std::fmt;
+
+use crate::game::*;
+
+/// The tags that every PGN game is expected to have, in the order they are written
+const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+	("Event", "?"),
+	("Site", "?"),
+	("Date", "????.??.??"),
+	("Round", "?"),
+	("White", "?"),
+	("Black", "?"),
+	("Result", "*"),
+];
+
+/// The length that lines of movetext are wrapped at when writing PGN
+const LINE_WIDTH: usize = 80;
+
+/// The deepest variations can be nested inside each other when reading PGN, so that a hostile game can't
+/// overflow the stack
+pub const MAX_VARIATION_DEPTH: usize = 64;
+
+/// A single game in Portable Game Notation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PgnGame {
+	/// The tag pairs of the game, in the order they are written
+	pub tags: Vec<(String, String)>,
+	/// The moves of the main line of the game
+	pub moves: Vec<PgnMove>,
+	/// The result of the game, or `None` if it is still in progress or the result is unknown
+	pub result: Option<GameResult>,
+}
+
+/// A move in a PGN game, along with its annotations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PgnMove {
+	pub mv: Move,
+	/// Numeric annotation glyphs attached to the move. Suffix annotations like `!?` are read as their
+	/// equivalent glyphs.
+	pub nags: Vec<u8>,
+	/// A comment appearing before the move
+	pub comment_before: Option<String>,
+	/// A comment appearing after the move
+	pub comment: Option<String>,
+	/// Alternative lines that could have been played instead of this move, each starting from the
+	/// position before it
+	pub variations: Vec<Vec<PgnMove>>,
+}
+
+impl PgnMove {
+	pub fn new(mv: Move) -> Self {
+		Self {
+			mv,
+			nags: Vec::new(),
+			comment_before: None,
+			comment: None,
+			variations: Vec::new(),
+		}
+	}
+}
+
+/// An error encountered while reading or writing PGN
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PgnError {
+	/// Something other than what was expected was found on the given line
+	UnexpectedToken { line: usize, token: String },
+	/// The text ended in the middle of a tag, string, comment or variation
+	UnexpectedEnd,
+	/// The FEN tag of the game did not describe a valid position
+	InvalidFen(FenError),
+	/// A move in the movetext on the given line could not be played
+	InvalidMove { line: usize, error: SanError },
+	/// A move in a game being written could not be played in the position it was made in
+	IllegalMove(Move),
+	/// A variation on the given line is nested more than `MAX_VARIATION_DEPTH` deep
+	VariationTooDeep { line: usize },
+}
+
+impl fmt::Display for PgnError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			PgnError::UnexpectedToken { line, token } => write!(f, "unexpected '{}' on line {}", token, line),
+			PgnError::UnexpectedEnd => write!(f, "unexpected end of input"),
+			PgnError::InvalidFen(e) => write!(f, "invalid FEN tag: {}", e),
+			PgnError::InvalidMove { line, error } => write!(f, "{} on line {}", error, line),
+			PgnError::IllegalMove(mv) => write!(f, "illegal move from {} to {}", mv.start, mv.end),
+			PgnError::VariationTooDeep { line } => write!(f, "variation on line {} is nested too deeply", line),
+		}
+	}
+}
+
+impl std::error::Error for PgnError {}
+
+impl From<FenError> for PgnError {
+	fn from(t: FenError) -> Self {
+		PgnError::InvalidFen(t)
+	}
+}
+
+impl PgnGame {
+	/// Create a game from the standard starting position with no moves and the seven tag roster set to
+	/// unknown values
+	pub fn new() -> Self {
+		Self {
+			tags: SEVEN_TAG_ROSTER
+				.iter()
+				.map(|&(name, value)| (name.to_owned(), value.to_owned()))
+				.collect(),
+			moves: Vec::new(),
+			result: None,
+		}
+	}
+
+	/// Create a game of the given moves played from the given position. If the moves end the game, the
+	/// result is set accordingly.
+	pub fn from_moves(start: &GameState, moves: &[Move]) -> Result<Self, PgnError> {
+		let mut game = Self::new();
+		game.set_start(start);
+		let mut game_state = start.clone();
+		for &mv in moves {
+			game_state.apply_move(mv).map_err(|_| PgnError::IllegalMove(mv))?;
+			game.moves.push(PgnMove::new(mv));
+		}
+		game.result = game_state.game_over().map(|game_over| game_over.result);
+		Ok(game)
+	}
+
+	/// Get the value of a tag
+	pub fn tag(&self, name: &str) -> Option<&str> {
+		self.tags
+			.iter()
+			.find(|(tag, _)| tag == name)
+			.map(|(_, value)| value.as_str())
+	}
+
+	/// Set the value of a tag, replacing the existing value if there is one
+	pub fn set_tag(&mut self, name: &str, value: &str) {
+		match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+			Some((_, existing)) => *existing = value.to_owned(),
+			None => self.tags.push((name.to_owned(), value.to_owned())),
+		}
+	}
+
+	/// Set the position the game starts from, using the `SetUp` and `FEN` tags if it is not the standard
+	/// starting position, and the `Variant` tag if the game is Chess960
+	pub fn set_start(&mut self, start: &GameState) {
+		self.tags
+			.retain(|(tag, _)| tag != "SetUp" && tag != "FEN" && tag != "Variant");
+		if start.chess960 {
+			self.set_tag("Variant", "Chess960");
+		}
+		if *start != GameState::standard() {
+			self.set_tag("SetUp", "1");
+			self.set_tag("FEN", &start.to_fen());
+		}
+	}
+
+	/// Get the position the game starts from
+	pub fn start(&self) -> Result<GameState, PgnError> {
+		let mut start = match self.tag("FEN") {
+			Some(fen) => GameState::from_fen(fen)?,
+			None => GameState::standard(),
+		};
+		if self
+			.tag("Variant")
+			.is_some_and(|variant| variant.eq_ignore_ascii_case("chess960"))
+		{
+			start.chess960 = true;
+		}
+		Ok(start)
+	}
+
+	/// Get every position in the main line of the game, starting with the starting position and ending
+	/// with the position after the last move
+	pub fn states(&self) -> Result<Vec<GameState>, PgnError> {
+		let mut game_state = self.start()?;
+		let mut states = vec![game_state.clone()];
+		for pgn_move in &self.moves {
+			game_state
+				.apply_move(pgn_move.mv)
+				.map_err(|_| PgnError::IllegalMove(pgn_move.mv))?;
+			states.push(game_state.clone());
+		}
+		Ok(states)
+	}
+
+	/// Read a single game from PGN text
+	pub fn parse(pgn: &str) -> Result<Self, PgnError> {
+		let mut parser = Parser::new(pgn)?;
+		let game = parser.parse_game()?.ok_or(PgnError::UnexpectedEnd)?;
+		match parser.tokens.get(parser.position) {
+			None => Ok(game),
+			Some((token, line)) => Err(PgnError::UnexpectedToken {
+				line: *line,
+				token: token.to_string(),
+			}),
+		}
+	}
+
+	/// Write the game as PGN text
+	pub fn to_pgn(&self) -> Result<String, PgnError> {
+		let mut pgn = String::new();
+		for (name, value) in &self.tags {
+			let value = if name == "Result" {
+				result_str(self.result)
+			} else {
+				value
+			};
+			let escaped = value.replace('\\', "\\\\").replace('"', "\\\"");
+			pgn.push_str(&format!("[{} \"{}\"]\n", name, escaped));
+		}
+		pgn.push('\n');
+
+		let mut words = Vec::new();
+		write_line(&self.start()?, &self.moves, &mut words)?;
+		words.push(result_str(self.result).to_owned());
+
+		let mut line_length = 0;
+		for (i, word) in words.iter().enumerate() {
+			let joined = i > 0 && words[i - 1] != "(" && word != ")";
+			if joined && line_length + 1 + word.len() > LINE_WIDTH {
+				pgn.push('\n');
+				line_length = 0;
+			} else if joined {
+				pgn.push(' ');
+				line_length += 1;
+			}
+			pgn.push_str(word);
+			line_length += word.len();
+		}
+		pgn.push('\n');
+		Ok(pgn)
+	}
+}
+
+impl Default for PgnGame {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// Read every game in a PGN file
+pub fn parse_pgn(pgn: &str) -> Result<Vec<PgnGame>, PgnError> {
+	let mut parser = Parser::new(pgn)?;
+	let mut games = Vec::new();
+	while let Some(game) = parser.parse_game()? {
+		games.push(game);
+	}
+	Ok(games)
+}
+
+fn result_str(result: Option<GameResult>) -> &'static str {
+	match result {
+		Some(GameResult::WhiteWins) => "1-0",
+		Some(GameResult::BlackWins) => "0-1",
+		Some(GameResult::Draw) => "1/2-1/2",
+		None => "*",
+	}
+}
+
+/// Write the words making up a line of moves and everything attached to them
+fn write_line(start: &GameState, moves: &[PgnMove], words: &mut Vec<String>) -> Result<(), PgnError> {
+	let mut game_state = start.clone();
+	let mut needs_number = true;
+	for pgn_move in moves {
+		if let Some(comment) = &pgn_move.comment_before {
+			words.push(format!("{{{}}}", comment));
+			needs_number = true;
+		}
+		match game_state.turn {
+			Color::White => words.push(format!("{}.", game_state.fullmove_number)),
+			Color::Black if needs_number => words.push(format!("{}...", game_state.fullmove_number)),
+			Color::Black => {}
+		}
+		if !game_state.is_legal(pgn_move.mv) {
+			return Err(PgnError::IllegalMove(pgn_move.mv));
+		}
+		words.push(game_state.move_to_san(pgn_move.mv));
+		words.extend(pgn_move.nags.iter().map(|nag| format!("${}", nag)));
+		needs_number = false;
+		if let Some(comment) = &pgn_move.comment {
+			words.push(format!("{{{}}}", comment));
+			needs_number = true;
+		}
+		for variation in &pgn_move.variations {
+			words.push("(".to_owned());
+			write_line(&game_state, variation, words)?;
+			words.push(")".to_owned());
+			needs_number = true;
+		}
+		game_state.apply_move_unchecked(pgn_move.mv);
+	}
+	Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+	TagOpen,
+	TagClose,
+	String(String),
+	Symbol(String),
+	Period,
+	Nag(u8),
+	Comment(String),
+	VariationOpen,
+	VariationClose,
+	Result(Option<GameResult>),
+}
+
+impl fmt::Display for Token {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Token::TagOpen => write!(f, "["),
+			Token::TagClose => write!(f, "]"),
+			Token::String(string) => write!(f, "\"{}\"", string),
+			Token::Symbol(symbol) => write!(f, "{}", symbol),
+			Token::Period => write!(f, "."),
+			Token::Nag(nag) => write!(f, "${}", nag),
+			Token::Comment(comment) => write!(f, "{{{}}}", comment),
+			Token::VariationOpen => write!(f, "("),
+			Token::VariationClose => write!(f, ")"),
+			Token::Result(result) => write!(f, "{}", result_str(*result)),
+		}
+	}
+}
+
+/// Split PGN text into tokens, each paired with the line it started on
+fn tokenize(pgn: &str) -> Result<Vec<(Token, usize)>, PgnError> {
+	let mut tokens = Vec::new();
+	let mut chars = pgn.chars().peekable();
+	let mut line = 1;
+	let mut line_start = true;
+	while let Some(c) = chars.next() {
+		let token_line = line;
+		let at_line_start = line_start;
+		line_start = c == '\n';
+		let token = match c {
+			'\n' => {
+				line += 1;
+				continue;
+			}
+			c if c.is_whitespace() => continue,
+			// A percent sign at the start of a line escapes the rest of the line
+			'%' if at_line_start => {
+				while chars.peek().is_some_and(|&c| c != '\n') {
+					chars.next();
+				}
+				continue;
+			}
+			';' => {
+				let mut comment = String::new();
+				while let Some(&c) = chars.peek() {
+					if c == '\n' {
+						break;
+					}
+					comment.push(c);
+					chars.next();
+				}
+				Token::Comment(comment.trim().to_owned())
+			}
+			'{' => {
+				let mut comment = String::new();
+				loop {
+					match chars.next() {
+						Some('}') => break,
+						Some(c) => {
+							if c == '\n' {
+								line += 1;
+							}
+							comment.push(c);
+						}
+						None => return Err(PgnError::UnexpectedEnd),
+					}
+				}
+				Token::Comment(comment.split_whitespace().collect::<Vec<_>>().join(" "))
+			}
+			'"' => {
+				let mut string = String::new();
+				loop {
+					match chars.next() {
+						Some('"') => break,
+						Some('\\') => match chars.next() {
+							Some(c) => string.push(c),
+							None => return Err(PgnError::UnexpectedEnd),
+						},
+						Some('\n') | None => return Err(PgnError::UnexpectedEnd),
+						Some(c) => string.push(c),
+					}
+				}
+				Token::String(string)
+			}
+			'[' => Token::TagOpen,
+			']' => Token::TagClose,
+			'(' => Token::VariationOpen,
+			')' => Token::VariationClose,
+			'.' => Token::Period,
+			'*' => Token::Result(None),
+			'$' => {
+				let mut digits = String::new();
+				while let Some(&c) = chars.peek().filter(|c| c.is_ascii_digit()) {
+					digits.push(c);
+					chars.next();
+				}
+				let nag = digits.parse().map_err(|_| PgnError::UnexpectedToken {
+					line,
+					token: format!("${}", digits),
+				})?;
+				Token::Nag(nag)
+			}
+			'!' | '?' => {
+				let mut suffix = c.to_string();
+				while let Some(&c) = chars.peek().filter(|&&c| c == '!' || c == '?') {
+					suffix.push(c);
+					chars.next();
+				}
+				let nag = match suffix.as_str() {
+					"!" => 1,
+					"?" => 2,
+					"!!" => 3,
+					"??" => 4,
+					"!?" => 5,
+					"?!" => 6,
+					_ => return Err(PgnError::UnexpectedToken { line, token: suffix }),
+				};
+				Token::Nag(nag)
+			}
+			c if c.is_ascii_alphanumeric() => {
+				let mut symbol = c.to_string();
+				while let Some(&c) = chars
+					.peek()
+					.filter(|&&c| c.is_ascii_alphanumeric() || "_+#=:-/".contains(c))
+				{
+					symbol.push(c);
+					chars.next();
+				}
+				match symbol.as_str() {
+					"1-0" => Token::Result(Some(GameResult::WhiteWins)),
+					"0-1" => Token::Result(Some(GameResult::BlackWins)),
+					"1/2-1/2" => Token::Result(Some(GameResult::Draw)),
+					_ => Token::Symbol(symbol),
+				}
+			}
+			c => {
+				return Err(PgnError::UnexpectedToken {
+					line,
+					token: c.to_string(),
+				})
+			}
+		};
+		tokens.push((token, token_line));
+	}
+	Ok(tokens)
+}
+
+struct Parser {
+	tokens: Vec<(Token, usize)>,
+	position: usize,
+}
+
+impl Parser {
+	fn new(pgn: &str) -> Result<Self, PgnError> {
+		Ok(Self {
+			tokens: tokenize(pgn)?,
+			position: 0,
+		})
+	}
+
+	fn peek(&self) -> Option<&Token> {
+		self.tokens.get(self.position).map(|(token, _)| token)
+	}
+
+	fn next(&mut self) -> Result<(Token, usize), PgnError> {
+		let token = self.tokens.get(self.position).cloned().ok_or(PgnError::UnexpectedEnd)?;
+		self.position += 1;
+		Ok(token)
+	}
+
+	fn expect(&mut self, expected: Token) -> Result<(), PgnError> {
+		match self.next()? {
+			(ref token, _) if *token == expected => Ok(()),
+			(token, line) => Err(PgnError::UnexpectedToken {
+				line,
+				token: token.to_string(),
+			}),
+		}
+	}
+
+	/// Read the next game, or `None` if there are no more games
+	fn parse_game(&mut self) -> Result<Option<PgnGame>, PgnError> {
+		if self.peek().is_none() {
+			return Ok(None);
+		}
+		let mut game = PgnGame {
+			tags: Vec::new(),
+			moves: Vec::new(),
+			result: None,
+		};
+		while self.peek() == Some(&Token::TagOpen) {
+			self.next()?;
+			let name = match self.next()? {
+				(Token::Symbol(name), _) => name,
+				(token, line) => {
+					return Err(PgnError::UnexpectedToken {
+						line,
+						token: token.to_string(),
+					})
+				}
+			};
+			let value = match self.next()? {
+				(Token::String(value), _) => value,
+				(token, line) => {
+					return Err(PgnError::UnexpectedToken {
+						line,
+						token: token.to_string(),
+					})
+				}
+			};
+			self.expect(Token::TagClose)?;
+			game.tags.push((name, value));
+		}
+
+		let start = game.start()?;
+		game.moves = self.parse_line(&start, 0)?;
+		match self.peek() {
+			Some(Token::Result(result)) => {
+				game.result = *result;
+				self.next()?;
+			}
+			// A game may leave out the result if it is the last one, or is followed directly by the tags
+			// of the next game
+			None | Some(Token::TagOpen) => {}
+			Some(_) => {
+				let (token, line) = self.next()?;
+				return Err(PgnError::UnexpectedToken {
+					line,
+					token: token.to_string(),
+				});
+			}
+		}
+		Ok(Some(game))
+	}
+
+	/// Read a line of moves played from the given position, stopping at the end of a variation or game
+	fn parse_line(&mut self, start: &GameState, depth: usize) -> Result<Vec<PgnMove>, PgnError> {
+		let mut moves: Vec<PgnMove> = Vec::new();
+		let mut game_state = start.clone();
+		let mut previous_state = start.clone();
+		let mut comment_before: Option<String> = None;
+		loop {
+			match self.peek() {
+				None | Some(Token::Result(_)) | Some(Token::VariationClose) | Some(Token::TagOpen) => break,
+				_ => {}
+			}
+			let (token, line) = self.next()?;
+			match token {
+				Token::Symbol(ref symbol) if symbol.chars().all(|c| c.is_ascii_digit()) => {}
+				Token::Period => {}
+				Token::Symbol(san) => {
+					let mv = game_state
+						.parse_san(&san)
+						.map_err(|error| PgnError::InvalidMove { line, error })?;
+					let mut pgn_move = PgnMove::new(mv);
+					pgn_move.comment_before = comment_before.take();
+					moves.push(pgn_move);
+					previous_state = game_state.clone();
+					game_state.apply_move_unchecked(mv);
+				}
+				Token::Nag(nag) => match moves.last_mut() {
+					Some(last) => last.nags.push(nag),
+					None => {
+						return Err(PgnError::UnexpectedToken {
+							line,
+							token: token.to_string(),
+						})
+					}
+				},
+				Token::Comment(comment) => {
+					let target = match moves.last_mut() {
+						Some(last) if comment_before.is_none() => &mut last.comment,
+						_ => &mut comment_before,
+					};
+					match target {
+						Some(existing) => {
+							existing.push(' ');
+							existing.push_str(&comment);
+						}
+						None => *target = Some(comment),
+					}
+				}
+				Token::VariationOpen => {
+					if depth >= MAX_VARIATION_DEPTH {
+						return Err(PgnError::VariationTooDeep { line });
+					}
+					let variation = self.parse_line(&previous_state, depth + 1)?;
+					self.expect(Token::VariationClose)?;
+					match moves.last_mut() {
+						Some(last) => last.variations.push(variation),
+						None => {
+							return Err(PgnError::UnexpectedToken {
+								line,
+								token: token.to_string(),
+							})
+						}
+					}
+				}
+				token => {
+					return Err(PgnError::UnexpectedToken {
+						line,
+						token: token.to_string(),
+					})
+				}
+			}
+		}
+		// A comment after the last move of a line is attached to that move
+		if let (Some(comment), Some(last)) = (comment_before, moves.last_mut()) {
+			last.comment = Some(comment);
+		}
+		Ok(moves)
+	}
+}
+
+#[test]
+fn pgn_round_trip_test() {
+	let pgn = "[Event \"Casual \\\"blitz\\\" game\"]
+[Site \"?\"]
+[Date \"2020.02.14\"]
+[Round \"?\"]
+[White \"Alice\"]
+[Black \"Bob\"]
+[Result \"1-0\"]
+
+{Opening comment} 1. e4 e5 2. Nf3 $1 {The most common move} (2. f4 exf4 (2... d5)
+3. Bc4) 2... Nc6 3. Bc4 Nd4 4. Nxe5 Qg5 5. Nxf7 $4 (5. Bxf7+ Ke7 6. c3) 5...
+Qxg2 6. Rf1 Qxe4+ 7. Be2 Nf3# 1-0
+";
+	let game = PgnGame::parse(pgn).unwrap();
+	assert_eq!(game.tag("Event"), Some("Casual \"blitz\" game"));
+	assert_eq!(game.moves.len(), 14);
+	assert_eq!(game.moves[0].comment_before.as_deref(), Some("Opening comment"));
+	assert_eq!(game.moves[2].nags, vec![1]);
+	assert_eq!(game.moves[2].comment.as_deref(), Some("The most common move"));
+	assert_eq!(game.moves[2].variations.len(), 1);
+	assert_eq!(game.moves[2].variations[0][1].variations.len(), 1);
+	assert_eq!(game.moves[8].nags, vec![4]);
+	assert_eq!(game.result, Some(GameResult::WhiteWins));
+	assert_eq!(game.to_pgn().unwrap(), pgn);
+
+	let states = game.states().unwrap();
+	assert_eq!(states.len(), 15);
+	assert_eq!(
+		states.last().unwrap().to_fen(),
+		"r1b1kbnr/pppp1Npp/8/8/4q3/5n2/PPPPBP1P/RNBQKR2 w Qkq - 2 8"
+	);
+	assert!(states.last().unwrap().is_checkmate());
+}
+
+#[test]
+fn pgn_parse_test() {
+	let pgn = "[Event \"First\"]
+[SetUp \"1\"]
+[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 30\"]
+
+30... Kd7 31. e4!? ; a rest of line comment
+Ke6 *
+
+% This line is escaped
+[Event \"Second\"]
+
+1. d4 d5 2. c4 1/2-1/2
+";
+	let games = parse_pgn(pgn).unwrap();
+	assert_eq!(games.len(), 2);
+	assert_eq!(games[0].result, None);
+	assert_eq!(games[0].moves.len(), 3);
+	assert_eq!(games[0].moves[1].nags, vec![5]);
+	assert_eq!(games[0].moves[1].comment.as_deref(), Some("a rest of line comment"));
+	assert_eq!(
+		games[0].to_pgn().unwrap(),
+		"[Event \"First\"]
+[SetUp \"1\"]
+[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 30\"]
+
+30... Kd7 31. e4 $5 {a rest of line comment} 31... Ke6 *
+"
+	);
+	assert_eq!(games[1].tag("Event"), Some("Second"));
+	assert_eq!(games[1].result, Some(GameResult::Draw));
+	assert_eq!(games[1].states().unwrap().len(), 4);
+
+	assert_eq!(
+		PgnGame::parse("1. e4 e5 2. Ke3 *"),
+		Err(PgnError::InvalidMove {
+			line: 1,
+			error: SanError::IllegalMove("Ke3".to_owned()),
+		})
+	);
+	assert_eq!(PgnGame::parse("1. e4 (1. d4"), Err(PgnError::UnexpectedEnd));
+
+	// Deeply nested variations are rejected instead of overflowing the stack
+	let nested = format!("1. e4 {}{} *", "(1. d4 ".repeat(100_000), ")".repeat(100_000));
+	assert_eq!(PgnGame::parse(&nested), Err(PgnError::VariationTooDeep { line: 1 }));
+}
+
+#[test]
+fn pgn_from_moves_test() {
+	let mut game_state = GameState::standard();
+	let mut moves = Vec::new();
+	for san in &["f3", "e5", "g4", "Qh4#"] {
+		let mv = game_state.parse_san(san).unwrap();
+		game_state.apply_move(mv).unwrap();
+		moves.push(mv);
+	}
+	let game = PgnGame::from_moves(&GameState::standard(), &moves).unwrap();
+	assert_eq!(game.result, Some(GameResult::BlackWins));
+	assert_eq!(
+		game.to_pgn().unwrap(),
+		"[Event \"?\"]
+[Site \"?\"]
+[Date \"????.??.??\"]
+[Round \"?\"]
+[White \"?\"]
+[Black \"?\"]
+[Result \"0-1\"]
+
+1. f3 e5 2. g4 Qh4# 0-1
+"
+	);
+}
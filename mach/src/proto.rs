@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-use crate::game::*;
+use crate::{game::*, variant::*};
 
 pub mod id;
 
@@ -23,6 +23,12 @@ pub enum MachMessage {
 	GameMoveRequest(GameMoveRequest),
 	GameMoveResponse(GameMoveResponse),
 	GameMoveHappened(GameMoveHappened),
+	GameDropRequest(GameDropRequest),
+	GameDropHappened(GameDropHappened),
+	GamePieceReceived(GamePieceReceived),
+	GameEnded(GameEnded),
+	ClaimDrawRequest(ClaimDrawRequest),
+	ClaimDrawResponse(ClaimDrawResponse),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -44,6 +50,58 @@ pub struct HandshakeFailure {
 pub struct CreateGameRequest {
 	pub id: Id,
 	pub color: Color,
+	/// The position the game starts from, the standard starting position if absent. Ignored for variants
+	/// that have their own starting position.
+	#[serde(default)]
+	pub start: StartPosition,
+	/// The variant whose rules the game is played by, standard chess if absent
+	#[serde(default)]
+	pub variant: VariantKind,
+	/// An engine run by the server to play the other side, instead of a player joining by invite token
+	#[serde(default)]
+	pub opponent: Option<EngineOpponent>,
+	/// For Bughouse, the game to play as the partner board of, which must be a Bughouse game that has no
+	/// partner board yet
+	#[serde(default)]
+	pub partner: Option<ServerId>,
+}
+
+/// A chess engine on the server machine, asked to play a game at a certain strength
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EngineOpponent {
+	/// The name the engine is configured under on the server, or the first engine configured if absent
+	#[serde(default)]
+	pub engine: Option<String>,
+	/// How strongly the engine plays, from 1 for the weakest to 20 for the strongest
+	pub strength: u32,
+}
+
+impl EngineOpponent {
+	pub const MIN_STRENGTH: u32 = 1;
+	pub const MAX_STRENGTH: u32 = 20;
+}
+
+/// The position a new game starts from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum StartPosition {
+	#[default]
+	Standard,
+	/// The Chess960 starting position with the given Scharnagl number. Numbers of 960 or more are taken
+	/// modulo 960.
+	Chess960(u32),
+	/// A Chess960 starting position picked at random by the server
+	RandomChess960,
+}
+
+impl StartPosition {
+	/// Create the game state a game starting from this position starts with
+	pub fn game_state(self) -> GameState {
+		match self {
+			StartPosition::Standard => GameState::standard(),
+			StartPosition::Chess960(n) => GameState::chess960(n % CHESS960_POSITIONS),
+			StartPosition::RandomChess960 => GameState::random_chess960(),
+		}
+	}
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -51,6 +109,12 @@ pub struct CreateGameResponse {
 	pub id: Id,
 	pub game_id: ServerId,
 	pub color: Color,
+	/// The name of the engine playing the other side, if one was requested and could be started
+	#[serde(default)]
+	pub opponent: Option<String>,
+	/// The partner board of the game, if one was requested and the game could be paired with it
+	#[serde(default)]
+	pub partner: Option<ServerId>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -88,6 +152,9 @@ pub struct GetGameStateRequest {
 pub struct GetGameStateResponse {
 	pub id: Id,
 	pub game_state: GameState,
+	/// The variant whose rules the game is played by
+	#[serde(default)]
+	pub variant: VariantKind,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -96,12 +163,50 @@ pub struct GameMoveRequest {
 	pub game_id: ServerId,
 	pub move_start: BoardIndex,
 	pub move_end: BoardIndex,
+	/// The piece to promote to, required when a pawn moves to the last row
+	#[serde(default)]
+	pub promotion: Option<Piece>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GameMoveResponse {
 	pub id: Id,
 	pub success: bool,
+	/// Why the move was refused, present when `success` is false
+	#[serde(default)]
+	pub reason: Option<MoveRejection>,
+}
+
+/// The reason the server refused to make a requested move
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MoveRejection {
+	/// There is no game with the requested id
+	UnknownGame,
+	/// The client that requested the move is not playing in the game
+	NotAPlayer,
+	/// The game has already ended
+	GameOver,
+	/// It is currently the other player's turn
+	NotYourTurn,
+	/// There is no piece on the start square of the move
+	EmptySquare,
+	/// The piece on the start square belongs to the other player
+	WrongColor,
+	/// The piece can not make this move, or making it would leave its own king in check
+	IllegalMove,
+	/// The start or end square of the move is not on the board of the game
+	OffBoard,
+}
+
+impl From<MoveError> for MoveRejection {
+	fn from(t: MoveError) -> Self {
+		match t {
+			MoveError::EmptySquare => MoveRejection::EmptySquare,
+			MoveError::WrongColor => MoveRejection::WrongColor,
+			MoveError::IllegalMove => MoveRejection::IllegalMove,
+			MoveError::OffBoard => MoveRejection::OffBoard,
+		}
+	}
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -109,4 +214,72 @@ pub struct GameMoveHappened {
 	pub game_id: ServerId,
 	pub move_start: BoardIndex,
 	pub move_end: BoardIndex,
+	#[serde(default)]
+	pub promotion: Option<Piece>,
+}
+
+/// Sent by a player to drop a piece from their pocket onto an empty square, in variants such as Crazyhouse.
+/// Answered with a `GameMoveResponse`, as for any other move.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameDropRequest {
+	pub id: Id,
+	pub game_id: ServerId,
+	pub piece: Piece,
+	pub index: BoardIndex,
+}
+
+/// Sent by the server to the opponent of a player who dropped a piece
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameDropHappened {
+	pub game_id: ServerId,
+	pub piece: Piece,
+	pub index: BoardIndex,
+}
+
+/// Sent by the server to both players of a Bughouse game when a piece captured on the partner board is
+/// passed into the pocket of the given color
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GamePieceReceived {
+	pub game_id: ServerId,
+	pub color: Color,
+	pub piece: Piece,
+}
+
+/// Sent by the server to both players once a game has ended
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameEnded {
+	pub game_id: ServerId,
+	pub result: GameResult,
+	pub termination: Termination,
+}
+
+/// Sent by a player to claim a draw by threefold repetition or the fifty-move rule. If the claim is
+/// accepted, the server ends the game and sends `GameEnded` to both players.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClaimDrawRequest {
+	pub id: Id,
+	pub game_id: ServerId,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClaimDrawResponse {
+	pub id: Id,
+	pub success: bool,
+	/// Why the claim was refused, present when `success` is false
+	#[serde(default)]
+	pub reason: Option<DrawClaimRejection>,
+}
+
+/// The reason the server refused a claim for a draw
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DrawClaimRejection {
+	/// There is no game with the requested id
+	UnknownGame,
+	/// The client that claimed the draw is not playing in the game
+	NotAPlayer,
+	/// The game has already ended
+	GameOver,
+	/// The current position has not occurred three times and fifty moves have not passed without a
+	/// capture or pawn move
+	NoDrawAvailable,
 }
@@ -0,0 +1,700 @@
+//! Endgame tablebases, which hold the distance to mate with perfect play from every position with only a few
+//! pieces left. Tables are generated by retrograde analysis, working backwards from every checkmate, and are
+//! saved to a directory so that they only need to be generated once.
+
+use std::{collections::HashMap, fmt, fs, io, path::Path};
+
+use crate::{
+	engine::{piece_value, Score},
+	game::*,
+};
+
+/// The most pieces, counting both kings, that a table can be generated for
+pub const MAX_PIECES: usize = 4;
+/// The pieces other than kings, in the order they are listed in the name of a material set
+const PIECE_ORDER: [Piece; 5] = [Piece::Queen, Piece::Rook, Piece::Bishop, Piece::Knight, Piece::Pawn];
+/// The bytes every table file starts with
+const MAGIC: &[u8] = b"MTB1";
+/// The extension of table files, which are named after their material set
+const EXTENSION: &str = "mtb";
+/// The stored value of a position that is drawn with perfect play. Other values are one more than the
+/// number of plies until mate, which is odd when the side to move gives mate and even when it is mated.
+const DRAW: u8 = 0;
+/// The stored value of a position whose value has not been found yet while a table is being generated
+const UNKNOWN: u8 = 254;
+/// The stored value of an index that does not describe a legal position
+const ILLEGAL: u8 = 255;
+/// The count of unresolved moves of a position that can not be lost, because one of its moves leaves the
+/// table into a position that is not lost
+const CANNOT_LOSE: u8 = 255;
+
+/// The distance to mate of a position with perfect play from both sides, counted in plies
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Dtm {
+	/// The side to move gives mate after the given number of plies
+	Win(u32),
+	/// The side to move is mated after the given number of plies
+	Loss(u32),
+	Draw,
+}
+
+impl Dtm {
+	fn from_value(value: u8) -> Option<Self> {
+		match value {
+			DRAW => Some(Dtm::Draw),
+			UNKNOWN | ILLEGAL => None,
+			value => {
+				let plies = u32::from(value - 1);
+				Some(if plies % 2 == 1 {
+					Dtm::Win(plies)
+				} else {
+					Dtm::Loss(plies)
+				})
+			}
+		}
+	}
+
+	/// The score of the position for the side to move, as the engine would give it
+	pub fn score(self) -> Score {
+		match self {
+			Dtm::Win(plies) => Score::mate_in(plies),
+			Dtm::Loss(plies) => Score::mated_in(plies),
+			Dtm::Draw => Score::DRAW,
+		}
+	}
+}
+
+/// An error encountered while generating, reading or writing tables
+#[derive(Debug)]
+pub enum TablebaseError {
+	/// A table file could not be read or written
+	Io(io::Error),
+	/// The file with the given name is not a table of the material set it is named after
+	InvalidTable(String),
+	/// No table can be generated for the material set, as it has more than `MAX_PIECES` pieces or pawns on
+	/// both sides, which would need en passant captures to be taken into account
+	Unsupported(Material),
+}
+
+impl fmt::Display for TablebaseError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			TablebaseError::Io(e) => write!(f, "{}", e),
+			TablebaseError::InvalidTable(name) => write!(f, "{} is not a valid table", name),
+			TablebaseError::Unsupported(material) => write!(f, "tables for {} can not be generated", material),
+		}
+	}
+}
+
+impl std::error::Error for TablebaseError {}
+
+impl From<io::Error> for TablebaseError {
+	fn from(t: io::Error) -> Self {
+		TablebaseError::Io(t)
+	}
+}
+
+/// The pieces left on the board besides the two kings, such as `KBNK` for a king, bishop and knight
+/// against a lone king. Tables are stored with the stronger side as white, and positions where black is
+/// the stronger side are looked up with the colors swapped.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Material {
+	/// The pieces of the stronger side, from the most to the least valuable
+	strong: Vec<Piece>,
+	/// The pieces of the weaker side, from the most to the least valuable
+	weak: Vec<Piece>,
+}
+
+impl Material {
+	/// Describe the pieces of two sides, in any order. Neither side may have a king among its pieces.
+	pub fn new(a: &[Piece], b: &[Piece]) -> Self {
+		let sorted = |pieces: &[Piece]| {
+			let mut pieces = pieces.to_vec();
+			pieces.sort_by_key(|&piece| order(piece));
+			pieces
+		};
+		let (a, b) = (sorted(a), sorted(b));
+		let value = |pieces: &[Piece]| pieces.iter().map(|&piece| piece_value(piece)).sum::<i32>();
+		let key = |pieces: &[Piece]| pieces.iter().map(|&piece| order(piece)).collect::<Vec<_>>();
+		let b_stronger = value(&b) > value(&a) || (value(&b) == value(&a) && key(&b) < key(&a));
+		if b_stronger {
+			Self { strong: b, weak: a }
+		} else {
+			Self { strong: a, weak: b }
+		}
+	}
+
+	/// Read a material set from its name, such as `KQK` or `KRKN`
+	pub fn parse(name: &str) -> Option<Self> {
+		let rest = name.strip_prefix('K')?;
+		let (strong, weak) = rest.split_at(rest.find('K')?);
+		let pieces = |letters: &str| {
+			letters
+				.chars()
+				.map(|c| Piece::from_char(c).filter(|&piece| piece != Piece::King))
+				.collect::<Option<Vec<_>>>()
+		};
+		Some(Self::new(&pieces(strong)?, &pieces(&weak[1..])?))
+	}
+
+	/// Find the material set of a board and which color is the stronger side. Returns `None` unless each
+	/// side has exactly one king.
+	pub fn of(board: &BitGameBoard) -> Option<(Self, Color)> {
+		let mut white = Vec::new();
+		let mut black = Vec::new();
+		for &color in &[Color::White, Color::Black] {
+			if board.piece_set(GamePiece::new(Piece::King, color)).count() != 1 {
+				return None;
+			}
+		}
+		for (_, piece) in board.pieces().filter(|(_, piece)| piece.piece != Piece::King) {
+			match piece.color {
+				Color::White => white.push(piece.piece),
+				Color::Black => black.push(piece.piece),
+			}
+		}
+		let material = Self::new(&white, &black);
+		white.sort_by_key(|&piece| order(piece));
+		let strong = if white == material.strong {
+			Color::White
+		} else {
+			Color::Black
+		};
+		Some((material, strong))
+	}
+
+	/// The number of pieces on the board, counting both kings
+	pub fn piece_count(&self) -> usize {
+		2 + self.strong.len() + self.weak.len()
+	}
+
+	/// Check whether neither side can ever give mate, so that no table is needed
+	pub fn is_dead(&self) -> bool {
+		let minor = |&piece: &Piece| piece == Piece::Bishop || piece == Piece::Knight;
+		self.strong.iter().chain(&self.weak).all(minor) && self.strong.len() + self.weak.len() <= 1
+	}
+
+	fn check_supported(&self) -> Result<(), TablebaseError> {
+		let has_pawn = |pieces: &[Piece]| pieces.contains(&Piece::Pawn);
+		if self.piece_count() > MAX_PIECES || (has_pawn(&self.strong) && has_pawn(&self.weak)) {
+			return Err(TablebaseError::Unsupported(self.clone()));
+		}
+		Ok(())
+	}
+
+	/// The pieces of a table in the order their squares make up its indices: the white king, the black
+	/// king, the pieces of the stronger side as white and the pieces of the weaker side as black
+	fn slots(&self) -> Vec<GamePiece> {
+		let white = self.strong.iter().map(|&piece| GamePiece::new(piece, Color::White));
+		let black = self.weak.iter().map(|&piece| GamePiece::new(piece, Color::Black));
+		vec![
+			GamePiece::new(Piece::King, Color::White),
+			GamePiece::new(Piece::King, Color::Black),
+		]
+		.into_iter()
+		.chain(white)
+		.chain(black)
+		.collect()
+	}
+
+	/// The material sets a single move can lead to, by capturing a piece, promoting a pawn or both
+	fn successors(&self) -> Vec<Material> {
+		let mut successors = Vec::new();
+		for (mover, other) in [(&self.strong, &self.weak), (&self.weak, &self.strong)] {
+			let mut promotions = vec![mover.clone()];
+			if let Some(pawn) = mover.iter().position(|&piece| piece == Piece::Pawn) {
+				for &promotion in &PROMOTION_PIECES {
+					let mut promoted = mover.clone();
+					promoted[pawn] = promotion;
+					promotions.push(promoted);
+				}
+			}
+			let mut captures = vec![other.clone()];
+			for captured in 0..other.len() {
+				let mut remaining = other.clone();
+				remaining.remove(captured);
+				captures.push(remaining);
+			}
+			for (i, mover) in promotions.iter().enumerate() {
+				for (j, other) in captures.iter().enumerate() {
+					let successor = Material::new(mover, other);
+					if (i, j) != (0, 0) && !successors.contains(&successor) {
+						successors.push(successor);
+					}
+				}
+			}
+		}
+		successors
+	}
+}
+
+impl fmt::Display for Material {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "K")?;
+		for piece in &self.strong {
+			write!(f, "{}", piece.to_char())?;
+		}
+		write!(f, "K")?;
+		for piece in &self.weak {
+			write!(f, "{}", piece.to_char())?;
+		}
+		Ok(())
+	}
+}
+
+/// The position of a piece other than a king in `PIECE_ORDER`
+fn order(piece: Piece) -> usize {
+	PIECE_ORDER
+		.iter()
+		.position(|&p| p == piece)
+		.unwrap_or(PIECE_ORDER.len())
+}
+
+/// The number a table gives a square of a standard board, counting along each row from a1, so that the
+/// square on the other side of the board is `square ^ 56`
+fn table_square(index: BoardIndex) -> usize {
+	(u32::from(index.row) * 8 + u32::from(index.column)) as usize
+}
+
+/// The inverse of `table_square`
+fn from_table_square(square: usize) -> BoardIndex {
+	BoardIndex::new(Column::nth(square as u32 % 8), Row::nth(square as u32 / 8))
+}
+
+/// The distance to mate of every position of one material set, with the stronger side as white. Each
+/// position is indexed by the squares of the pieces in the order of `Material::slots`, followed by the
+/// side to move.
+pub struct Table {
+	material: Material,
+	values: Vec<u8>,
+}
+
+impl Table {
+	/// Get the material set of the table
+	pub fn material(&self) -> &Material {
+		&self.material
+	}
+
+	/// The number of positions in a table of the given number of pieces
+	fn size(pieces: usize) -> usize {
+		2 << (6 * pieces)
+	}
+
+	fn index(squares: &[usize], turn: Color) -> usize {
+		squares.iter().fold(0, |index, &square| index * 64 + square) * 2 + (turn == Color::Black) as usize
+	}
+
+	/// Set up the position at an index, returning `None` if the index does not describe a legal position
+	fn position(slots: &[GamePiece], index: usize) -> Option<(BitGameBoard, Color, [usize; MAX_PIECES])> {
+		let turn = if index.is_multiple_of(2) {
+			Color::White
+		} else {
+			Color::Black
+		};
+		let mut squares = [0; MAX_PIECES];
+		let mut rest = index / 2;
+		for square in squares[..slots.len()].iter_mut().rev() {
+			*square = rest % 64;
+			rest /= 64;
+		}
+		let mut board = BitGameBoard::new();
+		for (&piece, &square) in slots.iter().zip(&squares) {
+			let square = from_table_square(square);
+			let last_row = square.row == Row::R1 || square.row == Row::R8;
+			if board.occupied().contains(square) || (piece.piece == Piece::Pawn && last_row) {
+				return None;
+			}
+			board.set_board_index(square, Some(piece));
+		}
+		if board.is_king_attacked(turn.opposite()) {
+			return None;
+		}
+		Some((board, turn, squares))
+	}
+
+	/// Look up a board with the given side to move, whose material set is that of this table with the
+	/// stronger side playing `strong`
+	fn get(&self, board: &BitGameBoard, turn: Color, strong: Color) -> Option<Dtm> {
+		// Black as the stronger side is looked up as white with the board upside down
+		let flip = strong == Color::Black;
+		let mut squares = [0; MAX_PIECES];
+		let mut used = Bitboard::EMPTY;
+		let slots = self.material.slots();
+		for (slot, square) in slots.iter().zip(squares.iter_mut()) {
+			let color = if flip { slot.color.opposite() } else { slot.color };
+			let index = (board.piece_set(GamePiece::new(slot.piece, color)) & !used).first()?;
+			used |= Bitboard::from_index(index);
+			*square = if flip {
+				table_square(index) ^ 56
+			} else {
+				table_square(index)
+			};
+		}
+		let turn = if flip { turn.opposite() } else { turn };
+		Dtm::from_value(self.values[Self::index(&squares[..slots.len()], turn)])
+	}
+
+	/// Generate the table of a material set by retrograde analysis. Every position is first checked for
+	/// checkmate and stalemate, and for moves that leave the table by capturing or promoting, which are
+	/// looked up in the tables already in `tablebase`. Positions are then resolved in order of their
+	/// distance to mate: the positions before a loss in `n` plies are wins in `n + 1`, and a position
+	/// all of whose moves lead to wins for the opponent is a loss once the last of them is resolved.
+	/// Whatever is left unresolved is a draw.
+	fn generate(material: &Material, tablebase: &Tablebase) -> Self {
+		let slots = material.slots();
+		let size = Self::size(slots.len());
+		let mut values = vec![ILLEGAL; size];
+		// The number of moves of each position that stay in the table and are not yet known to win for the
+		// opponent
+		let mut remaining = vec![0u8; size];
+		// The longest loss through the moves of each position that leave the table
+		let mut exit_loss = vec![0u8; size];
+		let mut buckets: Vec<Vec<u32>> = Vec::new();
+
+		for index in 0..size {
+			let (board, turn, _) = match Self::position(&slots, index) {
+				Some(position) => position,
+				None => continue,
+			};
+			values[index] = UNKNOWN;
+			let mut moves = 0;
+			let mut staying = 0u8;
+			let mut can_lose = true;
+			let mut exit_win = None;
+			for mv in board.pseudo_legal_moves(turn, PawnRules::STANDARD) {
+				let mut child = board;
+				let captured = child.make_move(mv);
+				if child.is_king_attacked(turn) {
+					continue;
+				}
+				moves += 1;
+				if captured.is_none() && mv.promotion.is_none() {
+					staying += 1;
+					continue;
+				}
+				match tablebase
+					.probe_board(&child, turn.opposite())
+					.expect("the tables a table depends on are generated before it")
+				{
+					Dtm::Loss(plies) => {
+						exit_win = Some(exit_win.map_or(plies + 1, |win: u32| win.min(plies + 1)));
+						can_lose = false;
+					}
+					Dtm::Win(plies) => exit_loss[index] = exit_loss[index].max(plies as u8 + 1),
+					Dtm::Draw => can_lose = false,
+				}
+			}
+
+			if moves == 0 {
+				if board.is_king_attacked(turn) {
+					push(&mut buckets, 0, index);
+				} else {
+					values[index] = DRAW;
+				}
+				continue;
+			}
+			if let Some(plies) = exit_win {
+				push(&mut buckets, plies as usize, index);
+			}
+			remaining[index] = if can_lose { staying } else { CANNOT_LOSE };
+			if can_lose && staying == 0 {
+				push(&mut buckets, usize::from(exit_loss[index]), index);
+			}
+		}
+
+		let mut plies = 0;
+		while plies < buckets.len() {
+			assert!(
+				plies + 1 < usize::from(UNKNOWN),
+				"{} has mates too long to store",
+				material
+			);
+			for index in std::mem::take(&mut buckets[plies]) {
+				let index = index as usize;
+				if values[index] != UNKNOWN {
+					continue;
+				}
+				values[index] = plies as u8 + 1;
+				let (board, turn, squares) = Self::position(&slots, index).unwrap();
+
+				// Undo each move the other side could have made to reach this position. Captures and
+				// promotions come from other tables, so only moves to empty squares are undone.
+				let occupied = board.occupied();
+				for (slot, (&piece, &square)) in slots.iter().zip(&squares).enumerate() {
+					if piece.color == turn {
+						continue;
+					}
+					let end = from_table_square(square);
+					let starts = if piece.piece == Piece::Pawn {
+						let back = -piece.color.pawn_direction();
+						let mut starts = Bitboard::EMPTY;
+						let size = BoardSize::STANDARD;
+						if let Some(single) = size.offset(end, 0, back).filter(|&start| !occupied.contains(start)) {
+							starts |= Bitboard::from_index(single);
+							let double = size.offset(single, 0, back).filter(|&start| {
+								start.row == size.row_from(piece.color, 1) && !occupied.contains(start)
+							});
+							if let Some(double) = double {
+								starts |= Bitboard::from_index(double);
+							}
+						}
+						starts
+					} else {
+						board.attacks(end, piece) & !occupied
+					};
+
+					let weight = 1 << (6 * (slots.len() - 1 - slot));
+					for start in starts.iter() {
+						let previous =
+							(index / 2 - square * weight + table_square(start) * weight) * 2 + (1 - index % 2);
+						if values[previous] != UNKNOWN {
+							continue;
+						}
+						if plies % 2 == 0 {
+							// The position before a loss is a win
+							push(&mut buckets, plies + 1, previous);
+						} else if remaining[previous] != CANNOT_LOSE {
+							remaining[previous] -= 1;
+							if remaining[previous] == 0 {
+								let loss = (plies + 1).max(usize::from(exit_loss[previous]));
+								push(&mut buckets, loss, previous);
+							}
+						}
+					}
+				}
+			}
+			plies += 1;
+		}
+
+		for value in values.iter_mut().filter(|value| **value == UNKNOWN) {
+			*value = DRAW;
+		}
+		Self {
+			material: material.clone(),
+			values,
+		}
+	}
+
+	/// Write the table as it is stored in a table file
+	pub fn to_bytes(&self) -> Vec<u8> {
+		MAGIC.iter().chain(&self.values).copied().collect()
+	}
+
+	/// Read the table of a material set from the contents of its table file. Returns `None` if the
+	/// contents are not a table of that material set.
+	pub fn from_bytes(material: &Material, bytes: &[u8]) -> Option<Self> {
+		let values = bytes.strip_prefix(MAGIC)?;
+		if material.check_supported().is_err() || values.len() != Self::size(material.piece_count()) {
+			return None;
+		}
+		Some(Self {
+			material: material.clone(),
+			values: values.to_vec(),
+		})
+	}
+}
+
+/// Add a position to be resolved at the given number of plies
+fn push(buckets: &mut Vec<Vec<u32>>, plies: usize, index: usize) {
+	if buckets.len() <= plies {
+		buckets.resize_with(plies + 1, Vec::new);
+	}
+	buckets[plies].push(index as u32);
+}
+
+/// A set of tables, which can be probed for any position whose material set has a table. Tables only
+/// cover positions without castling rights, and ignore the fifty move rule.
+#[derive(Default)]
+pub struct Tablebase {
+	tables: HashMap<Material, Table>,
+}
+
+impl Tablebase {
+	/// Create a tablebase without any tables
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Load every table file in a directory
+	pub fn open(dir: impl AsRef<Path>) -> Result<Self, TablebaseError> {
+		let mut tablebase = Self::new();
+		for entry in fs::read_dir(dir)? {
+			let path = entry?.path();
+			if path.extension().and_then(|extension| extension.to_str()) != Some(EXTENSION) {
+				continue;
+			}
+			let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+			let table = path
+				.file_stem()
+				.and_then(|stem| Material::parse(&stem.to_string_lossy()))
+				.and_then(|material| Table::from_bytes(&material, &fs::read(&path).ok()?))
+				.ok_or(TablebaseError::InvalidTable(name))?;
+			tablebase.tables.insert(table.material.clone(), table);
+		}
+		Ok(tablebase)
+	}
+
+	/// Write every table to a file in a directory, named after its material set
+	pub fn save(&self, dir: impl AsRef<Path>) -> Result<(), TablebaseError> {
+		fs::create_dir_all(&dir)?;
+		for (material, table) in &self.tables {
+			fs::write(
+				dir.as_ref().join(format!("{}.{}", material, EXTENSION)),
+				table.to_bytes(),
+			)?;
+		}
+		Ok(())
+	}
+
+	/// Get every table in the tablebase
+	pub fn tables(&self) -> impl Iterator<Item = &Table> {
+		self.tables.values()
+	}
+
+	/// Check whether the tablebase has a table for a material set
+	pub fn has_table(&self, material: &Material) -> bool {
+		self.tables.contains_key(material)
+	}
+
+	/// Generate the table of a material set, first generating any tables it leads to by captures and
+	/// promotions that the tablebase does not have yet
+	pub fn generate(&mut self, material: &Material) -> Result<(), TablebaseError> {
+		material.check_supported()?;
+		if material.is_dead() || self.has_table(material) {
+			return Ok(());
+		}
+		for successor in material.successors() {
+			self.generate(&successor)?;
+		}
+		let table = Table::generate(material, self);
+		self.tables.insert(material.clone(), table);
+		Ok(())
+	}
+
+	/// Look up the distance to mate of a position. Returns `None` if the tablebase has no table for its
+	/// material, or the position has castling rights, pieces in hand or a board of another size, which tables
+	/// do not cover.
+	pub fn probe(&self, game_state: &GameState) -> Option<Dtm> {
+		let castling = game_state.castling;
+		if castling.white_kingside || castling.white_queenside || castling.black_kingside || castling.black_queenside {
+			return None;
+		}
+		if game_state.pockets != Pockets::default() || game_state.board.size() != BoardSize::STANDARD {
+			return None;
+		}
+		self.probe_board(&BitGameBoard::from(&game_state.board), game_state.turn)
+	}
+
+	fn probe_board(&self, board: &BitGameBoard, turn: Color) -> Option<Dtm> {
+		if board.occupied().count() as usize > MAX_PIECES {
+			return None;
+		}
+		let (material, strong) = Material::of(board)?;
+		if material.is_dead() {
+			return Some(Dtm::Draw);
+		}
+		self.tables.get(&material)?.get(board, turn, strong)
+	}
+
+	/// Find the move that mates the quickest in a won position, holds a drawn position or delays mate the
+	/// longest in a lost position. Returns `None` if the position can not be probed or has no legal moves.
+	pub fn best_move(&self, game_state: &GameState) -> Option<Move> {
+		self.probe(game_state)?;
+		let mut best = None;
+		for mv in game_state.legal_moves() {
+			let mut child = game_state.clone();
+			child.apply_move_unchecked(mv);
+			let score = -self.probe(&child)?.score();
+			if best.is_none_or(|(_, best)| score > best) {
+				best = Some((mv, score));
+			}
+		}
+		best.map(|(mv, _)| mv)
+	}
+}
+
+#[test]
+fn material_test() {
+	let material = Material::parse("KBNK").unwrap();
+	assert_eq!(material.to_string(), "KBNK");
+	assert_eq!(Material::parse("KKNB"), Some(material.clone()));
+	assert_eq!(Material::parse("KRKQ").unwrap().to_string(), "KQKR");
+	assert_eq!(Material::parse("KQ"), None);
+	assert!(Material::parse("KNK").unwrap().is_dead());
+	assert!(!Material::parse("KPK").unwrap().is_dead());
+
+	let game_state = GameState::from_fen("8/8/4k3/8/8/2n5/1b6/4K3 w - - 0 1").unwrap();
+	let board = BitGameBoard::from(&game_state.board);
+	assert_eq!(Material::of(&board), Some((material, Color::Black)));
+	assert!(matches!(
+		Tablebase::new().generate(&Material::parse("KPKP").unwrap()),
+		Err(TablebaseError::Unsupported(_))
+	));
+}
+
+/// The number of moves of the longest mate in a table
+#[cfg(test)]
+fn longest_mate(tablebase: &Tablebase, name: &str) -> u8 {
+	let table = &tablebase.tables[&Material::parse(name).unwrap()];
+	table.values.iter().filter(|&&value| value != ILLEGAL).max().unwrap() / 2
+}
+
+#[test]
+fn tablebase_test() {
+	let mut tablebase = Tablebase::new();
+	tablebase.generate(&Material::parse("KQK").unwrap()).unwrap();
+	assert_eq!(longest_mate(&tablebase, "KQK"), 10);
+
+	let probe = |fen: &str| tablebase.probe(&GameState::from_fen(fen).unwrap());
+	assert_eq!(probe("7k/8/5KQ1/8/8/8/8/8 b - - 0 1"), Some(Dtm::Draw));
+	assert_eq!(probe("7k/6Q1/6K1/8/8/8/8/8 b - - 0 1"), Some(Dtm::Loss(0)));
+	assert_eq!(probe("7k/8/6K1/8/8/8/8/5Q2 w - - 0 1"), Some(Dtm::Win(1)));
+	// Capturing the queen leaves a dead draw
+	assert_eq!(probe("8/8/8/8/8/8/1Q6/2k4K b - - 0 1"), Some(Dtm::Draw));
+	// Colors are swapped for black as the stronger side
+	assert_eq!(probe("8/8/8/8/8/1k6/1q6/K7 w - - 0 1"), Some(Dtm::Loss(0)));
+	assert_eq!(probe("8/8/8/8/8/8/8/KRk5 b - - 0 1"), None);
+	assert_eq!(probe("4k3/8/8/8/8/8/8/4K2Q w K - 0 1"), None);
+
+	let game_state = GameState::from_fen("7k/8/6K1/8/8/8/8/5Q2 w - - 0 1").unwrap();
+	let mv = tablebase.best_move(&game_state).unwrap();
+	let mut mated = game_state.clone();
+	mated.apply_move(mv).unwrap();
+	assert!(mated.is_check() && !mated.has_legal_moves());
+
+	// Saving and loading gives back the same tables
+	let dir = std::env::temp_dir().join(format!("mach_tablebase_test_{}", std::process::id()));
+	tablebase.save(&dir).unwrap();
+	let loaded = Tablebase::open(&dir).unwrap();
+	assert_eq!(loaded.tables().count(), 1);
+	assert_eq!(loaded.probe(&game_state), Some(Dtm::Win(1)));
+	fs::remove_dir_all(&dir).unwrap();
+
+	// The engine plays as well as the tables from the first depth
+	let game_state = GameState::from_fen("8/8/8/3k4/8/8/8/Q3K3 w - - 0 1").unwrap();
+	let dtm = tablebase.probe(&game_state).unwrap();
+	let mut engine = crate::engine::Engine::new();
+	engine.set_tablebase(Some(std::sync::Arc::new(tablebase)));
+	let result = engine.search(&game_state, crate::engine::SearchLimits::depth(1));
+	assert_eq!(result.score, dtm.score());
+}
+
+#[test]
+#[ignore]
+fn tablebase_deep_test() {
+	let mut tablebase = Tablebase::new();
+	tablebase.generate(&Material::parse("KPK").unwrap()).unwrap();
+	tablebase.generate(&Material::parse("KBNK").unwrap()).unwrap();
+	// Promoting leads into the queen and rook tables
+	assert_eq!(longest_mate(&tablebase, "KQK"), 10);
+	assert_eq!(longest_mate(&tablebase, "KRK"), 16);
+	assert_eq!(longest_mate(&tablebase, "KBNK"), 33);
+
+	let probe = |fen: &str| tablebase.probe(&GameState::from_fen(fen).unwrap());
+	// A pawn wins when the defending king is behind it, and draws when the defending king holds the
+	// opposition in front of it
+	assert!(matches!(probe("8/8/8/8/4K3/4P3/8/4k3 w - - 0 1"), Some(Dtm::Win(_))));
+	assert_eq!(probe("8/8/4k3/8/4P3/4K3/8/8 w - - 0 1"), Some(Dtm::Draw));
+	// A rook pawn draws once the defending king reaches the corner
+	assert_eq!(probe("7k/8/8/8/7P/8/8/6K1 w - - 0 1"), Some(Dtm::Draw));
+}
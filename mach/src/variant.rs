@@ -0,0 +1,957 @@
+use serde::{Deserialize, Serialize};
+
+use crate::game::*;
+
+/// The FEN of the starting position of Horde
+pub const HORDE_FEN: &str = "rnbqkbnr/pppppppp/8/1PP2PP1/PPPPPPPP/PPPPPPPP/PPPPPPPP/PPPPPPPP w kq - 0 1";
+/// The FEN of the starting position of Racing Kings
+pub const RACING_KINGS_FEN: &str = "8/8/8/8/8/8/krbnNBRK/qrbnNBRQ w - - 0 1";
+/// The FEN of the starting position of Capablanca Chess
+pub const CAPABLANCA_FEN: &str = "rnabqkbcnr/pppppppppp/10/10/10/10/PPPPPPPPPP/RNABQKBCNR w KQkq - 0 1";
+/// The FEN of the starting position of Grand Chess
+pub const GRAND_FEN: &str = "r8r/1nbqkcabn1/pppppppppp/10/10/10/10/PPPPPPPPPP/1NBQKCABN1/R8R w - - 0 1";
+/// The FEN of the starting position of Gardner's Minichess
+pub const MINICHESS_FEN: &str = "rnbqk/ppppp/5/PPPPP/RNBQK w - - 0 1";
+
+/// The rules of a chess variant. Every method has a default that follows the standard rules, so a variant
+/// only overrides what it changes. Variants played on a board of another size or with other pieces set
+/// them up in their starting position, along with how their pawns move.
+pub trait Variant: Sync {
+	/// Which variant these are the rules of
+	fn kind(&self) -> VariantKind;
+
+	/// The position games of this variant start from, or `None` if they start from the standard or a
+	/// Chess960 starting position
+	fn start_position(&self) -> Option<GameState> {
+		None
+	}
+
+	/// Generate every legal move for the side whose turn it is
+	fn legal_moves(&self, game_state: &GameState) -> Vec<Move> {
+		game_state.legal_moves()
+	}
+
+	/// Check whether the given move is legal for the side whose turn it is, giving the reason if it is
+	/// not
+	fn validate_move(&self, game_state: &GameState, mv: Move) -> Result<(), MoveError> {
+		let size = game_state.board.size();
+		if !size.contains(mv.start) || !size.contains(mv.end) {
+			return Err(MoveError::OffBoard);
+		}
+		if !mv.is_drop() {
+			let piece = game_state
+				.board
+				.get_board_index(mv.start)
+				.ok_or(MoveError::EmptySquare)?;
+			if piece.color != game_state.turn {
+				return Err(MoveError::WrongColor);
+			}
+		}
+		if self.legal_moves(game_state).contains(&mv) {
+			Ok(())
+		} else {
+			Err(MoveError::IllegalMove)
+		}
+	}
+
+	/// Make a move for the side whose turn it is without checking that it is legal
+	fn apply_move(&self, game_state: &mut GameState, mv: Move) -> MoveOutcome {
+		game_state.apply_move_unchecked(mv)
+	}
+
+	/// Take back the last move that was applied to the game state, given the outcome that applying it
+	/// returned. Returns false without changing the game state if the move can not be taken back from
+	/// its outcome alone, in which case the previous position has to be found by replaying the game.
+	fn undo_move(&self, game_state: &mut GameState, mv: Move, outcome: MoveOutcome) -> bool {
+		game_state.undo_move(mv, outcome);
+		true
+	}
+
+	/// Check whether the game has ended in the given position, and if so how
+	fn game_over(&self, game_state: &GameState, history: &PositionHistory) -> Option<GameOver> {
+		history.game_over(game_state)
+	}
+}
+
+/// Each of the variants that mach knows the rules of
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum VariantKind {
+	#[default]
+	Standard,
+	KingOfTheHill,
+	ThreeCheck,
+	Antichess,
+	Atomic,
+	Horde,
+	RacingKings,
+	Crazyhouse,
+	Bughouse,
+	Capablanca,
+	Grand,
+	Minichess,
+}
+
+impl VariantKind {
+	/// Every variant, starting with standard chess
+	pub const ALL: [VariantKind; 12] = [
+		VariantKind::Standard,
+		VariantKind::KingOfTheHill,
+		VariantKind::ThreeCheck,
+		VariantKind::Antichess,
+		VariantKind::Atomic,
+		VariantKind::Horde,
+		VariantKind::RacingKings,
+		VariantKind::Crazyhouse,
+		VariantKind::Bughouse,
+		VariantKind::Capablanca,
+		VariantKind::Grand,
+		VariantKind::Minichess,
+	];
+
+	/// The rules of this variant
+	pub fn rules(self) -> &'static dyn Variant {
+		match self {
+			VariantKind::Standard => &Standard,
+			VariantKind::KingOfTheHill => &KingOfTheHill,
+			VariantKind::ThreeCheck => &ThreeCheck,
+			VariantKind::Antichess => &Antichess,
+			VariantKind::Atomic => &Atomic,
+			VariantKind::Horde => &Horde,
+			VariantKind::RacingKings => &RacingKings,
+			VariantKind::Crazyhouse => &Crazyhouse,
+			VariantKind::Bughouse => &Bughouse,
+			VariantKind::Capablanca => &Capablanca,
+			VariantKind::Grand => &Grand,
+			VariantKind::Minichess => &Minichess,
+		}
+	}
+
+	/// The name of this variant, as used in the `Variant` tag of PGN
+	pub fn name(self) -> &'static str {
+		match self {
+			VariantKind::Standard => "Standard",
+			VariantKind::KingOfTheHill => "King of the Hill",
+			VariantKind::ThreeCheck => "Three-check",
+			VariantKind::Antichess => "Antichess",
+			VariantKind::Atomic => "Atomic",
+			VariantKind::Horde => "Horde",
+			VariantKind::RacingKings => "Racing Kings",
+			VariantKind::Crazyhouse => "Crazyhouse",
+			VariantKind::Bughouse => "Bughouse",
+			VariantKind::Capablanca => "Capablanca",
+			VariantKind::Grand => "Grand Chess",
+			VariantKind::Minichess => "Minichess",
+		}
+	}
+}
+
+/// End the game if the side whose turn it is has no legal moves, with checkmate if it is in check and
+/// stalemate otherwise, or draw it by the fivefold repetition or seventy-five-move rules. Used by variants
+/// that change how pieces move and so can not use the standard checks for insufficient material.
+fn ending(game_state: &GameState, history: &PositionHistory, has_moves: bool, in_check: bool) -> Option<GameOver> {
+	if has_moves {
+		history.automatic_draw(game_state)
+	} else if in_check {
+		Some(GameOver {
+			result: GameResult::win_for(game_state.turn.opposite()),
+			termination: Termination::Checkmate,
+		})
+	} else {
+		Some(GameOver {
+			result: GameResult::Draw,
+			termination: Termination::Stalemate,
+		})
+	}
+}
+
+/// Check whether a move captures a piece, including capturing en passant
+fn is_capture(game_state: &GameState, mv: Move) -> bool {
+	match (
+		game_state.board.get_board_index(mv.start),
+		game_state.board.get_board_index(mv.end),
+	) {
+		(Some(piece), Some(target)) => piece.color != target.color,
+		(Some(piece), None) => piece.piece == Piece::Pawn && mv.start.column != mv.end.column,
+		_ => false,
+	}
+}
+
+/// Standard chess
+pub struct Standard;
+
+impl Variant for Standard {
+	fn kind(&self) -> VariantKind {
+		VariantKind::Standard
+	}
+}
+
+/// The squares in the center of the board that win the game in King of the Hill
+const CENTER: [(Column, Row); 4] = [
+	(Column::D, Row::R4),
+	(Column::E, Row::R4),
+	(Column::D, Row::R5),
+	(Column::E, Row::R5),
+];
+
+/// King of the Hill, where a player also wins by moving their king to one of the four center squares
+pub struct KingOfTheHill;
+
+impl Variant for KingOfTheHill {
+	fn kind(&self) -> VariantKind {
+		VariantKind::KingOfTheHill
+	}
+
+	fn game_over(&self, game_state: &GameState, history: &PositionHistory) -> Option<GameOver> {
+		let mover = game_state.turn.opposite();
+		if let Some(king) = game_state.board.find_king(mover) {
+			if CENTER.contains(&(king.column, king.row)) {
+				return Some(GameOver {
+					result: GameResult::win_for(mover),
+					termination: Termination::KingInCenter,
+				});
+			}
+		}
+		ending(game_state, history, game_state.has_legal_moves(), game_state.is_check())
+	}
+}
+
+/// Three-check, where a player also wins by checking the opposing king for the third time
+pub struct ThreeCheck;
+
+impl Variant for ThreeCheck {
+	fn kind(&self) -> VariantKind {
+		VariantKind::ThreeCheck
+	}
+
+	fn apply_move(&self, game_state: &mut GameState, mv: Move) -> MoveOutcome {
+		let outcome = game_state.apply_move_unchecked(mv);
+		if game_state.is_check() {
+			game_state.add_check(game_state.turn.opposite());
+		}
+		outcome
+	}
+
+	fn undo_move(&self, game_state: &mut GameState, mv: Move, outcome: MoveOutcome) -> bool {
+		if game_state.is_check() {
+			game_state.remove_check(game_state.turn.opposite());
+		}
+		game_state.undo_move(mv, outcome);
+		true
+	}
+
+	fn game_over(&self, game_state: &GameState, history: &PositionHistory) -> Option<GameOver> {
+		let mover = game_state.turn.opposite();
+		if game_state.checks.get(mover) >= 3 {
+			return Some(GameOver {
+				result: GameResult::win_for(mover),
+				termination: Termination::ThirdCheck,
+			});
+		}
+		// Any material can still give check, so the game is never drawn for lack of it
+		ending(game_state, history, game_state.has_legal_moves(), game_state.is_check())
+	}
+}
+
+/// Antichess, where capturing is compulsory, the king is an ordinary piece that pawns may also promote
+/// to, and a player wins by losing all of their pieces or having no legal moves
+pub struct Antichess;
+
+impl Variant for Antichess {
+	fn kind(&self) -> VariantKind {
+		VariantKind::Antichess
+	}
+
+	fn start_position(&self) -> Option<GameState> {
+		let mut game_state = GameState::standard();
+		game_state.castling = CastlingRights::none();
+		game_state.refresh_zobrist_key();
+		Some(game_state)
+	}
+
+	fn legal_moves(&self, game_state: &GameState) -> Vec<Move> {
+		let mut moves = game_state.pseudo_legal_moves();
+		let king_promotions: Vec<Move> = moves
+			.iter()
+			.filter(|mv| mv.promotion == Some(Piece::Queen))
+			.map(|&mv| Move::with_promotion(mv.start, mv.end, Piece::King))
+			.collect();
+		moves.extend(king_promotions);
+		if moves.iter().any(|&mv| is_capture(game_state, mv)) {
+			moves.retain(|&mv| is_capture(game_state, mv));
+		}
+		moves
+	}
+
+	fn game_over(&self, game_state: &GameState, history: &PositionHistory) -> Option<GameOver> {
+		let has_pieces = game_state
+			.board
+			.pieces()
+			.any(|(_, piece)| piece.color == game_state.turn);
+		if !has_pieces || self.legal_moves(game_state).is_empty() {
+			return Some(GameOver {
+				result: GameResult::win_for(game_state.turn),
+				termination: if has_pieces {
+					Termination::Stalemate
+				} else {
+					Termination::NoPiecesLeft
+				},
+			});
+		}
+		history.automatic_draw(game_state)
+	}
+}
+
+/// Atomic, where every capture causes an explosion that removes the capturing piece and every piece
+/// other than a pawn next to the square of the capture. Kings can not capture, a player may not explode
+/// their own king, and a player wins by exploding the opposing king. Kings next to each other can not
+/// check each other.
+pub struct Atomic;
+
+impl Atomic {
+	/// Check whether the king of the given color is in check, in the atomic sense
+	fn is_check(game_state: &GameState, color: Color) -> bool {
+		let board = &game_state.board;
+		match (board.find_king(color), board.find_king(color.opposite())) {
+			(Some(king), Some(opponent)) => {
+				let adjacent = (u32::from(king.column) as i32 - u32::from(opponent.column) as i32).abs() <= 1
+					&& (u32::from(king.row) as i32 - u32::from(opponent.row) as i32).abs() <= 1;
+				!adjacent && board.is_attacked(king, color.opposite())
+			}
+			_ => false,
+		}
+	}
+
+	/// Remove the pieces caught in an explosion at the given square, and the castling rights of any king
+	/// or rook removed
+	fn explode(game_state: &mut GameState, center: BoardIndex) {
+		let mut exploded = vec![center];
+		exploded.extend(
+			KING_OFFSETS
+				.iter()
+				.filter_map(|&(columns, rows)| game_state.board.size().offset(center, columns, rows))
+				.filter(|&index| {
+					game_state
+						.board
+						.get_board_index(index)
+						.is_some_and(|piece| piece.piece != Piece::Pawn)
+				}),
+		);
+		for index in exploded {
+			let piece = match game_state.board.get_board_index_mut(index).take() {
+				Some(piece) => piece,
+				None => continue,
+			};
+			let color = piece.color;
+			if piece.piece == Piece::King {
+				*game_state.castling.kingside_mut(color) = false;
+				*game_state.castling.queenside_mut(color) = false;
+			} else if index.row == game_state.board.size().home_row(color) {
+				if index.column == game_state.castling_columns.kingside(color) {
+					*game_state.castling.kingside_mut(color) = false;
+				}
+				if index.column == game_state.castling_columns.queenside(color) {
+					*game_state.castling.queenside_mut(color) = false;
+				}
+			}
+		}
+	}
+}
+
+impl Variant for Atomic {
+	fn kind(&self) -> VariantKind {
+		VariantKind::Atomic
+	}
+
+	fn legal_moves(&self, game_state: &GameState) -> Vec<Move> {
+		let color = game_state.turn;
+		let mut moves = game_state.pseudo_legal_moves();
+		moves.retain(|&mv| {
+			let piece = game_state.board.get_board_index(mv.start).unwrap();
+			if piece.piece == Piece::King && is_capture(game_state, mv) {
+				return false;
+			}
+			let mut after = game_state.clone();
+			self.apply_move(&mut after, mv);
+			after.board.find_king(color).is_some()
+				&& (after.board.find_king(color.opposite()).is_none() || !Atomic::is_check(&after, color))
+		});
+		moves
+	}
+
+	fn apply_move(&self, game_state: &mut GameState, mv: Move) -> MoveOutcome {
+		let outcome = game_state.apply_move_unchecked(mv);
+		if outcome.captured.is_some() {
+			Atomic::explode(game_state, mv.end);
+			game_state.refresh_zobrist_key();
+		}
+		outcome
+	}
+
+	fn undo_move(&self, game_state: &mut GameState, mv: Move, outcome: MoveOutcome) -> bool {
+		// The pieces removed by an explosion are not part of the outcome
+		if outcome.captured.is_some() {
+			return false;
+		}
+		game_state.undo_move(mv, outcome);
+		true
+	}
+
+	fn game_over(&self, game_state: &GameState, history: &PositionHistory) -> Option<GameOver> {
+		if game_state.board.find_king(game_state.turn).is_none() {
+			return Some(GameOver {
+				result: GameResult::win_for(game_state.turn.opposite()),
+				termination: Termination::KingExploded,
+			});
+		}
+		ending(
+			game_state,
+			history,
+			!self.legal_moves(game_state).is_empty(),
+			Atomic::is_check(game_state, game_state.turn),
+		)
+	}
+}
+
+/// Horde, where white has thirty-six pawns and no king and wins by checkmating black, and black wins by
+/// capturing every white piece. White pawns on the first row may move two squares, but can not be
+/// captured en passant after doing so.
+pub struct Horde;
+
+impl Variant for Horde {
+	fn kind(&self) -> VariantKind {
+		VariantKind::Horde
+	}
+
+	fn start_position(&self) -> Option<GameState> {
+		Some(GameState::from_fen(HORDE_FEN).unwrap())
+	}
+
+	fn legal_moves(&self, game_state: &GameState) -> Vec<Move> {
+		let mut moves = game_state.legal_moves();
+		if game_state.turn == Color::White {
+			let board = &game_state.board;
+			for (start, piece) in board.pieces() {
+				if piece != GamePiece::new(Piece::Pawn, Color::White) || start.row != Row::R1 {
+					continue;
+				}
+				let (passed, end) = (start.offset(0, 1).unwrap(), start.offset(0, 2).unwrap());
+				if board.get_board_index(passed).is_none() && board.get_board_index(end).is_none() {
+					moves.push(Move::new(start, end));
+				}
+			}
+		}
+		moves
+	}
+
+	fn apply_move(&self, game_state: &mut GameState, mv: Move) -> MoveOutcome {
+		let outcome = game_state.apply_move_unchecked(mv);
+		if mv.start.row == Row::R1 && game_state.en_passant.is_some() {
+			game_state.en_passant = None;
+			game_state.refresh_zobrist_key();
+		}
+		outcome
+	}
+
+	fn game_over(&self, game_state: &GameState, history: &PositionHistory) -> Option<GameOver> {
+		if !game_state.board.pieces().any(|(_, piece)| piece.color == Color::White) {
+			return Some(GameOver {
+				result: GameResult::BlackWins,
+				termination: Termination::NoPiecesLeft,
+			});
+		}
+		ending(
+			game_state,
+			history,
+			!self.legal_moves(game_state).is_empty(),
+			game_state.is_check(),
+		)
+	}
+}
+
+/// Racing Kings, where giving check is not allowed and a player wins by moving their king to the last
+/// row. If white gets there first, black may still draw by getting there with the very next move.
+pub struct RacingKings;
+
+impl RacingKings {
+	fn reached_last_row(game_state: &GameState, color: Color) -> bool {
+		game_state
+			.board
+			.find_king(color)
+			.is_some_and(|king| king.row == Row::R8)
+	}
+}
+
+impl Variant for RacingKings {
+	fn kind(&self) -> VariantKind {
+		VariantKind::RacingKings
+	}
+
+	fn start_position(&self) -> Option<GameState> {
+		Some(GameState::from_fen(RACING_KINGS_FEN).unwrap())
+	}
+
+	fn legal_moves(&self, game_state: &GameState) -> Vec<Move> {
+		let mut moves = game_state.legal_moves();
+		moves.retain(|&mv| {
+			let mut after = game_state.clone();
+			after.apply_move_unchecked(mv);
+			!after.is_check()
+		});
+		moves
+	}
+
+	fn game_over(&self, game_state: &GameState, history: &PositionHistory) -> Option<GameOver> {
+		let moves = self.legal_moves(game_state);
+		let result = match (
+			RacingKings::reached_last_row(game_state, Color::White),
+			RacingKings::reached_last_row(game_state, Color::Black),
+		) {
+			(true, true) => GameResult::Draw,
+			(false, true) => GameResult::BlackWins,
+			(true, false) => {
+				let can_follow = game_state.turn == Color::Black
+					&& moves.iter().any(|mv| {
+						mv.end.row == Row::R8
+							&& game_state.board.get_board_index(mv.start).map(|piece| piece.piece) == Some(Piece::King)
+					});
+				if can_follow {
+					return history.automatic_draw(game_state);
+				}
+				GameResult::WhiteWins
+			}
+			(false, false) => return ending(game_state, history, !moves.is_empty(), false),
+		};
+		Some(GameOver {
+			result,
+			termination: Termination::KingReachedLastRow,
+		})
+	}
+}
+
+/// Crazyhouse, where a captured piece goes into the pocket of the side that captured it, which may drop it
+/// back onto the board as one of its own pieces instead of moving. A piece promoted from a pawn goes back
+/// to being a pawn when it is captured.
+pub struct Crazyhouse;
+
+impl Variant for Crazyhouse {
+	fn kind(&self) -> VariantKind {
+		VariantKind::Crazyhouse
+	}
+
+	fn apply_move(&self, game_state: &mut GameState, mv: Move) -> MoveOutcome {
+		let color = game_state.turn;
+		let captured = game_state.pocket_capture(mv);
+		let outcome = apply_tracking_promotions(game_state, mv);
+		if let Some(piece) = captured {
+			game_state.add_to_pocket(color, piece);
+		}
+		outcome
+	}
+
+	fn undo_move(&self, game_state: &mut GameState, mv: Move, outcome: MoveOutcome) -> bool {
+		undo_tracking_promotions(game_state, mv, outcome)
+	}
+
+	fn game_over(&self, game_state: &GameState, history: &PositionHistory) -> Option<GameOver> {
+		ending(game_state, history, game_state.has_legal_moves(), game_state.is_check())
+	}
+}
+
+/// Bughouse, played by two teams of two on two boards by the rules of Crazyhouse, except that a captured
+/// piece goes to the partner of the player who captured it instead of into their own pocket. The partner
+/// plays the other color on the other board, and receives the piece through `Game::add_to_pocket`, with
+/// `GameState::pocket_capture` telling which piece it is. A checkmate only counts the pieces already in
+/// hand, not those the partner may still pass on.
+pub struct Bughouse;
+
+impl Variant for Bughouse {
+	fn kind(&self) -> VariantKind {
+		VariantKind::Bughouse
+	}
+
+	fn apply_move(&self, game_state: &mut GameState, mv: Move) -> MoveOutcome {
+		apply_tracking_promotions(game_state, mv)
+	}
+
+	fn undo_move(&self, game_state: &mut GameState, mv: Move, outcome: MoveOutcome) -> bool {
+		undo_tracking_promotions(game_state, mv, outcome)
+	}
+
+	fn game_over(&self, game_state: &GameState, history: &PositionHistory) -> Option<GameOver> {
+		ending(game_state, history, game_state.has_legal_moves(), game_state.is_check())
+	}
+}
+
+/// Capablanca Chess, played on a board of ten columns and eight rows with an archbishop, which moves as a
+/// bishop or a knight, and a chancellor, which moves as a rook or a knight, added to each side. Pawns may
+/// promote to either of them as well, and the king moves three squares when castling.
+pub struct Capablanca;
+
+impl Variant for Capablanca {
+	fn kind(&self) -> VariantKind {
+		VariantKind::Capablanca
+	}
+
+	fn start_position(&self) -> Option<GameState> {
+		Some(GameState::from_fen(CAPABLANCA_FEN).unwrap())
+	}
+}
+
+/// Grand Chess, played on a board of ten columns and rows with an archbishop and a chancellor added to each
+/// side and no castling. Pawns start on the third row, may promote on any of the last three rows and must
+/// on the last, but only to a kind of piece their side has lost.
+pub struct Grand;
+
+impl Variant for Grand {
+	fn kind(&self) -> VariantKind {
+		VariantKind::Grand
+	}
+
+	fn start_position(&self) -> Option<GameState> {
+		Some(GameState::from_fen(GRAND_FEN).unwrap())
+	}
+}
+
+/// Gardner's Minichess, played on a board of five columns and rows with the pieces of standard chess
+/// without the kingside bishop, knight and rook. Pawns never move two squares and there is no castling.
+pub struct Minichess;
+
+impl Variant for Minichess {
+	fn kind(&self) -> VariantKind {
+		VariantKind::Minichess
+	}
+
+	fn start_position(&self) -> Option<GameState> {
+		Some(GameState::from_fen(MINICHESS_FEN).unwrap())
+	}
+}
+
+/// Make a move in a variant with drops, keeping track of which pieces were promoted from pawns so that they
+/// go into a pocket as pawns when captured
+fn apply_tracking_promotions(game_state: &mut GameState, mv: Move) -> MoveOutcome {
+	let moved_promoted = !mv.is_drop() && game_state.promoted.contains(mv.start);
+	let outcome = game_state.apply_move_unchecked(mv);
+	game_state.promoted &= !(Bitboard::from_index(mv.start) | Bitboard::from_index(mv.end));
+	if moved_promoted || mv.promotion.is_some() {
+		game_state.promoted |= Bitboard::from_index(mv.end);
+	}
+	outcome
+}
+
+/// Take back a move made with `apply_tracking_promotions`, unless it captured a piece or moved a promoted
+/// one, since whether those pieces had been promoted before the move is not part of the outcome
+fn undo_tracking_promotions(game_state: &mut GameState, mv: Move, outcome: MoveOutcome) -> bool {
+	if outcome.captured.is_some() || mv.promotion.is_some() || game_state.promoted.contains(mv.end) {
+		return false;
+	}
+	game_state.undo_move(mv, outcome);
+	true
+}
+
+#[test]
+fn king_of_the_hill_test() {
+	let variant = VariantKind::KingOfTheHill.rules();
+	let game_state = GameState::from_fen("4k3/8/8/8/8/4K3/8/8 w - - 0 1").unwrap();
+	let history = PositionHistory::new(&game_state);
+	assert_eq!(variant.game_over(&game_state, &history), None);
+
+	let mut after = game_state;
+	let mv = after.parse_move("Ke4").unwrap();
+	variant.apply_move(&mut after, mv);
+	assert_eq!(
+		variant.game_over(&after, &history),
+		Some(GameOver {
+			result: GameResult::WhiteWins,
+			termination: Termination::KingInCenter,
+		})
+	);
+}
+
+#[test]
+fn three_check_test() {
+	let variant = VariantKind::ThreeCheck.rules();
+	let mut game_state = GameState::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+	let history = PositionHistory::new(&game_state);
+	for (i, &(check, escape)) in [("Ra8", "Ke7"), ("Ra7", "Kf6"), ("Ra6", "")].iter().enumerate() {
+		assert_eq!(variant.game_over(&game_state, &history), None);
+		let mv = game_state.parse_move(check).unwrap();
+		variant.apply_move(&mut game_state, mv);
+		assert_eq!(game_state.checks.white, i as u32 + 1);
+		if !escape.is_empty() {
+			let mv = game_state.parse_move(escape).unwrap();
+			variant.apply_move(&mut game_state, mv);
+		}
+	}
+	assert_eq!(
+		variant.game_over(&game_state, &history),
+		Some(GameOver {
+			result: GameResult::WhiteWins,
+			termination: Termination::ThirdCheck,
+		})
+	);
+	// The number of checks given is part of the key, so the same board with fewer checks is a different
+	// position
+	assert_eq!(game_state.zobrist_key(), game_state.compute_zobrist_key());
+	let mut fewer_checks = game_state.clone();
+	fewer_checks.remove_check(Color::White);
+	assert_ne!(fewer_checks.zobrist_key(), game_state.zobrist_key());
+
+	// Checks can still be given with a lone bishop or knight, so the game is not drawn for lack of material
+	let game_state = GameState::from_fen("4k3/8/8/8/8/8/8/2B1K3 w - - 0 1").unwrap();
+	assert_eq!(variant.game_over(&game_state, &PositionHistory::new(&game_state)), None);
+}
+
+#[test]
+fn antichess_test() {
+	let variant = VariantKind::Antichess.rules();
+	let start = variant.start_position().unwrap();
+	assert_eq!(variant.legal_moves(&start).len(), 20);
+
+	// Capturing is compulsory, even with the king
+	let game_state = GameState::from_fen("8/8/8/3p4/4K3/8/8/8 w - - 0 1").unwrap();
+	assert_eq!(variant.legal_moves(&game_state), vec![Move::from_uci("e4d5").unwrap()]);
+
+	// Pawns may promote to a king
+	let game_state = GameState::from_fen("8/P7/8/8/8/8/8/7n w - - 0 1").unwrap();
+	assert_eq!(variant.legal_moves(&game_state).len(), 5);
+	assert!(variant
+		.legal_moves(&game_state)
+		.contains(&Move::from_uci("a7a8k").unwrap()));
+
+	// A player without pieces wins
+	let game_state = GameState::from_fen("8/8/8/8/8/8/8/7n w - - 0 1").unwrap();
+	assert_eq!(
+		variant.game_over(&game_state, &PositionHistory::new(&game_state)),
+		Some(GameOver {
+			result: GameResult::WhiteWins,
+			termination: Termination::NoPiecesLeft,
+		})
+	);
+}
+
+#[test]
+fn atomic_test() {
+	let variant = VariantKind::Atomic.rules();
+	let mut game_state = GameState::from_fen("4k3/8/4b3/3pn3/8/4N3/8/4K3 w - - 0 1").unwrap();
+	let mv = Move::from_uci("e3d5").unwrap();
+	assert!(variant.validate_move(&game_state, mv).is_ok());
+	variant.apply_move(&mut game_state, mv);
+	// The knight, the captured pawn and the pieces next to it are gone
+	assert_eq!(game_state.to_fen(), "4k3/8/8/8/8/8/8/4K3 b - - 0 1");
+	assert_eq!(game_state.zobrist_key(), game_state.compute_zobrist_key());
+
+	// Exploding the opposing king wins, even while in check
+	let game_state = GameState::from_fen("3qk3/3p4/8/8/8/8/3R4/r3K3 w - - 0 1").unwrap();
+	let mv = Move::from_uci("d2d7").unwrap();
+	assert!(variant.validate_move(&game_state, mv).is_ok());
+	let mut after = game_state.clone();
+	variant.apply_move(&mut after, mv);
+	assert_eq!(
+		variant.game_over(&after, &PositionHistory::new(&after)),
+		Some(GameOver {
+			result: GameResult::WhiteWins,
+			termination: Termination::KingExploded,
+		})
+	);
+
+	// Kings can not capture, and a player may not explode their own king
+	let game_state = GameState::from_fen("4k3/8/8/8/8/8/3p4/3QK3 w - - 0 1").unwrap();
+	assert_eq!(
+		variant.validate_move(&game_state, Move::from_uci("e1d2").unwrap()),
+		Err(MoveError::IllegalMove)
+	);
+	assert_eq!(
+		variant.validate_move(&game_state, Move::from_uci("d1d2").unwrap()),
+		Err(MoveError::IllegalMove)
+	);
+}
+
+#[test]
+fn horde_test() {
+	let variant = VariantKind::Horde.rules();
+	let start = variant.start_position().unwrap();
+	let moves = variant.legal_moves(&start);
+	assert!(moves.contains(&Move::from_uci("a4a5").unwrap()));
+	let mut game_state = GameState::from_fen("4k3/8/8/8/8/8/8/P7 w - - 0 1").unwrap();
+	let mv = Move::from_uci("a1a3").unwrap();
+	assert!(variant.validate_move(&game_state, mv).is_ok());
+	variant.apply_move(&mut game_state, mv);
+	assert_eq!(game_state.en_passant, None);
+
+	let game_state = GameState::from_fen("4k3/8/8/8/8/8/8/8 w - - 0 1").unwrap();
+	assert_eq!(
+		variant.game_over(&game_state, &PositionHistory::new(&game_state)),
+		Some(GameOver {
+			result: GameResult::BlackWins,
+			termination: Termination::NoPiecesLeft,
+		})
+	);
+}
+
+#[test]
+fn racing_kings_test() {
+	let variant = VariantKind::RacingKings.rules();
+	let start = variant.start_position().unwrap();
+	assert_eq!(variant.legal_moves(&start).len(), 21);
+
+	// Giving check is not allowed
+	let game_state = GameState::from_fen("8/8/8/8/8/8/k7/6KR w - - 0 1").unwrap();
+	assert!(variant
+		.validate_move(&game_state, Move::from_uci("h1h2").unwrap())
+		.is_err());
+
+	// Black can still draw by reaching the last row straight after white
+	let game_state = GameState::from_fen("6K1/1k6/8/8/8/8/8/8 b - - 0 1").unwrap();
+	let history = PositionHistory::new(&game_state);
+	assert_eq!(variant.game_over(&game_state, &history), None);
+	let mut after = game_state.clone();
+	variant.apply_move(&mut after, Move::from_uci("b7b8").unwrap());
+	assert_eq!(variant.game_over(&after, &history).unwrap().result, GameResult::Draw);
+	let mut after = game_state;
+	variant.apply_move(&mut after, Move::from_uci("b7b6").unwrap());
+	assert_eq!(
+		variant.game_over(&after, &history).unwrap().result,
+		GameResult::WhiteWins
+	);
+}
+
+#[test]
+fn crazyhouse_test() {
+	let variant = VariantKind::Crazyhouse.rules();
+	let mut game = Game::with_variant(GameState::standard(), VariantKind::Crazyhouse);
+	for &san in &["e4", "d5", "exd5", "Qxd5", "Nc3", "Qa5"] {
+		let mv = game.state().parse_move(san).unwrap();
+		game.make_move(mv).unwrap();
+	}
+	assert_eq!(game.state().pockets.white.pawns, 1);
+	assert_eq!(game.state().pockets.black.pawns, 1);
+	assert_eq!(
+		game.state().to_fen(),
+		"rnb1kbnr/ppp1pppp/8/q7/8/2N5/PPPP1PPP/R1BQKBNR[Pp] w KQkq - 2 4"
+	);
+
+	// Dropping a piece can be taken back without replaying the game
+	let mut game_state = game.state().clone();
+	let drop = game_state.parse_move("P@d7").unwrap();
+	assert_eq!(game_state.move_to_san(drop), "P@d7+");
+	let outcome = variant.apply_move(&mut game_state, drop);
+	assert!(variant.undo_move(&mut game_state, drop, outcome));
+	assert_eq!(&game_state, game.state());
+
+	// A promoted piece goes back to being a pawn when captured
+	let mut game_state = GameState::from_fen("r3k3/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+	variant.apply_move(&mut game_state, Move::from_uci("b7a8q").unwrap());
+	assert_eq!(game_state.to_fen(), "Q~3k3/8/8/8/8/8/8/4K3[R] b - - 0 1");
+	let mut game_state = GameState::from_fen("Q~3k3/8/8/8/8/8/8/4K3[r] b - - 0 1").unwrap();
+	variant.apply_move(&mut game_state, Move::from_uci("e8e7").unwrap());
+	variant.apply_move(&mut game_state, Move::from_uci("a8b8").unwrap());
+	assert!(game_state.promoted.contains(BoardIndex::parse("b8").unwrap()));
+	variant.apply_move(&mut game_state, Move::from_uci("R@c8").unwrap());
+	variant.apply_move(&mut game_state, Move::from_uci("e1d1").unwrap());
+	variant.apply_move(&mut game_state, Move::from_uci("c8b8").unwrap());
+	assert_eq!(game_state.pockets.black.pawns, 1);
+	assert!(game_state.promoted.is_empty());
+}
+
+#[test]
+fn bughouse_test() {
+	let mut game = Game::with_variant(GameState::standard(), VariantKind::Bughouse);
+	for &san in &["e4", "d5"] {
+		let mv = game.state().parse_move(san).unwrap();
+		game.make_move(mv).unwrap();
+	}
+	// The captured pawn is passed on to the partner board instead of into white's pocket
+	let capture = game.state().parse_move("exd5").unwrap();
+	assert_eq!(game.state().pocket_capture(capture), Some(Piece::Pawn));
+	game.make_move(capture).unwrap();
+	assert!(game.state().pockets.is_empty());
+
+	// A piece passed on from the partner board can be dropped, and stays in hand when moves are undone
+	game.add_to_pocket(Color::Black, Piece::Knight);
+	assert_eq!(game.state().zobrist_key(), game.state().compute_zobrist_key());
+	let drop = game.state().parse_move("N@c3").unwrap();
+	game.make_move(drop).unwrap();
+	assert!(game.state().pockets.is_empty());
+	game.undo();
+	game.undo();
+	assert_eq!(game.state().pockets.black.knights, 1);
+	assert_eq!(
+		game.state().to_fen(),
+		"rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR[n] w KQkq - 0 2"
+	);
+}
+
+#[test]
+fn capablanca_test() {
+	let variant = VariantKind::Capablanca.rules();
+	let mut game_state = variant.start_position().unwrap();
+	assert_eq!(game_state.to_fen(), CAPABLANCA_FEN);
+	for uci in "i1j3 a7a6 h1i3 a6a5 h2h3 a5a4 g1h2 b7b6".split_whitespace() {
+		let mv = Move::from_uci(uci).unwrap();
+		assert!(variant.validate_move(&game_state, mv).is_ok(), "{}", uci);
+		variant.apply_move(&mut game_state, mv);
+	}
+	// The king moves three squares towards the rook in the corner when castling kingside
+	let castling = Move::from_uci("f1i1").unwrap();
+	assert_eq!(game_state.parse_move("O-O"), Ok(castling));
+	assert_eq!(game_state.move_to_san(castling), "O-O");
+	variant.apply_move(&mut game_state, castling);
+	assert_eq!(
+		game_state.to_fen(),
+		"rnabqkbcnr/2pppppppp/1p8/10/p9/7PCN/PPPPPPPBPP/RNABQ2RK1 b kq - 1 5"
+	);
+
+	// Pawns may also promote to an archbishop or a chancellor
+	let game_state = GameState::from_fen("4k5/P9/10/10/10/10/10/4K5 w - - 0 1").unwrap();
+	assert_eq!(variant.legal_moves(&game_state).len(), 11);
+	assert_eq!(game_state.parse_move("a8=C"), Ok(Move::from_uci("a7a8c").unwrap()));
+}
+
+#[test]
+fn grand_test() {
+	let variant = VariantKind::Grand.rules();
+	let start = variant.start_position().unwrap();
+	assert_eq!(start.to_fen(), GRAND_FEN);
+	assert!(variant.validate_move(&start, Move::from_uci("e3e5").unwrap()).is_ok());
+
+	// A pawn may only promote to a kind of piece its side has lost, and can not reach the last row
+	// while it has lost none
+	let game_state = GameState::from_fen("10/P4k4/10/10/10/10/10/10/1NBQKCABN1/R8R w - - 0 1").unwrap();
+	let end = BoardIndex::parse("a10").unwrap();
+	assert!(!variant.legal_moves(&game_state).iter().any(|mv| mv.end == end));
+	let game_state = GameState::from_fen("10/P4k4/10/10/10/10/10/10/1NBQKCABN1/9R w - - 0 1").unwrap();
+	assert!(variant
+		.validate_move(&game_state, Move::from_uci("a9a10r").unwrap())
+		.is_ok());
+	assert_eq!(
+		variant.validate_move(&game_state, Move::from_uci("a9a10q").unwrap()),
+		Err(MoveError::IllegalMove)
+	);
+
+	// Promoting is optional on the eighth and ninth rows
+	let game_state = GameState::from_fen("10/5k4/10/P9/10/10/10/10/1NBQKCABN1/9R w - - 0 1").unwrap();
+	assert!(variant
+		.validate_move(&game_state, Move::from_uci("a7a8").unwrap())
+		.is_ok());
+	assert!(variant
+		.validate_move(&game_state, Move::from_uci("a7a8r").unwrap())
+		.is_ok());
+}
+
+#[test]
+fn minichess_test() {
+	let variant = VariantKind::Minichess.rules();
+	let game_state = variant.start_position().unwrap();
+	assert_eq!(game_state.to_fen(), MINICHESS_FEN);
+	assert_eq!(variant.legal_moves(&game_state).len(), 7);
+	// Pawns never move two squares
+	assert_eq!(
+		variant.validate_move(&game_state, Move::from_uci("a2a4").unwrap()),
+		Err(MoveError::IllegalMove)
+	);
+	// Moves that leave the board are rejected rather than panicking
+	assert_eq!(
+		variant.validate_move(&game_state, Move::from_uci("j9j10").unwrap()),
+		Err(MoveError::OffBoard)
+	);
+}
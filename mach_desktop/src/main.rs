@@ -1,8 +1,10 @@
+#![allow(clippy::result_unit_err)]
+
 use std::{io::Write, net::TcpStream};
 
 use tungstenite::protocol::{Message, WebSocket};
 
-use mach::{game::*, proto::*};
+use mach::{engine::*, game::*, proto::*, variant::*};
 
 fn setup_logging() -> Result<(), ()> {
 	fern::Dispatch::new()
@@ -28,74 +30,124 @@ fn main() {
 		}
 	};
 	client.init().unwrap();
-	let game_id = client.create_game();
-	let invite_token = client.get_invite_token(game_id);
-	println!("Got invite token '{}'", invite_token);
-	let game_state = client.get_game_state(game_id);
+	let start = if std::env::args().any(|arg| arg == "--chess960") {
+		StartPosition::RandomChess960
+	} else {
+		StartPosition::Standard
+	};
+	let variant = VariantKind::ALL
+		.iter()
+		.copied()
+		.find(|variant| std::env::args().any(|arg| arg.eq_ignore_ascii_case(&format!("--{:?}", variant))))
+		.unwrap_or_default();
+	// `--engine` plays against the first engine the server has, and `--engine=<name>` against a particular one
+	let opponent = std::env::args()
+		.find_map(|arg| match arg.strip_prefix("--engine") {
+			Some("") => Some(None),
+			Some(rest) => rest.strip_prefix('=').map(|name| Some(name.to_owned())),
+			None => None,
+		})
+		.map(|engine| EngineOpponent {
+			engine,
+			strength: std::env::args()
+				.find_map(|arg| {
+					arg.strip_prefix("--strength=")
+						.and_then(|strength| strength.parse().ok())
+				})
+				.unwrap_or(10),
+		});
+	// `--partner=<game id>` plays Bughouse on the partner board of the given game
+	let partner = std::env::args()
+		.find_map(|arg| arg.strip_prefix("--partner=").and_then(|id| id.parse().ok()))
+		.filter(|&id| id < 0)
+		.map(|id| ServerId::new(Id::new(id)));
+	let requested_engine = opponent.is_some();
+	let (game_id, engine) = client.create_game(start, variant, opponent, partner);
+	match &engine {
+		Some(engine) => println!("Playing against {}", engine),
+		None => {
+			if requested_engine {
+				println!("The engine could not be started, so the game is waiting for another player");
+			}
+			let invite_token = client.get_invite_token(game_id);
+			println!("Got invite token '{}'", invite_token);
+		}
+	}
+	let (mut game_state, variant) = client.get_game_state(game_id);
+	// Every position of the game is recorded, so that repeating one is noticed
+	let mut history = PositionHistory::new(&game_state);
 	let mut line = String::new();
 	render_game(&game_state);
 	loop {
 		print!("move> ");
 		std::io::stdout().flush().unwrap();
 		std::io::stdin().read_line(&mut line).unwrap();
-		let (move_start, move_end) = match parse_input(&line) {
+		if line.trim() == "draw" {
+			line.clear();
+			if client.claim_draw(game_id) {
+				break;
+			}
+			continue;
+		}
+		if line.trim() == "eval" {
+			line.clear();
+			println!("{}", Evaluation::new(&game_state));
+			println!("{} for the side to move", evaluate(&game_state));
+			continue;
+		}
+		// Pieces passed on from the partner board in Bughouse arrive at any time, so the pockets are brought
+		// up to date before the move is read
+		if variant == VariantKind::Bughouse {
+			game_state = client.get_game_state(game_id).0;
+			history.replace_latest(&game_state);
+		}
+		let parsed = parse_input(&line, &game_state);
+		line.clear();
+		let mv = match parsed {
 			Ok(t) => t,
-			Err(_) => {
-				println!("The move entered was not valid. Enter the start of the move and the finish of the move separated by a space.");
+			Err(e) => {
+				println!("The move entered was not valid ({}). Enter a move in algebraic notation such as 'Nf3' or 'e8=Q', or as the start and end squares such as 'e2 e4', 'draw' to claim a draw, or 'eval' to see how the position is judged.", e);
 				continue;
 			}
 		};
-		line.clear();
-		client.game_move(game_id, move_start, move_end);
-		let game_state = client.get_game_state(game_id);
+		if !client.game_move(game_id, mv) {
+			continue;
+		}
+		variant.rules().apply_move(&mut game_state, mv);
+		history.push(&game_state);
+		// Wait for the reply of the opponent, whether it is an engine or another player, so that the next
+		// move entered is read in the position after it
+		if variant.rules().game_over(&game_state, &history).is_none() {
+			if !client.wait_for_opponent().unwrap() {
+				break;
+			}
+			game_state = client.get_game_state(game_id).0;
+			history.push(&game_state);
+		}
 		render_game(&game_state);
+		if let Some(game_over) = variant.rules().game_over(&game_state, &history) {
+			println!("Game over: {:?} by {:?}", game_over.result, game_over.termination);
+			break;
+		}
 	}
 }
 
-fn parse_input(line: &str) -> Result<(BoardIndex, BoardIndex), ()> {
-	let line = line.trim();
-	let mut split = line.split_whitespace();
-	let first = parse_board_index(split.next().ok_or(())?)?;
-	let second = parse_board_index(split.next().ok_or(())?)?;
-	Ok((first, second))
-}
-
-fn parse_board_index(input: &str) -> Result<BoardIndex, ()> {
-	if input.len() != 2 {
-		return Err(());
-	};
-	let column = match input.as_bytes()[0] {
-		b'a' => Column::A,
-		b'b' => Column::B,
-		b'c' => Column::C,
-		b'd' => Column::D,
-		b'e' => Column::E,
-		b'f' => Column::F,
-		b'g' => Column::G,
-		b'h' => Column::H,
-		_ => return Err(()),
-	};
-	let row = match input.as_bytes()[1] {
-		b'1' => Row::R1,
-		b'2' => Row::R2,
-		b'3' => Row::R3,
-		b'4' => Row::R4,
-		b'5' => Row::R5,
-		b'6' => Row::R6,
-		b'7' => Row::R7,
-		b'8' => Row::R8,
-		_ => return Err(()),
-	};
-	Ok(BoardIndex::new(column, row))
+/// Parse a move entered by the user in any algebraic notation. Whitespace is ignored so that the start
+/// and end squares of a move can be entered separately, as in `e7 e8 q`. Moves that are only legal in a
+/// variant can be entered in UCI notation, and are checked by the server.
+fn parse_input(line: &str, game_state: &GameState) -> Result<Move, SanError> {
+	let joined: String = line.split_whitespace().collect();
+	game_state
+		.parse_move(&joined)
+		.or_else(|e| Move::from_uci(&joined).ok_or(e))
 }
 
 fn render_game(game_state: &GameState) {
 	println!();
-	for row in 0..8 {
-		let row = Row::from(row);
+	let size = game_state.board.size();
+	for row in (0..size.rows).filter_map(Row::from_index) {
 		print!("\t");
-		for column in 0..8 {
-			let column = Column::from(column);
+		for column in (0..size.columns).filter_map(Column::from_index) {
 			let (c, color) = match game_state.board.get_board_index(BoardIndex::new(column, row)) {
 				Some(GamePiece {
 					piece: Piece::Pawn,
@@ -117,6 +169,14 @@ fn render_game(game_state: &GameState) {
 					piece: Piece::Queen,
 					color,
 				}) => ('q', color),
+				Some(GamePiece {
+					piece: Piece::Archbishop,
+					color,
+				}) => ('a', color),
+				Some(GamePiece {
+					piece: Piece::Chancellor,
+					color,
+				}) => ('c', color),
 				Some(GamePiece {
 					piece: Piece::King,
 					color,
@@ -131,9 +191,43 @@ fn render_game(game_state: &GameState) {
 		}
 		println!();
 	}
+	for &color in &[Color::White, Color::Black] {
+		let pocket = game_state.pockets.get(color);
+		if !pocket.is_empty() {
+			let pieces: Vec<String> = pocket
+				.pieces()
+				.map(|piece| format!("{}x{:?}", pocket.count(piece), piece))
+				.collect();
+			println!("\t{:?} pocket: {}", color, pieces.join(", "));
+		}
+	}
 	println!();
 }
 
+/// Print a message the server sent without being asked for it, such as the opponent moving. Returns false
+/// if the message is not one of those.
+fn print_notification(message: &MachMessage) -> bool {
+	match message {
+		MachMessage::GameMoveHappened(happened) => {
+			println!("Opponent moved {} to {}", happened.move_start, happened.move_end);
+		}
+		MachMessage::GameDropHappened(happened) => {
+			println!("Opponent dropped a {:?} on {}", happened.piece, happened.index);
+		}
+		MachMessage::GamePieceReceived(received) => {
+			println!(
+				"{:?} received a {:?} from the partner board",
+				received.color, received.piece
+			);
+		}
+		MachMessage::GameEnded(ended) => {
+			println!("Game over: {:?} by {:?}", ended.result, ended.termination);
+		}
+		_ => return false,
+	}
+	true
+}
+
 pub struct Client {
 	ws_stream: WebSocket<TcpStream>,
 	id_tracker: i32,
@@ -151,7 +245,32 @@ impl Client {
 		})
 	}
 
+	/// Read the next message from the server, handling any notifications that were not requested
 	pub fn read_message(&mut self) -> Result<MachMessage, ()> {
+		loop {
+			let message = self.read_any_message()?;
+			if !print_notification(&message) {
+				return Ok(message);
+			}
+		}
+	}
+
+	/// Wait until the opponent moves or the game ends, returning whether the opponent moved
+	pub fn wait_for_opponent(&mut self) -> Result<bool, ()> {
+		loop {
+			let message = self.read_any_message()?;
+			if print_notification(&message) {
+				match message {
+					MachMessage::GamePieceReceived(_) => continue,
+					MachMessage::GameEnded(_) => return Ok(false),
+					_ => return Ok(true),
+				}
+			}
+			log::warn!("Got unexpected message while waiting for the opponent: {:?}", message);
+		}
+	}
+
+	fn read_any_message(&mut self) -> Result<MachMessage, ()> {
 		match self.ws_stream.read_message() {
 			Ok(Message::Text(text)) => {
 				let deserialized: MachMessage = json::from_str(&text).map_err(|_e| {
@@ -191,13 +310,25 @@ impl Client {
 		Ok(())
 	}
 
-	pub fn create_game(&mut self) -> ServerId {
+	/// Create a game, returning its id and the name of the engine playing the other side if one was asked
+	/// for and started
+	pub fn create_game(
+		&mut self,
+		start: StartPosition,
+		variant: VariantKind,
+		opponent: Option<EngineOpponent>,
+		partner: Option<ServerId>,
+	) -> (ServerId, Option<String>) {
 		let id = self.next_id();
 		self.ws_stream
 			.write_message(Message::Text(
 				json::to_string(&MachMessage::CreateGameRequest(CreateGameRequest {
 					id: *id,
 					color: Color::White,
+					start,
+					variant,
+					opponent,
+					partner,
 				}))
 				.unwrap(),
 			))
@@ -206,7 +337,17 @@ impl Client {
 		match message {
 			MachMessage::CreateGameResponse(res) => {
 				assert_eq!(res.id, id);
-				res.game_id
+				if variant == VariantKind::Bughouse {
+					// Ids are printed as they are written in the protocol, so they can be passed to `--partner`
+					match res.partner {
+						Some(partner) => println!(
+							"Playing on the partner board of game {}",
+							json::to_string(&partner).unwrap()
+						),
+						None => println!("Created game {}", json::to_string(&res.game_id).unwrap()),
+					}
+				}
+				(res.game_id, res.opponent)
 			}
 			m => {
 				log::error!("Got unexpected message while waiting for create game response: {:?}", m);
@@ -237,7 +378,7 @@ impl Client {
 		}
 	}
 
-	pub fn get_game_state(&mut self, game_id: ServerId) -> GameState {
+	pub fn get_game_state(&mut self, game_id: ServerId) -> (GameState, VariantKind) {
 		let id = self.next_id();
 		let message = MachMessage::GetGameStateRequest(GetGameStateRequest { id: *id, game_id });
 		self.ws_stream
@@ -247,7 +388,7 @@ impl Client {
 		match message {
 			MachMessage::GetGameStateResponse(res) => {
 				assert_eq!(res.id, id);
-				res.game_state
+				(res.game_state, res.variant)
 			}
 			m => {
 				log::error!("Got unexpected message while waiting for game state response: {:?}", m);
@@ -256,14 +397,24 @@ impl Client {
 		}
 	}
 
-	pub fn game_move(&mut self, game_id: ServerId, move_start: BoardIndex, move_end: BoardIndex) {
+	/// Ask the server to make a move, returning whether it was made
+	pub fn game_move(&mut self, game_id: ServerId, mv: Move) -> bool {
 		let id = self.next_id();
-		let message = MachMessage::GameMoveRequest(GameMoveRequest {
-			id: *id,
-			game_id,
-			move_start,
-			move_end,
-		});
+		let message = match mv.drop {
+			Some(piece) => MachMessage::GameDropRequest(GameDropRequest {
+				id: *id,
+				game_id,
+				piece,
+				index: mv.end,
+			}),
+			None => MachMessage::GameMoveRequest(GameMoveRequest {
+				id: *id,
+				game_id,
+				move_start: mv.start,
+				move_end: mv.end,
+				promotion: mv.promotion,
+			}),
+		};
 		self.ws_stream
 			.write_message(Message::Text(json::to_string(&message).unwrap()))
 			.unwrap();
@@ -274,8 +425,12 @@ impl Client {
 				if res.success {
 					println!("Moved successfully");
 				} else {
-					println!("Piece move failed");
+					match res.reason {
+						Some(reason) => println!("Piece move failed: {:?}", reason),
+						None => println!("Piece move failed"),
+					}
 				}
+				res.success
 			}
 			m => {
 				log::error!("Got unexpected message while waiting for game state response: {:?}", m);
@@ -284,6 +439,32 @@ impl Client {
 		}
 	}
 
+	/// Claim a draw by repetition or the fifty-move rule, returning whether the claim was accepted
+	pub fn claim_draw(&mut self, game_id: ServerId) -> bool {
+		let id = self.next_id();
+		let message = MachMessage::ClaimDrawRequest(ClaimDrawRequest { id: *id, game_id });
+		self.ws_stream
+			.write_message(Message::Text(json::to_string(&message).unwrap()))
+			.unwrap();
+		let message = self.read_message().unwrap();
+		match message {
+			MachMessage::ClaimDrawResponse(res) => {
+				assert_eq!(res.id, id);
+				if !res.success {
+					match res.reason {
+						Some(reason) => println!("Draw claim failed: {:?}", reason),
+						None => println!("Draw claim failed"),
+					}
+				}
+				res.success
+			}
+			m => {
+				log::error!("Got unexpected message while waiting for draw claim response: {:?}", m);
+				panic!()
+			}
+		}
+	}
+
 	pub fn next_id(&mut self) -> ClientId {
 		let id_tracker = self.id_tracker;
 		self.id_tracker += 1;
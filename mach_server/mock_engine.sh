@@ -0,0 +1,27 @@
+#!/bin/sh
+# A stand-in for a UCI engine, used to test the UCI client without a real engine installed. It answers
+# every search with the same reply to 1. e4.
+while read -r command rest; do
+	case "$command" in
+		uci)
+			echo "id name Mock Engine"
+			echo "id author mach"
+			echo "option name Skill Level type spin default 20 min 0 max 20"
+			echo "option name UCI_Chess960 type check default false"
+			echo "option name UCI_Variant type combo default chess var chess var crazyhouse"
+			echo "uciok"
+			;;
+		isready)
+			echo "readyok"
+			;;
+		go)
+			echo "info depth 1 score cp 20 nodes 20 pv e7e5"
+			echo "info depth 2 score cp 15 nodes 120 pv e7e5 g1f3"
+			echo "info depth 2 currmove e7e5 currmovenumber 1"
+			echo "bestmove e7e5 ponder g1f3"
+			;;
+		quit)
+			exit 0
+			;;
+	esac
+done
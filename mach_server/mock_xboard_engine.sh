@@ -0,0 +1,36 @@
+#!/bin/sh
+# A stand-in for an XBoard engine, used to test the XBoard client without a real engine installed. It
+# answers every search with the same reply to 1. e4, and resigns against 1. f3.
+while read -r command rest; do
+	case "$command" in
+		protover)
+			echo "feature myname=\"Mock XBoard Engine\" usermove=1 setboard=1 variants=\"normal,crazyhouse\" done=0"
+			echo "feature ping=1 done=1"
+			;;
+		ping)
+			echo "pong $rest"
+			;;
+		usermove)
+			case "$rest" in
+				e2e4|f2f3)
+					last="$rest"
+					;;
+				*)
+					echo "Illegal move: $rest"
+					;;
+			esac
+			;;
+		go)
+			if [ "$last" = "f2f3" ]; then
+				echo "resign"
+			else
+				echo "1 20 0 20 e5"
+				echo "2 15 1 120 e5 Nf3"
+				echo "move e7e5"
+			fi
+			;;
+		quit)
+			exit 0
+			;;
+	esac
+done
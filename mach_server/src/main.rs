@@ -1,13 +1,26 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use futures::{sink::SinkExt, stream::StreamExt};
 use tokio::{
 	net::{TcpListener, TcpStream},
-	sync::Mutex,
+	sync::{mpsc, Mutex},
 };
 use tokio_tungstenite::{tungstenite::Message, WebSocketStream};
 
-use mach::{game::*, proto::*};
+use mach::{
+	game::*,
+	proto::*,
+	tablebase::{Dtm, Tablebase},
+	variant::VariantKind,
+};
+
+use self::{
+	uci::{EngineClaim, EngineConfig, EngineMove, EngineProtocol, UciEngine},
+	xboard::XboardEngine,
+};
+
+mod uci;
+mod xboard;
 
 fn setup_logging() -> Result<(), ()> {
 	fern::Dispatch::new()
@@ -29,7 +42,23 @@ async fn main() {
 	let addr = "127.0.0.1:8099";
 	let mut listener = TcpListener::bind(addr).await.expect("Failed to bind TCP listener");
 
-	let global_state = Arc::new(Mutex::new(GlobalState::new()));
+	let mut global_state = GlobalState::new();
+	global_state.engines = std::env::args()
+		.filter_map(|arg| EngineConfig::from_arg(&arg))
+		.collect();
+	for engine in &global_state.engines {
+		println!("Engine '{}' available from {}", engine.name, engine.path.display());
+	}
+	if let Some(dir) = std::env::args().find_map(|arg| arg.strip_prefix("--tablebase=").map(String::from)) {
+		match Tablebase::open(&dir) {
+			Ok(tablebase) => {
+				println!("Loaded {} endgame tables from {}", tablebase.tables().count(), dir);
+				global_state.tablebase = Some(Arc::new(tablebase));
+			}
+			Err(e) => println!("Failed to load endgame tables from {}: {}", dir, e),
+		}
+	}
+	let global_state = Arc::new(Mutex::new(global_state));
 
 	let server = async move {
 		let mut incoming = listener.incoming();
@@ -56,57 +85,96 @@ pub struct ConnectionState {
 	protocol_version: Option<u32>,
 	global_state: Arc<Mutex<GlobalState>>,
 	client_handle: ClientHandle,
+	/// Messages sent to this client by other connections, to be forwarded over the websocket
+	receiver: mpsc::UnboundedReceiver<MachMessage>,
 }
 
 impl ConnectionState {
 	pub async fn run(mut self) {
 		self.perform_handshake().await.unwrap();
 		loop {
-			match self.ws_stream.next().await {
-				Some(Ok(Message::Text(data))) => {
-					let message: MachMessage = json::from_str(&data).unwrap();
-					self.handle_message(message).await.unwrap();
-				}
-				Some(Ok(Message::Close(_close_frame))) => {
-					log::trace!("Websocket connection closed");
-					break;
-				}
-				Some(Ok(u)) => {
-					log::info!("Got other message while waiting for initialization: {:?}", u);
-					self.ws_stream.close(None).await.unwrap();
-					break;
-				}
-				Some(Err(e)) => {
-					log::warn!(
-						"Encountered an error with the websocket while waiting for initialization: {}",
-						e
-					);
-					break;
-				}
-				None => {
-					log::trace!("Websocket connection closed");
-					break;
+			tokio::select! {
+				incoming = self.ws_stream.next() => match incoming {
+					Some(Ok(Message::Text(data))) => match json::from_str::<MachMessage>(&data) {
+						Ok(message) => {
+							if self.handle_message(message).await.is_err() {
+								log::warn!("Failed to handle message from client: {}", data);
+							}
+						}
+						Err(e) => {
+							log::warn!("Ignoring malformed message from client: {}", e);
+						}
+					},
+					Some(Ok(Message::Close(_close_frame))) => {
+						log::trace!("Websocket connection closed");
+						break;
+					}
+					Some(Ok(u)) => {
+						log::info!("Got other message while waiting for initialization: {:?}", u);
+						self.ws_stream.close(None).await.unwrap();
+						break;
+					}
+					Some(Err(e)) => {
+						log::warn!(
+							"Encountered an error with the websocket while waiting for initialization: {}",
+							e
+						);
+						break;
+					}
+					None => {
+						log::trace!("Websocket connection closed");
+						break;
+					}
+				},
+				Some(outgoing) = self.receiver.recv() => {
+					self.ws_stream
+						.send(Message::Text(json::to_string(&outgoing).unwrap()))
+						.await
+						.unwrap();
 				}
 			}
 		}
+		self.global_state.lock().await.clients.remove(&self.client_handle);
 	}
 
 	async fn handle_message(&mut self, message: MachMessage) -> Result<(), ()> {
 		log::trace!("Got message: {:?}", message);
 		match message {
 			MachMessage::CreateGameRequest(create) => {
-				let mut game_state = GameState::new();
-				game_state.board.set_standard();
+				let start = create
+					.variant
+					.rules()
+					.start_position()
+					.unwrap_or_else(|| create.start.game_state());
+				let engine = match &create.opponent {
+					Some(opponent) => self.start_engine(opponent, start.chess960, create.variant).await,
+					None => None,
+				};
+				let opponent = engine.as_ref().map(|engine| engine.name().to_owned());
+				let engine = engine.map(|engine| EnginePlayer {
+					color: create.color.opposite(),
+					engine: Arc::new(Mutex::new(engine)),
+				});
+				let engine_moves_first = engine.as_ref().is_some_and(|engine| engine.color == start.turn);
 				let mut global_lock = self.global_state.lock().await;
 				let server_id = global_lock.next_server_id();
+				let tablebase = global_lock.tablebase.clone();
+				let partner = create
+					.partner
+					.filter(|_| create.variant == VariantKind::Bughouse)
+					.and_then(|partner| global_lock.pair_partner(partner, server_id));
 				let game = Game {
 					client_handle: self.client_handle,
 					client_color: create.color,
 					other_client_handle: None,
 					id: create.id,
 					server_id: *server_id,
-					game_state,
+					record: mach::Game::with_variant(start, create.variant),
+					game_over: None,
 					invite_tokens: Vec::new(),
+					engine,
+					tablebase,
+					partner,
 				};
 				global_lock.games.push(game);
 				drop(global_lock);
@@ -114,11 +182,16 @@ impl ConnectionState {
 					id: create.id,
 					game_id: server_id,
 					color: create.color,
+					opponent,
+					partner,
 				});
 				self.ws_stream
 					.send(Message::Text(json::to_string(&message).unwrap()))
 					.await
 					.unwrap();
+				if engine_moves_first {
+					tokio::spawn(play_engine_move(Arc::clone(&self.global_state), server_id));
+				}
 			}
 			MachMessage::GetInviteTokenRequest(get) => {
 				let mut global_lock = self.global_state.lock().await;
@@ -147,12 +220,7 @@ impl ConnectionState {
 				let mut global_lock = self.global_state.lock().await;
 				for game in &mut global_lock.games {
 					if game.invite_tokens.contains(&join.invite_token) {
-						let success = if game.other_client_handle.is_some() {
-							true
-						} else {
-							game.other_client_handle = Some(self.client_handle);
-							false
-						};
+						let success = game.join(self.client_handle);
 						let message = MachMessage::JoinGameResponse(JoinGameResponse { id: join.id, success });
 						self.ws_stream
 							.send(Message::Text(json::to_string(&message).unwrap()))
@@ -167,7 +235,8 @@ impl ConnectionState {
 					if game.server_id == get.game_id {
 						let message = MachMessage::GetGameStateResponse(GetGameStateResponse {
 							id: get.id,
-							game_state: game.game_state.clone(),
+							game_state: game.record.state().clone(),
+							variant: game.record.variant(),
 						});
 						self.ws_stream
 							.send(Message::Text(json::to_string(&message).unwrap()))
@@ -177,24 +246,42 @@ impl ConnectionState {
 				}
 			}
 			MachMessage::GameMoveRequest(req) => {
+				let mv = Move {
+					start: req.move_start,
+					end: req.move_end,
+					promotion: req.promotion,
+					drop: None,
+				};
+				self.make_move(req.id, req.game_id, mv).await;
+			}
+			MachMessage::GameDropRequest(req) => {
+				self.make_move(req.id, req.game_id, Move::new_drop(req.piece, req.index))
+					.await;
+			}
+			MachMessage::ClaimDrawRequest(req) => {
 				let mut global_lock = self.global_state.lock().await;
-				for game in &mut global_lock.games {
-					let game: &mut Game = game;
-					if game.server_id == req.game_id {
-						let start_piece =
-							std::mem::replace(game.game_state.board.get_board_index_mut(req.move_start), None);
-						let _end_piece =
-							std::mem::replace(game.game_state.board.get_board_index_mut(req.move_end), start_piece);
-						let message = MachMessage::GameMoveResponse(GameMoveResponse {
-							id: req.id,
-							success: true,
-						});
-						self.ws_stream
-							.send(Message::Text(json::to_string(&message).unwrap()))
-							.await
-							.unwrap();
-					}
+				let result = match global_lock.games.iter_mut().find(|game| game.server_id == req.game_id) {
+					Some(game) => game
+						.claim_draw(self.client_handle)
+						.map(|game_over| (game.players(), game_over)),
+					None => Err(DrawClaimRejection::UnknownGame),
+				};
+				if let Ok((players, game_over)) = &result {
+					global_lock.end_game(req.game_id, players, *game_over);
 				}
+				drop(global_lock);
+				if let Err(reason) = result {
+					log::debug!("Rejected draw claim in game {:?}: {:?}", req.game_id, reason);
+				}
+				let message = MachMessage::ClaimDrawResponse(ClaimDrawResponse {
+					id: req.id,
+					success: result.is_ok(),
+					reason: result.err(),
+				});
+				self.ws_stream
+					.send(Message::Text(json::to_string(&message).unwrap()))
+					.await
+					.unwrap();
 			}
 			m => {
 				log::debug!("Got unexpected message from client: {:?}", m);
@@ -203,6 +290,101 @@ impl ConnectionState {
 		Ok(())
 	}
 
+	/// Make a move or drop requested by the client, telling the opponent about it and ending the game if
+	/// it is over
+	async fn make_move(&mut self, id: Id, game_id: ServerId, mv: Move) {
+		let mut global_lock = self.global_state.lock().await;
+		let result = match global_lock.games.iter_mut().find(|game| game.server_id == game_id) {
+			Some(game) => game
+				.make_move(self.client_handle, mv)
+				.map(|passed| (game.players(), game.game_over, game.engine_to_move(), passed)),
+			None => Err(MoveRejection::UnknownGame),
+		};
+		if let Ok((players, game_over, engine_to_move, passed)) = &result {
+			global_lock.announce_move(game_id, players, Some(self.client_handle), mv);
+			if let Some(piece) = passed {
+				global_lock.pass_to_partner(game_id, *piece);
+			}
+			if let Some(game_over) = game_over {
+				global_lock.end_game(game_id, players, *game_over);
+			}
+			if *engine_to_move {
+				tokio::spawn(play_engine_move(Arc::clone(&self.global_state), game_id));
+			}
+		}
+		drop(global_lock);
+		let result = result.map(|_| ());
+		if let Err(reason) = result {
+			log::debug!("Rejected move {:?} in game {:?}: {:?}", mv, game_id, reason);
+		}
+		let message = MachMessage::GameMoveResponse(GameMoveResponse {
+			id,
+			success: result.is_ok(),
+			reason: result.err(),
+		});
+		self.ws_stream
+			.send(Message::Text(json::to_string(&message).unwrap()))
+			.await
+			.unwrap();
+	}
+
+	/// Start the engine a client asked to play against, returning `None` if it is not configured, fails
+	/// to start or can not play the variant
+	async fn start_engine(
+		&self,
+		opponent: &EngineOpponent,
+		chess960: bool,
+		variant: VariantKind,
+	) -> Option<EngineProcess> {
+		if variant == VariantKind::Bughouse {
+			// Engines are not told about the pieces passed on from the partner board
+			log::warn!("Engines can not play {}", variant.name());
+			return None;
+		}
+		let config = {
+			let global_lock = self.global_state.lock().await;
+			let config = match &opponent.engine {
+				Some(name) => global_lock.engines.iter().find(|engine| &engine.name == name),
+				None => global_lock.engines.first(),
+			};
+			match config {
+				Some(config) => config.clone(),
+				None => {
+					log::warn!("No engine is configured for {:?}", opponent.engine);
+					return None;
+				}
+			}
+		};
+		let started = async {
+			match config.protocol {
+				EngineProtocol::Uci => {
+					let mut engine = UciEngine::spawn(&config).await?;
+					engine.set_strength(opponent.strength).await?;
+					engine.new_game(chess960, variant).await?;
+					Ok::<_, std::io::Error>(EngineProcess::Uci(engine))
+				}
+				EngineProtocol::Xboard => {
+					let mut engine = XboardEngine::spawn(&config).await?;
+					if !engine.can_play(variant, chess960) {
+						return Err(std::io::Error::new(
+							std::io::ErrorKind::InvalidInput,
+							format!("Engine {} can not play {}", engine.name, variant.name()),
+						));
+					}
+					engine.set_strength(opponent.strength);
+					Ok(EngineProcess::Xboard(engine))
+				}
+			}
+		};
+		match started.await {
+			Ok(engine) => Some(engine),
+			Err(e) => {
+				log::warn!("Failed to start engine '{}': {}", config.name, e);
+				None
+			}
+		}
+	}
+
 	async fn perform_handshake(&mut self) -> Result<(), ()> {
 		self.ws_stream
 			.send(Message::Text(
@@ -241,36 +423,123 @@ impl ConnectionState {
 	}
 }
 
+/// Ask the engine playing in a game for its move and make it, telling the players about it. An engine that
+/// fails to reply with a legal move forfeits the game.
+async fn play_engine_move(global_state: Arc<Mutex<GlobalState>>, game_id: ServerId) {
+	let (engine, record) = {
+		let global_lock = global_state.lock().await;
+		match global_lock.games.iter().find(|game| game.server_id == game_id) {
+			Some(Game {
+				engine: Some(engine),
+				record,
+				..
+			}) => (Arc::clone(&engine.engine), record.clone()),
+			_ => return,
+		}
+	};
+	let reply = engine.lock().await.best_move(&record).await;
+
+	let mut global_lock = global_state.lock().await;
+	let game = match global_lock.games.iter_mut().find(|game| game.server_id == game_id) {
+		Some(game) => game,
+		None => return,
+	};
+	let played = match reply {
+		Ok(EngineMove { claim: Some(claim), .. }) => {
+			log::info!("Engine ended game {:?} with {:?}", game_id, claim);
+			let game_over = game.engine_claim(claim);
+			if let Some(game_over) = game_over {
+				let players = game.players();
+				global_lock.end_game(game_id, &players, game_over);
+			}
+			return;
+		}
+		Ok(EngineMove {
+			best_move: Some(mv), ..
+		}) => match game.make_engine_move(mv) {
+			Ok(passed) => Some((mv, passed)),
+			Err(reason) => {
+				log::warn!("Engine move {:?} in game {:?} was rejected: {:?}", mv, game_id, reason);
+				None
+			}
+		},
+		Ok(_) => {
+			log::warn!("Engine found no move in game {:?}", game_id);
+			None
+		}
+		Err(e) => {
+			log::warn!("Engine failed in game {:?}: {}", game_id, e);
+			None
+		}
+	};
+	let game_over = match played {
+		Some(_) => game.game_over,
+		None => game.engine_forfeit(),
+	};
+	let players = game.players();
+	if let Some((mv, passed)) = played {
+		global_lock.announce_move(game_id, &players, None, mv);
+		if let Some(piece) = passed {
+			global_lock.pass_to_partner(game_id, piece);
+		}
+	}
+	if let Some(game_over) = game_over {
+		global_lock.end_game(game_id, &players, game_over);
+	}
+}
+
 pub async fn init(socket: TcpStream, global_state: Arc<Mutex<GlobalState>>) {
 	let ws_stream = tokio_tungstenite::accept_async(socket).await.unwrap();
-	let client_handle = global_state.lock().await.next_client_handle();
+	let (sender, receiver) = mpsc::unbounded_channel();
+	let client_handle = {
+		let mut global_lock = global_state.lock().await;
+		let client_handle = global_lock.next_client_handle();
+		global_lock.clients.insert(client_handle, sender);
+		client_handle
+	};
 	let connection_state = ConnectionState {
 		ws_stream,
 		protocol_version: None,
 		global_state,
 		client_handle,
+		receiver,
 	};
 	connection_state.run().await;
 }
 
 pub struct GlobalState {
 	games: Vec<Game>,
+	/// Channels used to send messages to each connected client
+	clients: HashMap<ClientHandle, mpsc::UnboundedSender<MachMessage>>,
 	client_handle_tracker: ClientHandle,
 	id_tracker: i32,
 	invite_token_tracker: Vec<u8>,
+	/// The engines clients can ask to play against
+	engines: Vec<EngineConfig>,
+	/// Endgame tables used to end games that are drawn with perfect play
+	tablebase: Option<Arc<Tablebase>>,
 }
 
 impl GlobalState {
 	pub fn new() -> Self {
 		Self {
 			games: Vec::new(),
+			clients: HashMap::new(),
 			client_handle_tracker: 1,
 			id_tracker: -1,
 			invite_token_tracker: String::from("aaaaaaaa").into_bytes(),
+			engines: Vec::new(),
+			tablebase: None,
 		}
 	}
 }
 
+impl Default for GlobalState {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
 impl GlobalState {
 	pub fn next_client_handle(&mut self) -> ClientHandle {
 		let current = self.client_handle_tracker;
@@ -287,10 +556,123 @@ impl GlobalState {
 	pub fn next_invite_token(&mut self) -> String {
 		next_invite_token(&mut self.invite_token_tracker)
 	}
+
+	/// Send a message to a connected client. Messages to clients that have disconnected are dropped.
+	pub fn send_to(&self, client_handle: ClientHandle, message: MachMessage) {
+		if let Some(sender) = self.clients.get(&client_handle) {
+			if sender.send(message).is_err() {
+				log::debug!("Dropped message to disconnected client {}", client_handle);
+			}
+		}
+	}
+
+	fn find_game(&self, game_id: ServerId) -> Option<&Game> {
+		self.games.iter().find(|game| game.server_id == game_id)
+	}
+
+	/// Make a new Bughouse game the partner board of an existing one, if that is a Bughouse game that is
+	/// still being played and has no partner board yet. Returns the id of the existing game if it was paired.
+	pub fn pair_partner(&mut self, partner_id: ServerId, game_id: ServerId) -> Option<ServerId> {
+		let partner = self.games.iter_mut().find(|game| {
+			game.server_id == partner_id
+				&& game.record.variant() == VariantKind::Bughouse
+				&& game.partner.is_none()
+				&& game.game_over.is_none()
+		})?;
+		partner.partner = Some(game_id);
+		Some(partner_id)
+	}
+
+	/// Pass a piece captured in a Bughouse game on to the pocket of the given color on its partner board,
+	/// telling the players there about it
+	pub fn pass_to_partner(&mut self, game_id: ServerId, piece: GamePiece) {
+		let partner_id = match self.find_game(game_id).and_then(|game| game.partner) {
+			Some(partner_id) => partner_id,
+			None => return,
+		};
+		let partner = match self.games.iter_mut().find(|game| game.server_id == partner_id) {
+			Some(partner) if partner.game_over.is_none() => partner,
+			_ => return,
+		};
+		partner.record.add_to_pocket(piece.color, piece.piece);
+		for player in partner.players() {
+			self.send_to(
+				player,
+				MachMessage::GamePieceReceived(GamePieceReceived {
+					game_id: partner_id,
+					color: piece.color,
+					piece: piece.piece,
+				}),
+			);
+		}
+	}
+
+	/// Tell the players in a game about a move made in it, except for the player who made it
+	pub fn announce_move(&self, game_id: ServerId, players: &[ClientHandle], mover: Option<ClientHandle>, mv: Move) {
+		for &player in players.iter().filter(|&&player| Some(player) != mover) {
+			let message = match mv.drop {
+				Some(piece) => MachMessage::GameDropHappened(GameDropHappened {
+					game_id,
+					piece,
+					index: mv.end,
+				}),
+				None => MachMessage::GameMoveHappened(GameMoveHappened {
+					game_id,
+					move_start: mv.start,
+					move_end: mv.end,
+					promotion: mv.promotion,
+				}),
+			};
+			self.send_to(player, message);
+		}
+	}
+
+	/// Tell every player in a game that it has ended. The partner board of a Bughouse game ends with it,
+	/// won by the same team.
+	pub fn end_game(&mut self, game_id: ServerId, players: &[ClientHandle], game_over: GameOver) {
+		self.announce_game_over(game_id, players, game_over);
+		let partner_id = match self.find_game(game_id).and_then(|game| game.partner) {
+			Some(partner_id) => partner_id,
+			None => return,
+		};
+		let partner = match self.games.iter_mut().find(|game| game.server_id == partner_id) {
+			Some(partner) if partner.game_over.is_none() => partner,
+			_ => return,
+		};
+		// Partners play different colors, so the team that won this board wins the other one with the other
+		// color
+		let result = match game_over.result {
+			GameResult::WhiteWins => GameResult::BlackWins,
+			GameResult::BlackWins => GameResult::WhiteWins,
+			GameResult::Draw => GameResult::Draw,
+		};
+		let partner_over = GameOver {
+			result,
+			termination: Termination::PartnerGameOver,
+		};
+		partner.game_over = Some(partner_over);
+		partner.engine = None;
+		let partner_players = partner.players();
+		self.announce_game_over(partner_id, &partner_players, partner_over);
+	}
+
+	fn announce_game_over(&self, game_id: ServerId, players: &[ClientHandle], game_over: GameOver) {
+		log::debug!("Game {:?} ended: {:?}", game_id, game_over);
+		for &player in players {
+			self.send_to(
+				player,
+				MachMessage::GameEnded(GameEnded {
+					game_id,
+					result: game_over.result,
+					termination: game_over.termination,
+				}),
+			);
+		}
+	}
 }
 
-fn next_invite_token(invite_token_tracker: &mut Vec<u8>) -> String {
-	let current = invite_token_tracker.clone();
+fn next_invite_token(invite_token_tracker: &mut [u8]) -> String {
+	let current = invite_token_tracker.to_vec();
 	let bytes = invite_token_tracker;
 	let mut index = bytes.len() - 1;
 	loop {
@@ -331,8 +713,276 @@ pub struct Game {
 	other_client_handle: Option<ClientHandle>,
 	id: Id,
 	server_id: Id,
-	game_state: mach::GameState,
+	/// The moves played in the game and the position they lead to
+	record: mach::Game,
+	/// How the game ended, once it has
+	game_over: Option<GameOver>,
 	invite_tokens: Vec<String>,
+	/// The engine playing the other side, if the client that created the game asked for one. It is shut
+	/// down when the game ends.
+	engine: Option<EnginePlayer>,
+	/// Endgame tables that the game is ended with as a draw once they show it can not be won
+	tablebase: Option<Arc<Tablebase>>,
+	/// The other board of a Bughouse game, where each player's partner plays the other color
+	partner: Option<ServerId>,
+}
+
+/// An engine run by the server as a player in a game
+pub struct EnginePlayer {
+	color: Color,
+	engine: Arc<Mutex<EngineProcess>>,
+}
+
+/// A running engine process, speaking whichever protocol it was configured with
+pub enum EngineProcess {
+	Uci(UciEngine),
+	Xboard(XboardEngine),
+}
+
+impl EngineProcess {
+	/// The name the engine gave itself
+	fn name(&self) -> &str {
+		match self {
+			EngineProcess::Uci(engine) => &engine.name,
+			EngineProcess::Xboard(engine) => &engine.name,
+		}
+	}
+
+	/// Ask the engine for its move in the current position of a game
+	async fn best_move(&mut self, game: &mach::Game) -> std::io::Result<EngineMove> {
+		match self {
+			EngineProcess::Uci(engine) => engine.best_move(game).await,
+			EngineProcess::Xboard(engine) => engine.best_move(game).await,
+		}
+	}
+}
+
+impl Game {
+	/// Get the color that the given client is playing in this game, if they are playing in it at all
+	fn player_color(&self, client_handle: ClientHandle) -> Option<Color> {
+		if client_handle == self.client_handle {
+			Some(self.client_color)
+		} else if Some(client_handle) == self.other_client_handle {
+			Some(self.client_color.opposite())
+		} else {
+			None
+		}
+	}
+
+	/// Get the handles of every client playing in this game
+	fn players(&self) -> Vec<ClientHandle> {
+		std::iter::once(self.client_handle)
+			.chain(self.other_client_handle)
+			.collect()
+	}
+
+	/// Let the given client join this game as the other player, returning whether it did. A game that
+	/// already has a second player, or an engine playing the other side, can't be joined.
+	fn join(&mut self, client_handle: ClientHandle) -> bool {
+		if self.other_client_handle.is_some() || self.engine.is_some() {
+			return false;
+		}
+		self.other_client_handle = Some(client_handle);
+		true
+	}
+
+	/// Check whether the engine playing in this game is the one to move
+	fn engine_to_move(&self) -> bool {
+		self.game_over.is_none()
+			&& self
+				.engine
+				.as_ref()
+				.is_some_and(|engine| engine.color == self.record.state().turn)
+	}
+
+	/// Make a move on behalf of the given client, if they are allowed to make it. Returns the piece to pass
+	/// on to the partner board if the move captured one in Bughouse, in the color of the partner who
+	/// receives it.
+	fn make_move(&mut self, client_handle: ClientHandle, mv: Move) -> Result<Option<GamePiece>, MoveRejection> {
+		let color = self.player_color(client_handle).ok_or(MoveRejection::NotAPlayer)?;
+		self.play(color, mv)
+	}
+
+	/// Make a move on behalf of the engine playing in this game
+	fn make_engine_move(&mut self, mv: Move) -> Result<Option<GamePiece>, MoveRejection> {
+		let color = self.engine.as_ref().ok_or(MoveRejection::NotAPlayer)?.color;
+		self.play(color, mv)
+	}
+
+	fn play(&mut self, color: Color, mv: Move) -> Result<Option<GamePiece>, MoveRejection> {
+		if self.game_over.is_some() {
+			return Err(MoveRejection::GameOver);
+		}
+		if color != self.record.state().turn {
+			return Err(MoveRejection::NotYourTurn);
+		}
+		let passed = self
+			.partner
+			.and(self.record.state().pocket_capture(mv))
+			.map(|piece| GamePiece::new(piece, color.opposite()));
+		self.record.make_move(mv)?;
+		self.game_over = self.record.game_over().or_else(|| self.tablebase_draw());
+		if self.game_over.is_some() {
+			self.engine = None;
+		}
+		Ok(passed)
+	}
+
+	/// End the game as a loss for the engine playing in it, after it failed to make a legal move. Returns
+	/// how the game ended, or `None` if it had already ended.
+	fn engine_forfeit(&mut self) -> Option<GameOver> {
+		let engine = self.engine.take()?;
+		if self.game_over.is_some() {
+			return None;
+		}
+		let game_over = GameOver {
+			result: GameResult::win_for(engine.color.opposite()),
+			termination: Termination::Forfeit,
+		};
+		self.game_over = Some(game_over);
+		Some(game_over)
+	}
+
+	/// End the game the way the engine playing in it declared: a resignation or a claimed win for its
+	/// opponent ends the game as a loss for the engine, and a draw claim ends it as a draw if the position
+	/// allows one. Any other claim forfeits the game. Returns how the game ended, or `None` if it had
+	/// already ended.
+	fn engine_claim(&mut self, claim: EngineClaim) -> Option<GameOver> {
+		let color = self.engine.as_ref()?.color;
+		if self.game_over.is_some() {
+			return None;
+		}
+		let game_over = match claim {
+			EngineClaim::Resign => GameOver {
+				result: GameResult::win_for(color.opposite()),
+				termination: Termination::Resignation,
+			},
+			EngineClaim::Result(result) if result == GameResult::win_for(color.opposite()) => GameOver {
+				result,
+				termination: Termination::Resignation,
+			},
+			EngineClaim::Result(GameResult::Draw) => match self.record.draw_claim() {
+				Some(termination) => GameOver {
+					result: GameResult::Draw,
+					termination,
+				},
+				None => return self.engine_forfeit(),
+			},
+			EngineClaim::Result(_) => return self.engine_forfeit(),
+		};
+		self.engine = None;
+		self.game_over = Some(game_over);
+		Some(game_over)
+	}
+
+	/// End the game as a draw if it is standard chess and the endgame tables show that neither side can win
+	/// from the current position. Won positions are played out, since the players have to find the mate.
+	fn tablebase_draw(&self) -> Option<GameOver> {
+		let tablebase = self
+			.tablebase
+			.as_ref()
+			.filter(|_| self.record.variant() == VariantKind::Standard)?;
+		match tablebase.probe(self.record.state())? {
+			Dtm::Draw => Some(GameOver {
+				result: GameResult::Draw,
+				termination: Termination::TablebaseDraw,
+			}),
+			_ => None,
+		}
+	}
+
+	/// Claim a draw on behalf of the given client, ending the game if the claim is valid
+	fn claim_draw(&mut self, client_handle: ClientHandle) -> Result<GameOver, DrawClaimRejection> {
+		self.player_color(client_handle).ok_or(DrawClaimRejection::NotAPlayer)?;
+		if self.game_over.is_some() {
+			return Err(DrawClaimRejection::GameOver);
+		}
+		let termination = self.record.draw_claim().ok_or(DrawClaimRejection::NoDrawAvailable)?;
+		let game_over = GameOver {
+			result: GameResult::Draw,
+			termination,
+		};
+		self.game_over = Some(game_over);
+		Ok(game_over)
+	}
 }
 
 pub type ClientHandle = u64;
+
+#[test]
+fn join_test() {
+	let mut game = Game {
+		client_handle: 1,
+		client_color: Color::White,
+		other_client_handle: None,
+		id: Id::new(1),
+		server_id: Id::new(1),
+		record: mach::Game::standard(),
+		game_over: None,
+		invite_tokens: vec![String::from("aaaaaaaa")],
+		engine: None,
+		tablebase: None,
+		partner: None,
+	};
+	assert!(game.join(2));
+	assert_eq!(game.player_color(2), Some(Color::Black));
+	// Only one client can join
+	assert!(!game.join(3));
+	assert_eq!(game.player_color(3), None);
+}
+
+#[test]
+fn bughouse_test() {
+	let mut global_state = GlobalState::new();
+	let mut ids = Vec::new();
+	for players in &[(1, 2), (3, 4)] {
+		let server_id = global_state.next_server_id();
+		ids.push(server_id);
+		global_state.games.push(Game {
+			client_handle: players.0,
+			client_color: Color::White,
+			other_client_handle: Some(players.1),
+			id: Id::new(1),
+			server_id: *server_id,
+			record: mach::Game::with_variant(GameState::standard(), VariantKind::Bughouse),
+			game_over: None,
+			invite_tokens: Vec::new(),
+			engine: None,
+			tablebase: None,
+			partner: None,
+		});
+	}
+	let (first, second) = (ids[0], ids[1]);
+	assert_eq!(global_state.pair_partner(first, second), Some(first));
+	global_state.games[1].partner = Some(first);
+	// A game can only have one partner board
+	assert_eq!(global_state.pair_partner(first, second), None);
+
+	// A pawn white captures on the first board goes to black, played by white's partner, on the second
+	for uci in &["e2e4", "d7d5", "e4d5"] {
+		let game = &mut global_state.games[0];
+		let color = game.record.state().turn;
+		let handle = if color == Color::White { 1 } else { 2 };
+		let passed = game.make_move(handle, Move::from_uci(uci).unwrap()).unwrap();
+		if let Some(piece) = passed {
+			assert_eq!(piece, GamePiece::new(Piece::Pawn, Color::Black));
+			global_state.pass_to_partner(first, piece);
+		}
+	}
+	assert_eq!(global_state.games[1].record.state().pockets.black.pawns, 1);
+
+	// Ending one board ends the other, won by the same team
+	let game_over = GameOver {
+		result: GameResult::WhiteWins,
+		termination: Termination::Checkmate,
+	};
+	global_state.games[0].game_over = Some(game_over);
+	global_state.end_game(first, &[1, 2], game_over);
+	assert_eq!(
+		global_state.games[1].game_over,
+		Some(GameOver {
+			result: GameResult::BlackWins,
+			termination: Termination::PartnerGameOver,
+		})
+	);
+}
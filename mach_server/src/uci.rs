@@ -0,0 +1,473 @@
+//! A client for chess engines that speak the Universal Chess Interface, which the server runs as child
+//! processes to play against its players
+
+use std::{io, path::PathBuf, process::Stdio, time::Duration};
+
+use tokio::{
+	io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+	process::{Child, ChildStdin, ChildStdout, Command},
+};
+
+use mach::{engine::Score, game::*, proto::EngineOpponent, variant::VariantKind};
+
+/// How long an engine may think about each move for each point of strength, in milliseconds
+const MOVETIME_PER_STRENGTH: u32 = 100;
+/// How much longer than the time it was given an engine may take to reply with its move before it is
+/// given up on
+pub const MOVE_TIMEOUT_GRACE: Duration = Duration::from_secs(5);
+
+/// The protocol an engine speaks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EngineProtocol {
+	/// The Universal Chess Interface
+	Uci,
+	/// The Chess Engine Communication Protocol used by XBoard
+	Xboard,
+}
+
+/// An engine executable the server can run, as given on its command line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EngineConfig {
+	/// The name clients ask for the engine by
+	pub name: String,
+	pub path: PathBuf,
+	pub protocol: EngineProtocol,
+}
+
+impl EngineConfig {
+	/// Read an engine from a command line argument of the form `--engine=<name>=<path>` for UCI engines
+	/// or `--xboard-engine=<name>=<path>` for XBoard engines. The name can be left out to name the engine
+	/// after its file.
+	pub fn from_arg(arg: &str) -> Option<Self> {
+		let (value, protocol) = match arg.strip_prefix("--engine=") {
+			Some(value) => (value, EngineProtocol::Uci),
+			None => (arg.strip_prefix("--xboard-engine=")?, EngineProtocol::Xboard),
+		};
+		let (name, path) = match value.find('=') {
+			Some(separator) => (value[..separator].to_owned(), PathBuf::from(&value[separator + 1..])),
+			None => {
+				let path = PathBuf::from(value);
+				let name = path.file_stem()?.to_string_lossy().into_owned();
+				(name, path)
+			}
+		};
+		Some(Self { name, path, protocol })
+	}
+}
+
+/// An option an engine accepts, as it described it in reply to the `uci` command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UciOption {
+	pub name: String,
+	/// The kind of option, such as `spin`, `check`, `combo`, `button` or `string`
+	pub kind: String,
+	pub default: Option<String>,
+	pub min: Option<i64>,
+	pub max: Option<i64>,
+	/// The values a `combo` option can take
+	pub vars: Vec<String>,
+}
+
+/// What an engine reported about its search in an `info` line. Anything it left out is absent.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct EngineInfo {
+	pub depth: Option<u32>,
+	/// The score of the position for the side to move
+	pub score: Option<Score>,
+	pub nodes: Option<u64>,
+	/// The principal variation, which is the line of play the engine expects
+	pub pv: Vec<Move>,
+}
+
+/// The move an engine chose and what it last reported about the search that found it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EngineMove {
+	/// The move the engine chose, or `None` if it found no legal move or ended the game with `claim`
+	pub best_move: Option<Move>,
+	/// The reply the engine expects
+	pub ponder: Option<Move>,
+	pub info: EngineInfo,
+	/// How the engine ended the game instead of moving, if it did
+	pub claim: Option<EngineClaim>,
+}
+
+/// A way an engine can end a game instead of moving
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EngineClaim {
+	Resign,
+	/// The engine declared the game over with the given result, such as when claiming a draw by repetition
+	Result(GameResult),
+}
+
+/// A running UCI engine process. The process is killed when this is dropped.
+pub struct UciEngine {
+	/// The name the engine gave itself
+	pub name: String,
+	options: Vec<UciOption>,
+	/// How strongly the engine plays, from `EngineOpponent::MIN_STRENGTH` to `EngineOpponent::MAX_STRENGTH`
+	strength: u32,
+	_child: Child,
+	stdin: ChildStdin,
+	stdout: BufReader<ChildStdout>,
+}
+
+impl UciEngine {
+	/// Start an engine process and wait for it to describe itself and its options
+	pub async fn spawn(config: &EngineConfig) -> io::Result<Self> {
+		let mut child = Command::new(&config.path)
+			.stdin(Stdio::piped())
+			.stdout(Stdio::piped())
+			.kill_on_drop(true)
+			.spawn()?;
+		let stdin = child.stdin.take().unwrap();
+		let stdout = BufReader::new(child.stdout.take().unwrap());
+		let mut engine = Self {
+			name: config.name.clone(),
+			options: Vec::new(),
+			strength: EngineOpponent::MAX_STRENGTH,
+			_child: child,
+			stdin,
+			stdout,
+		};
+		engine.send("uci").await?;
+		loop {
+			let line = engine.read_line().await?;
+			if line == "uciok" {
+				break;
+			} else if let Some(name) = line.strip_prefix("id name ") {
+				engine.name = name.trim().to_owned();
+			} else if let Some(option) = parse_option(&line) {
+				engine.options.push(option);
+			}
+		}
+		Ok(engine)
+	}
+
+	/// Find an option the engine accepts by its name, which is not case sensitive
+	pub fn option(&self, name: &str) -> Option<&UciOption> {
+		self.options
+			.iter()
+			.find(|option| option.name.eq_ignore_ascii_case(name))
+	}
+
+	async fn send(&mut self, command: &str) -> io::Result<()> {
+		log::trace!("Sending to engine {}: {}", self.name, command);
+		self.stdin.write_all(format!("{}\n", command).as_bytes()).await?;
+		self.stdin.flush().await
+	}
+
+	/// Read the next line the engine writes, failing if it exits
+	async fn read_line(&mut self) -> io::Result<String> {
+		let mut line = String::new();
+		if self.stdout.read_line(&mut line).await? == 0 {
+			return Err(io::Error::new(
+				io::ErrorKind::UnexpectedEof,
+				format!("Engine {} exited", self.name),
+			));
+		}
+		Ok(line.trim_end().to_owned())
+	}
+
+	/// Set one of the engine's options. Buttons are pressed by leaving out the value.
+	pub async fn set_option(&mut self, name: &str, value: Option<&str>) -> io::Result<()> {
+		match value {
+			Some(value) => self.send(&format!("setoption name {} value {}", name, value)).await,
+			None => self.send(&format!("setoption name {}", name)).await,
+		}
+	}
+
+	/// Wait until the engine has handled every command sent to it
+	pub async fn wait_ready(&mut self) -> io::Result<()> {
+		self.send("isready").await?;
+		while self.read_line().await? != "readyok" {}
+		Ok(())
+	}
+
+	/// Make the engine play at the given strength. Weaker play searches less deeply for less time, and
+	/// engines that have a `Skill Level` option or an `UCI_Elo` option have it set in proportion.
+	pub async fn set_strength(&mut self, strength: u32) -> io::Result<()> {
+		self.strength = strength.clamp(EngineOpponent::MIN_STRENGTH, EngineOpponent::MAX_STRENGTH);
+		let scale = |option: &UciOption| match (option.min, option.max) {
+			(Some(min), Some(max)) => Some(
+				min + (max - min) * i64::from(self.strength - EngineOpponent::MIN_STRENGTH)
+					/ i64::from(EngineOpponent::MAX_STRENGTH - EngineOpponent::MIN_STRENGTH),
+			),
+			_ => None,
+		};
+		if let Some(level) = self.option("Skill Level").and_then(scale) {
+			self.set_option("Skill Level", Some(&level.to_string())).await?;
+		} else if self.option("UCI_LimitStrength").is_some() {
+			if let Some(elo) = self.option("UCI_Elo").and_then(scale) {
+				self.set_option("UCI_LimitStrength", Some("true")).await?;
+				self.set_option("UCI_Elo", Some(&elo.to_string())).await?;
+			}
+		}
+		Ok(())
+	}
+
+	/// Tell the engine a new game is starting, which in Chess960 writes castling as the king moving onto
+	/// its rook. Variants other than standard chess are chosen with the `UCI_Variant` option, and fail
+	/// with `InvalidInput` if the engine does not list the variant as one it can play.
+	pub async fn new_game(&mut self, chess960: bool, variant: VariantKind) -> io::Result<()> {
+		if self.option("UCI_Chess960").is_some() {
+			let value = if chess960 { "true" } else { "false" };
+			self.set_option("UCI_Chess960", Some(value)).await?;
+		}
+		let supported = self
+			.option("UCI_Variant")
+			.is_some_and(|option| option.vars.iter().any(|var| var == uci_variant(variant)));
+		if supported {
+			self.set_option("UCI_Variant", Some(uci_variant(variant))).await?;
+		} else if variant != VariantKind::Standard {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidInput,
+				format!("Engine {} can not play {}", self.name, variant.name()),
+			));
+		}
+		self.send("ucinewgame").await?;
+		self.wait_ready().await
+	}
+
+	/// Ask the engine for its move in the current position of a game. Fails with `TimedOut` if the engine
+	/// takes more than `MOVE_TIMEOUT_GRACE` longer than the time it was given.
+	pub async fn best_move(&mut self, game: &Game) -> io::Result<EngineMove> {
+		let movetime = self.strength * MOVETIME_PER_STRENGTH;
+		self.send(&position_command(game)).await?;
+		self.send(&format!("go depth {} movetime {}", self.strength, movetime))
+			.await?;
+		let timeout = Duration::from_millis(movetime.into()) + MOVE_TIMEOUT_GRACE;
+		match tokio::time::timeout(timeout, self.read_best_move()).await {
+			Ok(reply) => reply,
+			Err(_) => Err(io::Error::new(
+				io::ErrorKind::TimedOut,
+				format!("Engine {} took too long to move", self.name),
+			)),
+		}
+	}
+
+	/// Read the engine's output until it replies with its move
+	async fn read_best_move(&mut self) -> io::Result<EngineMove> {
+		let mut info = EngineInfo::default();
+		loop {
+			let line = self.read_line().await?;
+			if let Some(latest) = parse_info(&line) {
+				// Lines without a PV, such as the ones reporting the current move, would hide the line
+				// the engine is expecting
+				if !latest.pv.is_empty() {
+					info = latest;
+				}
+			} else if let Some((best_move, ponder)) = parse_bestmove(&line) {
+				return Ok(EngineMove {
+					best_move,
+					ponder,
+					info,
+					claim: None,
+				});
+			}
+		}
+	}
+}
+
+/// The name a variant is chosen by with the `UCI_Variant` option, as engines such as Fairy-Stockfish
+/// name them
+pub fn uci_variant(variant: VariantKind) -> &'static str {
+	match variant {
+		VariantKind::Standard => "chess",
+		VariantKind::KingOfTheHill => "kingofthehill",
+		VariantKind::ThreeCheck => "3check",
+		VariantKind::Antichess => "antichess",
+		VariantKind::Atomic => "atomic",
+		VariantKind::Horde => "horde",
+		VariantKind::RacingKings => "racingkings",
+		VariantKind::Crazyhouse => "crazyhouse",
+		VariantKind::Bughouse => "bughouse",
+		VariantKind::Capablanca => "capablanca",
+		VariantKind::Grand => "grand",
+		VariantKind::Minichess => "gardner",
+	}
+}
+
+/// Write the `position` command that sets up the current position of a game, as the moves played from
+/// its starting position
+pub fn position_command(game: &Game) -> String {
+	let mut command = if *game.start() == GameState::standard() {
+		String::from("position startpos")
+	} else {
+		format!("position fen {}", game.start().to_fen())
+	};
+	if !game.moves().is_empty() {
+		command.push_str(" moves");
+		for played in game.moves() {
+			command.push(' ');
+			command.push_str(&played.mv.to_uci());
+		}
+	}
+	command
+}
+
+/// Read an `option` line, such as `option name Hash type spin default 16 min 1 max 1024`
+pub fn parse_option(line: &str) -> Option<UciOption> {
+	const KEYWORDS: [&str; 6] = ["name", "type", "default", "min", "max", "var"];
+	let mut words = line.split_whitespace();
+	if words.next() != Some("option") {
+		return None;
+	}
+	// Names and values can contain spaces, so each runs until the next keyword
+	let mut fields: Vec<(&str, Vec<&str>)> = Vec::new();
+	for word in words {
+		match fields.last_mut() {
+			Some((_, value)) if !KEYWORDS.contains(&word) => value.push(word),
+			_ if KEYWORDS.contains(&word) => fields.push((word, Vec::new())),
+			_ => return None,
+		}
+	}
+	let field = |key: &str| {
+		fields
+			.iter()
+			.find(|(field, _)| *field == key)
+			.map(|(_, value)| value.join(" "))
+	};
+	Some(UciOption {
+		name: field("name")?,
+		kind: field("type")?,
+		default: field("default"),
+		min: field("min").and_then(|min| min.parse().ok()),
+		max: field("max").and_then(|max| max.parse().ok()),
+		vars: fields
+			.iter()
+			.filter(|(field, _)| *field == "var")
+			.map(|(_, value)| value.join(" "))
+			.collect(),
+	})
+}
+
+/// Read an `info` line reporting on a search. Returns `None` if the line is not an `info` line.
+pub fn parse_info(line: &str) -> Option<EngineInfo> {
+	let mut words = line.split_whitespace();
+	if words.next() != Some("info") {
+		return None;
+	}
+	let mut info = EngineInfo::default();
+	while let Some(word) = words.next() {
+		match word {
+			"depth" => info.depth = words.next().and_then(|depth| depth.parse().ok()),
+			"nodes" => info.nodes = words.next().and_then(|nodes| nodes.parse().ok()),
+			"score" => {
+				info.score = match (words.next(), words.next().and_then(|value| value.parse::<i32>().ok())) {
+					(Some("cp"), Some(centipawns)) => Some(Score::centipawns(centipawns)),
+					(Some("mate"), Some(moves)) => Score::from_mate_moves(moves),
+					_ => None,
+				}
+			}
+			"pv" => {
+				info.pv = words.by_ref().map_while(Move::from_uci).collect();
+			}
+			// The rest of the line is free text
+			"string" => break,
+			_ => {}
+		}
+	}
+	Some(info)
+}
+
+/// Read a `bestmove` line into the chosen move and the expected reply. Returns `None` if the line is not
+/// a `bestmove` line.
+pub fn parse_bestmove(line: &str) -> Option<(Option<Move>, Option<Move>)> {
+	let mut words = line.split_whitespace();
+	if words.next() != Some("bestmove") {
+		return None;
+	}
+	let best_move = words.next().and_then(Move::from_uci);
+	let ponder = match (words.next(), words.next()) {
+		(Some("ponder"), Some(ponder)) => Move::from_uci(ponder),
+		_ => None,
+	};
+	Some((best_move, ponder))
+}
+
+#[test]
+fn parse_test() {
+	assert_eq!(
+		parse_option("option name Skill Level type spin default 20 min 0 max 20"),
+		Some(UciOption {
+			name: String::from("Skill Level"),
+			kind: String::from("spin"),
+			default: Some(String::from("20")),
+			min: Some(0),
+			max: Some(20),
+			vars: Vec::new(),
+		})
+	);
+	assert_eq!(
+		parse_option("option name UCI_Variant type combo default chess var chess var 3check")
+			.unwrap()
+			.vars,
+		["chess", "3check"]
+	);
+	assert_eq!(
+		parse_option("option name Clear Hash type button").unwrap().default,
+		None
+	);
+	assert_eq!(parse_option("id name Mock Engine"), None);
+
+	let info = parse_info("info depth 12 seldepth 18 score mate -3 nodes 52011 nps 1000 pv e2e4 e7e5 g1f3").unwrap();
+	assert_eq!(info.depth, Some(12));
+	assert_eq!(info.score, Some(Score::mated_in(6)));
+	assert_eq!(info.nodes, Some(52011));
+	assert_eq!(info.pv.len(), 3);
+	let info = parse_info("info score cp -35 upperbound").unwrap();
+	assert_eq!(info.score, Some(Score::centipawns(-35)));
+	let info = parse_info("info depth 3 score mate -2147483648").unwrap();
+	assert_eq!(info.score, None);
+	assert_eq!(parse_info("info string depth 3").unwrap(), EngineInfo::default());
+
+	assert_eq!(
+		parse_bestmove("bestmove e7e8q ponder a2a1n"),
+		Some((Move::from_uci("e7e8q"), Move::from_uci("a2a1n")))
+	);
+	assert_eq!(parse_bestmove("bestmove (none)"), Some((None, None)));
+	assert_eq!(parse_bestmove("readyok"), None);
+
+	assert_eq!(
+		EngineConfig::from_arg("--engine=/usr/bin/stockfish"),
+		Some(EngineConfig {
+			name: String::from("stockfish"),
+			path: PathBuf::from("/usr/bin/stockfish"),
+			protocol: EngineProtocol::Uci,
+		})
+	);
+	assert_eq!(EngineConfig::from_arg("--engine=mach=./mach_uci").unwrap().name, "mach");
+	assert_eq!(
+		EngineConfig::from_arg("--xboard-engine=crafty").unwrap().protocol,
+		EngineProtocol::Xboard
+	);
+	assert_eq!(EngineConfig::from_arg("--chess960"), None);
+}
+
+#[tokio::test]
+async fn mock_engine_test() {
+	let config = EngineConfig {
+		name: String::from("mock"),
+		path: PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/mock_engine.sh")),
+		protocol: EngineProtocol::Uci,
+	};
+	let mut engine = UciEngine::spawn(&config).await.unwrap();
+	assert_eq!(engine.name, "Mock Engine");
+	assert_eq!(engine.option("skill level").unwrap().max, Some(20));
+	engine.set_strength(5).await.unwrap();
+	engine.new_game(false, VariantKind::Standard).await.unwrap();
+	// The engine only lists Crazyhouse among the variants it can play
+	engine.new_game(false, VariantKind::Crazyhouse).await.unwrap();
+	let unsupported = engine.new_game(false, VariantKind::Atomic).await.unwrap_err();
+	assert_eq!(unsupported.kind(), io::ErrorKind::InvalidInput);
+	engine.new_game(false, VariantKind::Standard).await.unwrap();
+
+	let mut game = Game::standard();
+	game.make_move(Move::from_uci("e2e4").unwrap()).unwrap();
+	assert_eq!(position_command(&game), "position startpos moves e2e4");
+	let reply = engine.best_move(&game).await.unwrap();
+	assert_eq!(reply.best_move, Move::from_uci("e7e5"));
+	assert_eq!(reply.ponder, Move::from_uci("g1f3"));
+	assert_eq!(reply.info.depth, Some(2));
+	assert_eq!(reply.info.score, Some(Score::centipawns(15)));
+}
@@ -0,0 +1,402 @@
+//! A client for chess engines that speak the Chess Engine Communication Protocol used by XBoard, which
+//! the server runs as child processes to play against its players
+
+use std::{io, process::Stdio, time::Duration};
+
+use tokio::{
+	io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+	process::{Child, ChildStdin, ChildStdout, Command},
+};
+
+use mach::{engine::Score, game::*, proto::EngineOpponent, variant::VariantKind};
+
+use crate::uci::{EngineClaim, EngineConfig, EngineInfo, EngineMove, MOVE_TIMEOUT_GRACE};
+
+/// How long an engine may think about each move for each point of strength, in milliseconds
+const MOVETIME_PER_STRENGTH: u32 = 100;
+/// How long to wait for an engine to list its features before assuming it is an older engine that does
+/// not, as the protocol suggests
+const FEATURE_TIMEOUT: Duration = Duration::from_secs(2);
+/// The score XBoard engines report for a mate in one move, with mates further away scoring one more for
+/// each move
+const XBOARD_MATE_SCORE: i32 = 100_000;
+
+/// A running XBoard engine process. The process is killed when this is dropped.
+pub struct XboardEngine {
+	/// The name the engine gave itself
+	pub name: String,
+	/// Whether the engine asked for moves to be sent with the `usermove` command
+	usermove: bool,
+	/// The variants the engine listed with the `variants` feature, which is only standard chess if it
+	/// listed none
+	variants: Vec<String>,
+	/// How strongly the engine plays, from `EngineOpponent::MIN_STRENGTH` to `EngineOpponent::MAX_STRENGTH`
+	strength: u32,
+	/// The number of moves of the current game the engine knows about, or `None` before it has been told
+	/// about a game
+	moves_sent: Option<usize>,
+	_child: Child,
+	stdin: ChildStdin,
+	stdout: BufReader<ChildStdout>,
+}
+
+impl XboardEngine {
+	/// Start an engine process and wait for it to list the features it supports
+	pub async fn spawn(config: &EngineConfig) -> io::Result<Self> {
+		let mut child = Command::new(&config.path)
+			.stdin(Stdio::piped())
+			.stdout(Stdio::piped())
+			.kill_on_drop(true)
+			.spawn()?;
+		let stdin = child.stdin.take().unwrap();
+		let stdout = BufReader::new(child.stdout.take().unwrap());
+		let mut engine = Self {
+			name: config.name.clone(),
+			usermove: false,
+			variants: vec![String::from("normal")],
+			strength: EngineOpponent::MAX_STRENGTH,
+			moves_sent: None,
+			_child: child,
+			stdin,
+			stdout,
+		};
+		engine.send("xboard").await?;
+		engine.send("protover 2").await?;
+
+		// An engine that sends `done=0` needs more time to start up, and is waited for until it sends
+		// `done=1`
+		let mut patient = false;
+		loop {
+			let line = if patient {
+				engine.read_line().await?
+			} else {
+				match tokio::time::timeout(FEATURE_TIMEOUT, engine.read_line()).await {
+					Ok(line) => line?,
+					Err(_) => break,
+				}
+			};
+			let features = match line.strip_prefix("feature ") {
+				Some(features) => parse_features(features),
+				None => continue,
+			};
+			let mut done = false;
+			for (name, value) in features {
+				match (name.as_str(), value.as_str()) {
+					("myname", _) => engine.name = value.clone(),
+					("usermove", "1") => engine.usermove = true,
+					("variants", _) => engine.variants = value.split(',').map(|name| name.trim().to_owned()).collect(),
+					("done", "0") => patient = true,
+					("done", "1") => done = true,
+					_ => {}
+				}
+				// Moves are sent and read in coordinate notation
+				if (name.as_str(), value.as_str()) == ("san", "1") {
+					engine.send(&format!("rejected {}", name)).await?;
+				} else {
+					engine.send(&format!("accepted {}", name)).await?;
+				}
+			}
+			if done {
+				break;
+			}
+		}
+		Ok(engine)
+	}
+
+	async fn send(&mut self, command: &str) -> io::Result<()> {
+		log::trace!("Sending to engine {}: {}", self.name, command);
+		self.stdin.write_all(format!("{}\n", command).as_bytes()).await?;
+		self.stdin.flush().await
+	}
+
+	/// Read the next line the engine writes, failing if it exits
+	async fn read_line(&mut self) -> io::Result<String> {
+		let mut line = String::new();
+		if self.stdout.read_line(&mut line).await? == 0 {
+			return Err(io::Error::new(
+				io::ErrorKind::UnexpectedEof,
+				format!("Engine {} exited", self.name),
+			));
+		}
+		Ok(line.trim_end().to_owned())
+	}
+
+	/// Check whether the engine can play a game of the given variant, from a Chess960 starting position or
+	/// not
+	pub fn can_play(&self, variant: VariantKind, chess960: bool) -> bool {
+		match xboard_variant(variant, chess960) {
+			Some(name) => self.variants.iter().any(|listed| listed == name),
+			None => !chess960,
+		}
+	}
+
+	/// Make the engine play at the given strength from the next game on. Weaker play searches less deeply
+	/// for less time.
+	pub fn set_strength(&mut self, strength: u32) {
+		self.strength = strength.clamp(EngineOpponent::MIN_STRENGTH, EngineOpponent::MAX_STRENGTH);
+	}
+
+	/// Set up the starting position of a game, with the engine in force mode so that it does not move
+	/// until asked to
+	async fn new_game(&mut self, game: &Game) -> io::Result<()> {
+		self.send("new").await?;
+		if let Some(variant) = xboard_variant(game.variant(), game.start().chess960) {
+			self.send(&format!("variant {}", variant)).await?;
+		}
+		self.send("force").await?;
+		if *game.start() != GameState::standard() {
+			self.send(&format!("setboard {}", game.start().to_fen())).await?;
+		}
+		self.send("post").await?;
+		self.send(&format!("sd {}", self.strength)).await?;
+		self.send(&format!("st {}", self.move_seconds())).await?;
+		self.moves_sent = Some(0);
+		Ok(())
+	}
+
+	/// How many seconds the engine may think about each move at its strength
+	fn move_seconds(&self) -> u32 {
+		(self.strength * MOVETIME_PER_STRENGTH).div_ceil(1000)
+	}
+
+	/// Ask the engine for its move in the current position of a game. The engine keeps track of the game
+	/// itself, so only the moves it has not seen yet are sent, unless the game is not the one it knows.
+	/// Fails with `TimedOut` if the engine takes more than `MOVE_TIMEOUT_GRACE` longer than the time it
+	/// was given.
+	pub async fn best_move(&mut self, game: &Game) -> io::Result<EngineMove> {
+		let moves = game.moves();
+		let sent = match self.moves_sent {
+			Some(sent) if sent <= moves.len() => sent,
+			_ => {
+				self.new_game(game).await?;
+				0
+			}
+		};
+		self.send("force").await?;
+
+		// Moves are written in the position they are made in, since castling depends on it
+		let rules = game.variant().rules();
+		let mut position = game.start().clone();
+		for (ply, played) in moves.iter().enumerate() {
+			if ply >= sent {
+				let mv = position.move_to_coordinate(played.mv);
+				if self.usermove {
+					self.send(&format!("usermove {}", mv)).await?;
+				} else {
+					self.send(&mv).await?;
+				}
+			}
+			rules.apply_move(&mut position, played.mv);
+		}
+		self.moves_sent = Some(moves.len());
+		self.send("go").await?;
+
+		let timeout = Duration::from_secs(self.move_seconds().into()) + MOVE_TIMEOUT_GRACE;
+		match tokio::time::timeout(timeout, self.read_best_move(&position, moves.len())).await {
+			Ok(reply) => reply,
+			Err(_) => {
+				self.moves_sent = None;
+				Err(io::Error::new(
+					io::ErrorKind::TimedOut,
+					format!("Engine {} took too long to move", self.name),
+				))
+			}
+		}
+	}
+
+	/// Read the engine's output until it replies with its move in the given position, reached after the
+	/// given number of moves, or ends the game instead
+	async fn read_best_move(&mut self, position: &GameState, moves: usize) -> io::Result<EngineMove> {
+		let mut info = EngineInfo::default();
+		loop {
+			let line = self.read_line().await?;
+			let mut words = line.split_whitespace();
+			match words.next() {
+				Some("move") => {
+					let text = words.next().unwrap_or_default();
+					let best_move = position.parse_move(text).map_err(|e| {
+						io::Error::new(io::ErrorKind::InvalidData, format!("Engine {} moved {}", self.name, e))
+					})?;
+					// The engine has made the move itself, so it does not need to be sent back
+					self.moves_sent = Some(moves + 1);
+					return Ok(EngineMove {
+						best_move: Some(best_move),
+						ponder: info.pv.get(1).copied(),
+						info,
+						claim: None,
+					});
+				}
+				Some(word @ "resign") | Some(word @ "1-0") | Some(word @ "0-1") | Some(word @ "1/2-1/2") => {
+					self.moves_sent = None;
+					let claim = match word {
+						"1-0" => EngineClaim::Result(GameResult::WhiteWins),
+						"0-1" => EngineClaim::Result(GameResult::BlackWins),
+						"1/2-1/2" => EngineClaim::Result(GameResult::Draw),
+						_ => EngineClaim::Resign,
+					};
+					return Ok(EngineMove {
+						best_move: None,
+						ponder: None,
+						info,
+						claim: Some(claim),
+					});
+				}
+				Some(word) if word.starts_with("Illegal") || word.starts_with("Error") => {
+					self.moves_sent = None;
+					return Err(io::Error::new(
+						io::ErrorKind::InvalidData,
+						format!("Engine {} rejected the game: {}", self.name, line),
+					));
+				}
+				_ => {
+					if let Some(latest) = parse_thinking(&line, position) {
+						info = latest;
+					}
+				}
+			}
+		}
+	}
+}
+
+/// The name of a variant in the `variant` command, or `None` for standard chess, which needs no command.
+/// Variants other than standard chess are only played from their usual starting positions.
+pub fn xboard_variant(variant: VariantKind, chess960: bool) -> Option<&'static str> {
+	Some(match variant {
+		VariantKind::Standard if chess960 => "fischerandom",
+		VariantKind::Standard => return None,
+		VariantKind::KingOfTheHill => "kingofthehill",
+		VariantKind::ThreeCheck => "3check",
+		VariantKind::Antichess => "giveaway",
+		VariantKind::Atomic => "atomic",
+		VariantKind::Horde => "horde",
+		VariantKind::RacingKings => "racingkings",
+		VariantKind::Crazyhouse => "crazyhouse",
+		VariantKind::Bughouse => "bughouse",
+		VariantKind::Capablanca => "capablanca",
+		VariantKind::Grand => "grand",
+		VariantKind::Minichess => "gardner",
+	})
+}
+
+/// Read the `name=value` pairs of a `feature` line. String values are in double quotes and may contain
+/// spaces.
+pub fn parse_features(features: &str) -> Vec<(String, String)> {
+	let mut parsed = Vec::new();
+	let mut rest = features.trim_start();
+	while let Some(equals) = rest.find('=') {
+		let name = rest[..equals].trim().to_owned();
+		rest = &rest[equals + 1..];
+		let value = match rest.strip_prefix('"') {
+			Some(quoted) => {
+				let end = quoted.find('"').unwrap_or(quoted.len());
+				rest = quoted.get(end + 1..).unwrap_or("");
+				&quoted[..end]
+			}
+			None => {
+				let end = rest.find(' ').unwrap_or(rest.len());
+				let value = &rest[..end];
+				rest = &rest[end..];
+				value
+			}
+		};
+		parsed.push((name, value.to_owned()));
+		rest = rest.trim_start();
+	}
+	parsed
+}
+
+/// Read a line of thinking output, made of the depth, the score, the time in centiseconds, the number of
+/// positions searched and the principal variation in any notation, given the position being searched.
+/// Returns `None` if the line is not thinking output.
+pub fn parse_thinking(line: &str, game_state: &GameState) -> Option<EngineInfo> {
+	let mut words = line.split_whitespace();
+	let depth = words.next()?.trim_end_matches(['.', '&']).parse().ok()?;
+	// A mate too far away to represent leaves the score out
+	let score = match words.next()?.parse::<i32>().ok()? {
+		score if score > XBOARD_MATE_SCORE => Score::from_mate_moves(score - XBOARD_MATE_SCORE),
+		score if score < -XBOARD_MATE_SCORE => Score::from_mate_moves(score + XBOARD_MATE_SCORE),
+		score => Some(Score::centipawns(score)),
+	};
+	let _time = words.next()?;
+	let nodes = words.next()?.parse().ok()?;
+
+	// Move numbers and annotations in the PV are skipped, and it ends at the first move that can not be
+	// read
+	let mut position = game_state.clone();
+	let mut pv = Vec::new();
+	for word in words {
+		let word = word.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+		if word.is_empty() {
+			continue;
+		}
+		match position.parse_move(word) {
+			Ok(mv) => {
+				pv.push(mv);
+				position.apply_move_unchecked(mv);
+			}
+			Err(_) => break,
+		}
+	}
+	Some(EngineInfo {
+		depth: Some(depth),
+		score,
+		nodes: Some(nodes),
+		pv,
+	})
+}
+
+#[test]
+fn parse_test() {
+	assert_eq!(
+		parse_features("myname=\"Mock Engine 1.0\" usermove=1 san=0  done=1"),
+		vec![
+			(String::from("myname"), String::from("Mock Engine 1.0")),
+			(String::from("usermove"), String::from("1")),
+			(String::from("san"), String::from("0")),
+			(String::from("done"), String::from("1")),
+		]
+	);
+
+	let game_state = GameState::standard();
+	let info = parse_thinking("9 -35 124 801234 1. e4 e5 2. Nf3 Nc6 {book}", &game_state).unwrap();
+	assert_eq!(info.depth, Some(9));
+	assert_eq!(info.score, Some(Score::centipawns(-35)));
+	assert_eq!(info.nodes, Some(801_234));
+	assert_eq!(info.pv.len(), 4);
+	let info = parse_thinking("3 100002 0 500 e2e4", &game_state).unwrap();
+	assert_eq!(info.score, Some(Score::mate_in(3)));
+	let info = parse_thinking("3 -2147483648 0 500 e2e4", &game_state).unwrap();
+	assert_eq!(info.score, None);
+	assert_eq!(parse_thinking("pong 3", &game_state), None);
+}
+
+#[tokio::test]
+async fn mock_engine_test() {
+	let config = EngineConfig {
+		name: String::from("mock"),
+		path: concat!(env!("CARGO_MANIFEST_DIR"), "/mock_xboard_engine.sh").into(),
+		protocol: crate::uci::EngineProtocol::Xboard,
+	};
+	let mut engine = XboardEngine::spawn(&config).await.unwrap();
+	assert_eq!(engine.name, "Mock XBoard Engine");
+	assert!(engine.usermove);
+	assert!(engine.can_play(VariantKind::Crazyhouse, false));
+	assert!(!engine.can_play(VariantKind::Standard, true));
+	engine.set_strength(5);
+
+	let mut game = Game::standard();
+	game.make_move(Move::from_uci("e2e4").unwrap()).unwrap();
+	let reply = engine.best_move(&game).await.unwrap();
+	assert_eq!(reply.best_move, Move::from_uci("e7e5"));
+	assert_eq!(reply.ponder, Move::from_uci("g1f3"));
+	assert_eq!(reply.info.depth, Some(2));
+	assert_eq!(engine.moves_sent, Some(2));
+
+	// The engine resigns instead of answering 1. f3
+	let mut game = Game::standard();
+	game.make_move(Move::from_uci("f2f3").unwrap()).unwrap();
+	let reply = engine.best_move(&game).await.unwrap();
+	assert_eq!(reply.best_move, None);
+	assert_eq!(reply.claim, Some(EngineClaim::Resign));
+	assert_eq!(engine.moves_sent, None);
+}
@@ -0,0 +1,395 @@
+//! Runs the mach engine as a Universal Chess Interface engine, reading commands from stdin and writing
+//! replies to stdout, so that it can be loaded into any UCI chess GUI
+
+use std::{
+	io::BufRead,
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc,
+	},
+	thread::{self, JoinHandle},
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use mach::{book::*, engine::*, game::*, tablebase::*};
+
+/// The largest transposition table that can be asked for with the `Hash` option, in megabytes
+const MAX_HASH_SIZE: usize = 4096;
+/// How many more moves a game is expected to last when the GUI does not say how many moves are left
+/// until the next time control
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+/// Time left on the clock that is never spent, to allow for the time it takes to send the move
+const MOVE_OVERHEAD: Duration = Duration::from_millis(50);
+/// The endgame tables generated when no material sets are given to `--generate-tablebase`
+const DEFAULT_TABLES: [&str; 4] = ["KQK", "KRK", "KPK", "KBNK"];
+
+fn main() {
+	let args: Vec<String> = std::env::args().skip(1).collect();
+	if let Some(dir) = args.iter().find_map(|arg| arg.strip_prefix("--generate-tablebase=")) {
+		let names: Vec<&str> = args
+			.iter()
+			.filter(|arg| !arg.starts_with("--"))
+			.map(String::as_str)
+			.collect();
+		let names = if names.is_empty() {
+			&DEFAULT_TABLES[..]
+		} else {
+			&names[..]
+		};
+		if let Err(e) = generate_tablebase(dir, names) {
+			eprintln!("{}", e);
+			std::process::exit(1);
+		}
+		return;
+	}
+
+	let mut uci = Uci::new();
+	let stdin = std::io::stdin();
+	for line in stdin.lock().lines() {
+		let line = match line {
+			Ok(line) => line,
+			Err(_) => break,
+		};
+		if !uci.handle(&line) {
+			break;
+		}
+	}
+	uci.stop();
+}
+
+/// The state of the UCI engine between commands
+pub struct Uci {
+	/// The engine, while no search is running. A running search owns the engine and hands it back when it
+	/// finishes.
+	engine: Option<Engine>,
+	search: Option<JoinHandle<Engine>>,
+	stop: Arc<AtomicBool>,
+	/// The position set by the last `position` command
+	game_state: GameState,
+	/// The positions leading up to `game_state`, so that the engine avoids or aims for repetitions
+	history: PositionHistory,
+	/// Whether castling is written as the king moving onto its rook, as the GUI asked for with the
+	/// `UCI_Chess960` option
+	chess960: bool,
+	/// The opening book set with the `Book` option, which is played from instead of searching while it has
+	/// moves for the position
+	book: Option<Book>,
+}
+
+impl Uci {
+	pub fn new() -> Self {
+		let engine = Engine::new();
+		let game_state = GameState::standard();
+		Self {
+			stop: engine.stop_flag(),
+			engine: Some(engine),
+			search: None,
+			history: PositionHistory::new(&game_state),
+			game_state,
+			chess960: false,
+			book: None,
+		}
+	}
+
+	/// Handle one line of input from the GUI. Returns `false` when the GUI asks the engine to quit.
+	pub fn handle(&mut self, line: &str) -> bool {
+		let mut words = line.split_whitespace();
+		match words.next() {
+			Some("uci") => {
+				println!("id name mach");
+				println!("id author intrepidpig");
+				println!(
+					"option name Hash type spin default {} min 1 max {}",
+					Engine::DEFAULT_HASH_SIZE,
+					MAX_HASH_SIZE
+				);
+				println!("option name Clear Hash type button");
+				println!("option name UCI_Chess960 type check default false");
+				println!("option name Book type string default <empty>");
+				println!("option name Tablebase type string default <empty>");
+				println!("uciok");
+			}
+			Some("isready") => println!("readyok"),
+			Some("setoption") => self.set_option(&words.collect::<Vec<_>>()),
+			Some("ucinewgame") => self.engine().clear(),
+			Some("position") => {
+				self.stop();
+				if let Err(e) = self.set_position(&words.collect::<Vec<_>>()) {
+					println!("info string {}", e);
+				}
+			}
+			Some("go") => {
+				self.stop();
+				let limits = parse_go(&words.collect::<Vec<_>>(), self.game_state.turn);
+				self.go(limits);
+			}
+			Some("stop") => self.stop(),
+			Some("quit") => return false,
+			Some(command) => println!("info string Unknown command '{}'", command),
+			None => {}
+		}
+		true
+	}
+
+	/// Get the engine, stopping the running search if there is one. A search started with `go infinite`
+	/// never finishes on its own, so the engine is never waited for without stopping it first.
+	fn engine(&mut self) -> &mut Engine {
+		self.stop();
+		self.engine.as_mut().unwrap()
+	}
+
+	/// Wait for the running search to finish, if there is one, and take the engine back from it
+	fn wait(&mut self) {
+		if let Some(search) = self.search.take() {
+			self.engine = Some(search.join().expect("The search thread panicked"));
+		}
+	}
+
+	/// Stop the running search, if there is one. The search still replies with the best move it found.
+	pub fn stop(&mut self) {
+		self.stop.store(true, Ordering::Relaxed);
+		self.wait();
+	}
+
+	/// Handle `setoption name <name> [value <value>]`
+	fn set_option(&mut self, words: &[&str]) {
+		let value_at = words.iter().position(|&word| word == "value");
+		let name = words[..value_at.unwrap_or(words.len())]
+			.iter()
+			.skip_while(|&&word| word == "name")
+			.copied()
+			.collect::<Vec<_>>()
+			.join(" ");
+		let value = value_at.map(|at| words[at + 1..].join(" ")).unwrap_or_default();
+		match name.to_ascii_lowercase().as_str() {
+			"hash" => match value.parse::<usize>() {
+				Ok(megabytes) => self.engine().set_hash_size(megabytes.clamp(1, MAX_HASH_SIZE)),
+				Err(_) => println!("info string Invalid hash size '{}'", value),
+			},
+			"clear hash" => self.engine().clear(),
+			"uci_chess960" => self.chess960 = value == "true",
+			"book" if value.is_empty() || value == "<empty>" => self.book = None,
+			"book" => match Book::open(&value) {
+				Ok(book) => self.book = Some(book),
+				Err(e) => println!("info string Could not read book '{}': {}", value, e),
+			},
+			"tablebase" if value.is_empty() || value == "<empty>" => self.engine().set_tablebase(None),
+			"tablebase" => match Tablebase::open(&value) {
+				Ok(tablebase) => self.engine().set_tablebase(Some(Arc::new(tablebase))),
+				Err(e) => println!("info string Could not read tablebase '{}': {}", value, e),
+			},
+			_ => println!("info string Unknown option '{}'", name),
+		}
+	}
+
+	/// Handle `position [startpos | fen <fen>] [moves <move>...]`
+	fn set_position(&mut self, words: &[&str]) -> Result<(), String> {
+		let moves_at = words.iter().position(|&word| word == "moves").unwrap_or(words.len());
+		let mut game_state = match words.first() {
+			Some(&"startpos") => GameState::standard(),
+			Some(&"fen") => {
+				let fen = words[1..moves_at].join(" ");
+				GameState::from_fen(&fen).map_err(|e| format!("Invalid FEN '{}': {}", fen, e))?
+			}
+			_ => return Err(String::from("Expected 'startpos' or 'fen'")),
+		};
+		game_state.chess960 |= self.chess960;
+		let mut history = PositionHistory::new(&game_state);
+		for &word in words.iter().skip(moves_at + 1) {
+			let mv = Move::from_uci(word).ok_or_else(|| format!("Invalid move '{}'", word))?;
+			game_state
+				.apply_move(mv)
+				.map_err(|e| format!("Illegal move '{}': {:?}", word, e))?;
+			history.push(&game_state);
+		}
+		self.game_state = game_state;
+		self.history = history;
+		Ok(())
+	}
+
+	/// Start searching the current position on another thread, which reports each depth it completes
+	/// with an `info` line and the move it chooses with a `bestmove` line
+	fn go(&mut self, limits: SearchLimits) {
+		if let Some(book_move) = self.book_move() {
+			println!("bestmove {}", book_move);
+			return;
+		}
+		let mut engine = self.engine.take().unwrap();
+		engine.reset_stop();
+		let game_state = self.game_state.clone();
+		let history = self.history.clone();
+		self.search = Some(thread::spawn(move || {
+			let result = engine.search_with(&game_state, &history, limits, |result| {
+				println!("{}", info_line(result))
+			});
+			match result.best_move {
+				Some(best_move) => match result.pv.get(1) {
+					Some(ponder) => println!("bestmove {} ponder {}", best_move, ponder),
+					None => println!("bestmove {}", best_move),
+				},
+				None => println!("bestmove 0000"),
+			}
+			engine
+		}));
+	}
+
+	/// Choose a move for the current position from the opening book, if there is one
+	fn book_move(&self) -> Option<Move> {
+		let random = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map_or(0, |time| time.subsec_nanos().into());
+		self.book.as_ref()?.pick(&self.game_state, random)
+	}
+}
+
+impl Default for Uci {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// Generate the endgame tables of the named material sets, and any they lead to, into a directory along
+/// with the tables already there
+fn generate_tablebase(dir: &str, names: &[&str]) -> Result<(), String> {
+	let mut tablebase = match Tablebase::open(dir) {
+		Ok(tablebase) => tablebase,
+		Err(TablebaseError::Io(_)) => Tablebase::new(),
+		Err(e) => return Err(e.to_string()),
+	};
+	for &name in names {
+		let material = Material::parse(name).ok_or_else(|| format!("Invalid material set '{}'", name))?;
+		println!("Generating {}", material);
+		tablebase.generate(&material).map_err(|e| e.to_string())?;
+	}
+	tablebase.save(dir).map_err(|e| e.to_string())?;
+	println!("Saved {} tables to {}", tablebase.tables().count(), dir);
+	Ok(())
+}
+
+/// Read the arguments of a `go` command into the limits of a search for the given side. Searching
+/// without any arguments, or with `infinite`, runs until `stop`.
+pub fn parse_go(words: &[&str], turn: Color) -> SearchLimits {
+	let mut limits = SearchLimits::default();
+	let mut remaining = None;
+	let mut increment = Duration::from_millis(0);
+	let mut moves_to_go = None;
+	let mut words = words.iter();
+	while let Some(&word) = words.next() {
+		let mut number = || words.next().and_then(|value| value.parse::<u64>().ok());
+		match (word, turn) {
+			("depth", _) => limits.depth = number().map(|depth| depth as u32),
+			("nodes", _) => limits.nodes = number(),
+			("movetime", _) => limits.time = number().map(Duration::from_millis),
+			("wtime", Color::White) | ("btime", Color::Black) => remaining = number().map(Duration::from_millis),
+			("winc", Color::White) | ("binc", Color::Black) => {
+				increment = number().map(Duration::from_millis).unwrap_or_default()
+			}
+			("movestogo", _) => moves_to_go = number().map(|moves| moves as u32),
+			// The clock of the other side, and arguments without a value
+			("wtime", _) | ("btime", _) | ("winc", _) | ("binc", _) => {
+				number();
+			}
+			_ => {}
+		}
+	}
+	if let (None, Some(remaining)) = (limits.time, remaining) {
+		limits.time = Some(time_budget(remaining, increment, moves_to_go));
+	}
+	limits
+}
+
+/// Decide how long to think about a move given the time left on the clock, spreading it evenly over the
+/// moves until the next time control and spending most of the increment
+fn time_budget(remaining: Duration, increment: Duration, moves_to_go: Option<u32>) -> Duration {
+	let usable = remaining.checked_sub(MOVE_OVERHEAD).unwrap_or_default();
+	let moves = moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+	(usable / moves + increment * 3 / 4).min(usable)
+}
+
+/// Write a search result as a UCI `info` line
+pub fn info_line(result: &SearchResult) -> String {
+	let score = match result.score.mate_moves() {
+		Some(moves) => format!("mate {}", moves),
+		None => format!("cp {}", result.score.0),
+	};
+	let millis = result.time.as_millis() as u64;
+	let nps = result.nodes * 1000 / millis.max(1);
+	let pv: Vec<String> = result.pv.iter().map(|mv| mv.to_uci()).collect();
+	format!(
+		"info depth {} score {} nodes {} nps {} time {} pv {}",
+		result.depth,
+		score,
+		result.nodes,
+		nps,
+		millis,
+		pv.join(" ")
+	)
+}
+
+#[test]
+fn go_test() {
+	assert_eq!(parse_go(&["depth", "6"], Color::White), SearchLimits::depth(6));
+	assert_eq!(
+		parse_go(&["movetime", "1500"], Color::Black),
+		SearchLimits::time(Duration::from_millis(1500))
+	);
+	assert_eq!(parse_go(&["infinite"], Color::White), SearchLimits::default());
+
+	// Only the clock of the side to move counts
+	let limits = parse_go(&["wtime", "60050", "btime", "1000", "movestogo", "20"], Color::White);
+	assert_eq!(limits.time, Some(Duration::from_secs(3)));
+	let limits = parse_go(&["wtime", "1000", "btime", "30050", "binc", "2000"], Color::Black);
+	assert_eq!(limits.time, Some(Duration::from_millis(2500)));
+	// Never more time than is left
+	assert_eq!(
+		time_budget(Duration::from_millis(40), Duration::from_secs(1), None),
+		Duration::from_millis(0)
+	);
+}
+
+#[test]
+fn position_test() {
+	let mut uci = Uci::new();
+	uci.handle("position startpos moves e2e4 e7e5 g1f3");
+	assert_eq!(
+		uci.game_state.to_fen(),
+		"rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"
+	);
+	uci.handle("position fen 4k3/8/8/8/8/8/8/R3K3 w Q - 0 1 moves e1c1");
+	assert_eq!(uci.game_state.to_fen(), "4k3/8/8/8/8/8/8/2KR4 b - - 1 1");
+
+	// An illegal move leaves the position as it was
+	uci.handle("position startpos moves e2e5");
+	assert_eq!(uci.game_state.to_fen(), "4k3/8/8/8/8/8/8/2KR4 b - - 1 1");
+
+	// A new position stops an infinite search instead of waiting for it
+	uci.handle("go infinite");
+	uci.handle("position startpos");
+	uci.handle("go infinite");
+	uci.handle("ucinewgame");
+	assert!(uci.search.is_none());
+
+	let mut result = Engine::new().search(
+		&GameState::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap(),
+		SearchLimits::depth(1),
+	);
+	result.time = Duration::from_millis(0);
+	assert!(info_line(&result).starts_with("info depth 1 score mate 1 nodes"));
+	assert!(info_line(&result).ends_with("pv a1a8"));
+}
+
+#[test]
+fn book_test() {
+	let mut builder = BookBuilder::new();
+	builder.add_pgn("[Result \"1-0\"]\n1. d4 d5 2. c4 1-0\n").unwrap();
+	let path = std::env::temp_dir().join(format!("mach_uci_book_test_{}.bin", std::process::id()));
+	builder.build().save(&path).unwrap();
+
+	let mut uci = Uci::new();
+	uci.handle(&format!("setoption name Book value {}", path.display()));
+	uci.handle("position startpos moves d2d4 d7d5");
+	assert_eq!(uci.book_move(), Move::from_uci("c2c4"));
+	uci.handle("position startpos moves e2e4");
+	assert_eq!(uci.book_move(), None);
+	std::fs::remove_file(path).unwrap();
+}
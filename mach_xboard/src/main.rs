@@ -0,0 +1,385 @@
+//! Runs the mach engine as an XBoard engine, speaking the Chess Engine Communication Protocol over stdin
+//! and stdout, so that it can be loaded into XBoard, WinBoard and other GUIs that use that protocol
+
+use std::{
+	io::BufRead,
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc,
+	},
+	thread::{self, JoinHandle},
+	time::Duration,
+};
+
+use mach::{engine::*, game::*};
+
+/// How many more moves a game is expected to last when the time control does not say how many moves are
+/// left until the next one
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+/// Time left on the clock that is never spent, to allow for the time it takes to send the move
+const MOVE_OVERHEAD: Duration = Duration::from_millis(50);
+/// The score XBoard expects for a mate in one move, with mates further away scoring one more for each move
+const XBOARD_MATE_SCORE: i32 = 100_000;
+
+fn main() {
+	let mut xboard = Xboard::new();
+	let stdin = std::io::stdin();
+	for line in stdin.lock().lines() {
+		let line = match line {
+			Ok(line) => line,
+			Err(_) => break,
+		};
+		if !xboard.handle(&line) {
+			break;
+		}
+	}
+	xboard.interrupt();
+}
+
+/// The time control set by the GUI
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TimeControl {
+	/// The number of moves to make before the clock is reset, or 0 to play the whole game on one clock
+	pub moves_per_session: u32,
+	/// The time added to the clock after each move
+	pub increment: Duration,
+	/// The exact time to spend on each move, set with `st` instead of a clock
+	pub move_time: Option<Duration>,
+	/// The depth to search to, set with `sd`
+	pub depth: Option<u32>,
+	/// The time left on the engine's clock, as last reported with `time`
+	pub remaining: Option<Duration>,
+}
+
+/// The state of the XBoard engine between commands. Unlike UCI, the engine keeps track of the game
+/// itself, and plays one side of it until told otherwise.
+pub struct Xboard {
+	/// The engine, while it is not thinking. A running search owns the engine and hands it back along
+	/// with the move it played when it finishes.
+	engine: Option<Engine>,
+	search: Option<JoinHandle<(Engine, Option<Move>)>>,
+	stop: Arc<AtomicBool>,
+	/// Set to throw away the move of the running search, such as when the GUI sets up a new position
+	cancel: Arc<AtomicBool>,
+	game: Game,
+	/// The side the engine plays, or `None` in force mode, where it only keeps track of the moves made
+	engine_color: Option<Color>,
+	chess960: bool,
+	/// Whether to show what the engine is thinking, as switched with `post` and `nopost`
+	post: bool,
+	time_control: TimeControl,
+}
+
+impl Xboard {
+	pub fn new() -> Self {
+		let engine = Engine::new();
+		Self {
+			stop: engine.stop_flag(),
+			engine: Some(engine),
+			search: None,
+			cancel: Arc::new(AtomicBool::new(false)),
+			game: Game::standard(),
+			engine_color: Some(Color::Black),
+			chess960: false,
+			post: false,
+			time_control: TimeControl::default(),
+		}
+	}
+
+	/// Handle one line of input from the GUI. Returns `false` when the GUI asks the engine to quit.
+	pub fn handle(&mut self, line: &str) -> bool {
+		let (command, arguments) = match line.trim().find(' ') {
+			Some(space) => (&line.trim()[..space], line.trim()[space + 1..].trim()),
+			None => (line.trim(), ""),
+		};
+		match command {
+			"protover" => println!(
+				"feature myname=\"mach\" usermove=1 setboard=1 ping=1 playother=1 sigint=0 sigterm=0 colors=0 \
+				 analyze=0 variants=\"normal,fischerandom\" done=1"
+			),
+			"new" => {
+				self.interrupt();
+				self.game = Game::standard();
+				self.engine_color = Some(Color::Black);
+				self.chess960 = false;
+				self.time_control.depth = None;
+				self.engine.as_mut().unwrap().clear();
+			}
+			"variant" => match arguments {
+				"normal" => self.chess960 = false,
+				"fischerandom" => self.chess960 = true,
+				_ => println!("Error (unsupported variant): {}", arguments),
+			},
+			"force" => {
+				self.interrupt();
+				self.engine_color = None;
+			}
+			"go" => {
+				self.wait();
+				self.engine_color = Some(self.game.state().turn);
+				self.think();
+			}
+			"playother" => {
+				self.wait();
+				self.engine_color = Some(self.game.state().turn.opposite());
+			}
+			"usermove" => self.user_move(arguments),
+			"setboard" => {
+				self.interrupt();
+				match GameState::from_fen(arguments) {
+					Ok(mut game_state) => {
+						game_state.chess960 |= self.chess960;
+						self.game = Game::new(game_state);
+					}
+					Err(e) => println!("tellusererror Illegal position: {}", e),
+				}
+			}
+			"undo" => {
+				self.interrupt();
+				self.game.undo();
+			}
+			"remove" => {
+				self.interrupt();
+				self.game.undo();
+				self.game.undo();
+			}
+			"level" => match parse_level(arguments) {
+				Some((moves_per_session, increment)) => {
+					self.time_control.moves_per_session = moves_per_session;
+					self.time_control.increment = increment;
+					self.time_control.move_time = None;
+				}
+				None => println!("Error (invalid time control): {}", arguments),
+			},
+			"st" => match parse_seconds(arguments) {
+				Some(move_time) => self.time_control.move_time = Some(move_time),
+				None => println!("Error (invalid time): {}", arguments),
+			},
+			"sd" => match arguments.parse() {
+				Ok(depth) => self.time_control.depth = Some(depth),
+				Err(_) => println!("Error (invalid depth): {}", arguments),
+			},
+			"time" => {
+				self.time_control.remaining = arguments
+					.parse::<u64>()
+					.ok()
+					.map(|centiseconds| Duration::from_millis(centiseconds * 10))
+			}
+			"?" => self.stop.store(true, Ordering::Relaxed),
+			"ping" => println!("pong {}", arguments),
+			"post" => self.post = true,
+			"nopost" => self.post = false,
+			"quit" => return false,
+			// Commands this engine has nothing to do for
+			"xboard" | "accepted" | "rejected" | "random" | "easy" | "hard" | "otim" | "computer" | "name"
+			| "rating" | "result" | "draw" | "hint" | "bk" | "ics" => {}
+			"" => {}
+			// GUIs that do not accept the usermove feature send moves on their own
+			_ => match self.game.state().parse_move(command) {
+				Ok(_) => self.user_move(command),
+				Err(_) => println!("Error (unknown command): {}", command),
+			},
+		}
+		true
+	}
+
+	/// Make a move for the opponent of the engine, and reply to it if the engine is playing
+	fn user_move(&mut self, input: &str) {
+		self.wait();
+		match self.game.state().parse_move(input) {
+			Ok(mv) => {
+				self.game.make_move(mv).unwrap();
+				if self.engine_color == Some(self.game.state().turn) {
+					self.think();
+				}
+			}
+			Err(_) => println!("Illegal move: {}", input),
+		}
+	}
+
+	/// Wait for the engine to finish thinking, if it is, and play the move it chose. A move that was sent
+	/// to the GUI is always played, even if the search was interrupted after sending it.
+	fn wait(&mut self) {
+		if let Some(search) = self.search.take() {
+			let (engine, mv) = search.join().expect("The search thread panicked");
+			self.engine = Some(engine);
+			if let Some(mv) = mv {
+				self.game.make_move(mv).unwrap();
+			}
+		}
+	}
+
+	/// Stop the engine thinking, if it is, without playing the move it chose
+	pub fn interrupt(&mut self) {
+		self.cancel.store(true, Ordering::Relaxed);
+		self.stop.store(true, Ordering::Relaxed);
+		self.wait();
+	}
+
+	/// Start thinking about a move on another thread, which plays it with a `move` line when it finishes
+	fn think(&mut self) {
+		if self.game.game_over().is_some() {
+			return;
+		}
+		let mut engine = self.engine.take().unwrap();
+		engine.reset_stop();
+		let limits = self.limits();
+		let mut game = self.game.clone();
+		let post = self.post;
+		let cancel = Arc::clone(&self.cancel);
+		cancel.store(false, Ordering::Relaxed);
+		self.search = Some(thread::spawn(move || {
+			let game_state = game.state().clone();
+			let result = engine.search_with(&game_state, game.history(), limits, |result| {
+				if post {
+					println!("{}", thinking_line(&game_state, result));
+				}
+			});
+			let mv = match result.best_move {
+				Some(mv) if !cancel.load(Ordering::Relaxed) => mv,
+				_ => return (engine, None),
+			};
+			println!("move {}", game_state.move_to_coordinate(mv));
+			game.make_move(mv).unwrap();
+			if let Some(game_over) = game.game_over() {
+				println!("{}", result_line(game_over));
+			}
+			(engine, Some(mv))
+		}));
+	}
+
+	/// Decide how far to search for the next move
+	fn limits(&self) -> SearchLimits {
+		let time_control = self.time_control;
+		let mut limits = SearchLimits {
+			depth: time_control.depth,
+			..SearchLimits::default()
+		};
+		limits.time = time_control.move_time.or_else(|| {
+			let moves_to_go = match time_control.moves_per_session {
+				0 => DEFAULT_MOVES_TO_GO,
+				moves => moves - (self.game.state().fullmove_number - 1) % moves,
+			};
+			time_control
+				.remaining
+				.map(|remaining| time_budget(remaining, time_control.increment, moves_to_go))
+		});
+		limits
+	}
+}
+
+impl Default for Xboard {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// Read the arguments of a `level` command, such as `40 5 0` or `0 2:30 1.5`, into the number of moves
+/// per session and the increment. The base time is not needed, as the GUI reports the time on the clock
+/// before each move.
+pub fn parse_level(arguments: &str) -> Option<(u32, Duration)> {
+	let arguments: Vec<&str> = arguments.split_whitespace().collect();
+	if arguments.len() != 3 || !arguments[1].split(':').all(|part| part.parse::<f64>().is_ok()) {
+		return None;
+	}
+	let moves_per_session = arguments[0].parse().ok()?;
+	let increment = parse_seconds(arguments[2])?;
+	Some((moves_per_session, increment))
+}
+
+/// Read a number of seconds, such as `1.5`, returning `None` if it is negative or too large to be a
+/// duration
+fn parse_seconds(seconds: &str) -> Option<Duration> {
+	seconds
+		.parse()
+		.ok()
+		.and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+}
+
+/// Decide how long to think about a move given the time left on the clock, spreading it evenly over the
+/// moves until the next time control and spending most of the increment
+fn time_budget(remaining: Duration, increment: Duration, moves_to_go: u32) -> Duration {
+	let usable = remaining.checked_sub(MOVE_OVERHEAD).unwrap_or_default();
+	(usable / moves_to_go.max(1) + increment * 3 / 4).min(usable)
+}
+
+/// Write a search result as the thinking output XBoard shows: the depth, the score, the time in
+/// centiseconds, the number of positions searched and the principal variation in SAN
+pub fn thinking_line(game_state: &GameState, result: &SearchResult) -> String {
+	let score = match result.score.mate_moves() {
+		Some(moves) if moves > 0 => XBOARD_MATE_SCORE + moves,
+		Some(moves) => -XBOARD_MATE_SCORE + moves,
+		None => result.score.0,
+	};
+	let mut position = game_state.clone();
+	let mut pv = Vec::new();
+	for &mv in &result.pv {
+		if !position.is_legal(mv) {
+			break;
+		}
+		pv.push(position.move_to_san(mv));
+		position.apply_move_unchecked(mv);
+	}
+	format!(
+		"{} {} {} {} {}",
+		result.depth,
+		score,
+		result.time.as_millis() / 10,
+		result.nodes,
+		pv.join(" ")
+	)
+}
+
+/// Write how a game ended in the form XBoard expects, such as `1-0 {White mates}`
+fn result_line(game_over: GameOver) -> String {
+	let result = match game_over.result {
+		GameResult::WhiteWins => "1-0",
+		GameResult::BlackWins => "0-1",
+		GameResult::Draw => "1/2-1/2",
+	};
+	format!("{} {{{:?}}}", result, game_over.termination)
+}
+
+#[test]
+fn level_test() {
+	assert_eq!(parse_level("40 5 0"), Some((40, Duration::from_secs(0))));
+	assert_eq!(parse_level("0 2:30 1.5"), Some((0, Duration::from_millis(1500))));
+	assert_eq!(parse_level("40 5"), None);
+	assert_eq!(parse_level("40 5 inf"), None);
+	assert_eq!(parse_level("40 5 1e30"), None);
+
+	let mut xboard = Xboard::new();
+	xboard.handle("level 40 5 0");
+	xboard.handle("time 30005");
+	assert_eq!(xboard.limits().time, Some(Duration::from_millis(7500)));
+	xboard.handle("st 2");
+	assert_eq!(xboard.limits().time, Some(Duration::from_secs(2)));
+	xboard.handle("st 1e30");
+	assert_eq!(xboard.limits().time, Some(Duration::from_secs(2)));
+}
+
+#[test]
+fn game_test() {
+	let mut xboard = Xboard::new();
+	xboard.handle("new");
+	xboard.handle("force");
+	xboard.handle("usermove e2e4");
+	xboard.handle("usermove e5");
+	xboard.handle("usermove e1e3");
+	assert_eq!(xboard.game.moves().len(), 2);
+	xboard.handle("undo");
+	assert_eq!(xboard.game.state().turn, Color::Black);
+
+	// The engine finds the mate and plays it once the GUI sends the next command
+	xboard.handle("setboard 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+	xboard.handle("sd 2");
+	xboard.handle("go");
+	xboard.handle("ping 1");
+	xboard.wait();
+	assert_eq!(xboard.game.state().to_fen(), "R5k1/5ppp/8/8/8/8/8/6K1 b - - 1 1");
+	assert_eq!(xboard.engine_color, Some(Color::White));
+
+	let result = Engine::new().search(xboard.game.start(), SearchLimits::depth(1));
+	assert!(thinking_line(xboard.game.start(), &result).starts_with("1 100001 "));
+	assert!(thinking_line(xboard.game.start(), &result).ends_with(" Ra8#"));
+}